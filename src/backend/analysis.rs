@@ -0,0 +1,274 @@
+/// Difficulty metrics computed from the full state space of a level
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use super::notation::format_moves;
+use super::solver::{StateKey, search_start};
+use super::utils_backend::{Coordinate, Index, OUT_OF_BOUND, PlayerInput, TextureType};
+use super::{Board, GameState};
+
+// Weights of the individual metrics in the difficulty score
+const LENGTH_WEIGHT: f64 = 1.0;
+const COUNTER_INTUITIVE_WEIGHT: f64 = 2.0;
+const DEAD_END_WEIGHT: f64 = 5.0;
+const STATE_SPACE_WEIGHT: f64 = 0.5;
+const BRANCHING_WEIGHT: f64 = 1.0;
+const SOLUTION_COUNT_WEIGHT: f64 = 1.0;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LevelAnalysis {
+    pub solvable: bool,
+    /// False if the exploration stopped at the state limit, in which case the metrics only describe the
+    /// explored positions and can be off in either direction
+    pub complete: bool,
+    pub optimal_length: Option<usize>,
+    /// Number of distinct input sequences of optimal length that win the level
+    pub optimal_solutions: u64,
+    pub state_space_size: usize,
+    /// Average number of distinct positions reachable in one input along the reported solution
+    pub branching_factor: f64,
+    /// Share of the explored running positions from which no explored won position can be reached, exact
+    /// only when the exploration is complete
+    pub dead_end_ratio: f64,
+    /// Inputs of the reported solution that move the agents further away from the goals
+    pub counter_intuitive_moves: usize,
+    pub difficulty_score: Option<f64>,
+    /// One optimal solution in compact notation
+    pub solution: String,
+}
+
+struct Node {
+    depth: usize,
+    state: GameState,
+    /// One entry per input that leads to a different position, so positions reachable in several ways repeat
+    successors: Vec<usize>,
+    parent: Option<(usize, PlayerInput)>,
+    goal_distance: u32,
+}
+
+/// Sum over all living agents of the Manhattan distance to the closest goal block
+fn goal_distance(board: &Board) -> u32 {
+    let mut goals: Vec<Coordinate> = vec![];
+    for x in 0..board.x_size {
+        for y in 0..board.y_size {
+            if let TextureType::Goal(_) = board.read_block(Coordinate { x, y }).get_texture() {
+                goals.push(Coordinate { x, y });
+            }
+        }
+    }
+
+    let mut total: u32 = 0;
+    for position in &board.agent_positions {
+        if *position == OUT_OF_BOUND {
+            continue;
+        }
+        let closest: Option<Index> = goals
+            .iter()
+            .map(|goal| (goal.x - position.x).abs() + (goal.y - position.y).abs())
+            .min();
+        total += closest.unwrap_or(0) as u32;
+    }
+    total
+}
+
+/// Explores up to max_states positions of the level and derives the difficulty metrics from them
+pub fn analyze_level(board: &Board, max_states: usize) -> LevelAnalysis {
+    let start: Board = search_start(board);
+
+    let mut ids: HashMap<StateKey, usize> = HashMap::new();
+    let mut nodes: Vec<Node> = vec![];
+    let mut queue: VecDeque<(usize, Board)> = VecDeque::new();
+    let mut complete: bool = true;
+
+    ids.insert(start.state_key(), 0);
+    nodes.push(Node {
+        depth: 0,
+        state: start.game_state,
+        successors: vec![],
+        parent: None,
+        goal_distance: goal_distance(&start),
+    });
+    queue.push_back((0, start));
+
+    while let Some((id, current)) = queue.pop_front() {
+        if current.game_state != GameState::Running {
+            continue;
+        }
+        let current_key: StateKey = current.state_key();
+
+        for input in current.legal_inputs() {
+            let mut next: Board = current.clone();
+            next.apply_input(input);
+
+            let key: StateKey = next.state_key();
+            if key == current_key {
+                continue;
+            }
+
+            let next_id: usize = match ids.get(&key) {
+                Some(existing) => *existing,
+                None => {
+                    if nodes.len() >= max_states {
+                        complete = false;
+                        continue;
+                    }
+                    let new_id: usize = nodes.len();
+                    ids.insert(key, new_id);
+                    nodes.push(Node {
+                        depth: nodes[id].depth + 1,
+                        state: next.game_state,
+                        successors: vec![],
+                        parent: Some((id, input)),
+                        goal_distance: goal_distance(&next),
+                    });
+                    queue.push_back((new_id, next));
+                    new_id
+                }
+            };
+            nodes[id].successors.push(next_id);
+        }
+    }
+
+    summarize(&nodes, complete)
+}
+
+fn summarize(nodes: &[Node], complete: bool) -> LevelAnalysis {
+    // Nodes are numbered in breadth first order, so every shortest path visits them in increasing order
+    let mut paths: Vec<u64> = vec![0; nodes.len()];
+    paths[0] = 1;
+    for (id, node) in nodes.iter().enumerate() {
+        for successor in &node.successors {
+            if nodes[*successor].depth == node.depth + 1 {
+                paths[*successor] = paths[*successor].saturating_add(paths[id]);
+            }
+        }
+    }
+
+    let optimal_length: Option<usize> = nodes
+        .iter()
+        .filter(|node| node.state == GameState::Won)
+        .map(|node| node.depth)
+        .min();
+
+    let mut optimal_solutions: u64 = 0;
+    let mut final_node: Option<usize> = None;
+    if let Some(length) = optimal_length {
+        for (id, node) in nodes.iter().enumerate() {
+            if node.state == GameState::Won && node.depth == length {
+                optimal_solutions = optimal_solutions.saturating_add(paths[id]);
+                final_node.get_or_insert(id);
+            }
+        }
+    }
+
+    // Walk backwards from all won positions to find the positions that can still be won
+    let mut predecessors: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+    for (id, node) in nodes.iter().enumerate() {
+        for successor in &node.successors {
+            predecessors[*successor].push(id);
+        }
+    }
+    let mut can_win: Vec<bool> = nodes
+        .iter()
+        .map(|node| node.state == GameState::Won)
+        .collect();
+    let mut queue: VecDeque<usize> = (0..nodes.len()).filter(|id| can_win[*id]).collect();
+    while let Some(id) = queue.pop_front() {
+        for predecessor in &predecessors[id] {
+            if !can_win[*predecessor] {
+                can_win[*predecessor] = true;
+                queue.push_back(*predecessor);
+            }
+        }
+    }
+
+    let running: Vec<usize> = (0..nodes.len())
+        .filter(|id| nodes[*id].state == GameState::Running)
+        .collect();
+    let dead_ends: usize = running.iter().filter(|id| !can_win[**id]).count();
+    let dead_end_ratio: f64 = if running.is_empty() {
+        0.0
+    } else {
+        dead_ends as f64 / running.len() as f64
+    };
+
+    let mut path: Vec<usize> = vec![];
+    let mut solution: Vec<PlayerInput> = vec![];
+    let mut cursor: Option<usize> = final_node;
+    while let Some(id) = cursor {
+        path.push(id);
+        cursor = nodes[id].parent.map(|(parent, input)| {
+            solution.push(input);
+            parent
+        });
+    }
+    path.reverse();
+    solution.reverse();
+
+    let mut branching_factor: f64 = 0.0;
+    let mut counter_intuitive_moves: usize = 0;
+    if path.len() > 1 {
+        let mut total_branches: usize = 0;
+        for step in path.windows(2) {
+            let distinct: HashSet<&usize> = nodes[step[0]].successors.iter().collect();
+            total_branches += distinct.len();
+            if nodes[step[1]].goal_distance > nodes[step[0]].goal_distance {
+                counter_intuitive_moves += 1;
+            }
+        }
+        branching_factor = total_branches as f64 / (path.len() - 1) as f64;
+    }
+
+    let difficulty_score: Option<f64> = optimal_length.map(|length| {
+        LENGTH_WEIGHT * length as f64
+            + COUNTER_INTUITIVE_WEIGHT * counter_intuitive_moves as f64
+            + DEAD_END_WEIGHT * dead_end_ratio
+            + STATE_SPACE_WEIGHT * (nodes.len() as f64).log2()
+            + BRANCHING_WEIGHT * branching_factor.max(1.0).log2()
+            - SOLUTION_COUNT_WEIGHT * (optimal_solutions.max(1) as f64).log2()
+    });
+
+    LevelAnalysis {
+        solvable: optimal_length.is_some(),
+        complete,
+        optimal_length,
+        optimal_solutions,
+        state_space_size: nodes.len(),
+        branching_factor,
+        dead_end_ratio,
+        counter_intuitive_moves,
+        difficulty_score,
+        solution: format_moves(&solution),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::level_file::LevelFile;
+
+    /// Agent 0 walks right twice to the goal, and one agent may fall off the board on the way
+    const TWO_AGENTS: &str = "format_version = 2\nx_size = 3\ny_size = 2\nnum_agents = 2\n\
+        num_agents_must_finish = 1\nvictory_conditions.BlocksSatisfied = 1\n\
+        agent.0.start = [0, 0]\nagent.1.start = [0, 1]\n\
+        block.0.0 = { type = \"basic_block\" }\nblock.0.1 = { type = \"basic_block\" }\n\
+        block.2.0 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n";
+
+    #[test]
+    fn counts_dead_ends_among_running_positions() {
+        let board: Board =
+            Board::from_level_file(&TWO_AGENTS.parse::<LevelFile>().unwrap()).unwrap();
+        let analysis: LevelAnalysis = analyze_level(&board, 1000);
+        assert!(analysis.complete && analysis.solvable);
+        assert_eq!(analysis.optimal_length, Some(2));
+        assert_eq!(analysis.state_space_size, 22);
+        // 13 of the positions are running, in 2 of them one agent fell off and the other is stranded on
+        // the bottom left block
+        assert_eq!(analysis.dead_end_ratio, 2.0 / 13.0);
+
+        let partial: LevelAnalysis = analyze_level(&board, 3);
+        assert!(!partial.complete && !partial.solvable);
+        assert_eq!(partial.state_space_size, 3);
+    }
+}
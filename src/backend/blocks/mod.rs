@@ -2,13 +2,16 @@ use std::collections::HashSet;
 
 use toml::{Table, Value};
 
+use super::level_file::BlockSpec;
+
 /// Basic blocks used by the sliding game
 use super::utils_backend::{
     AgentID, DestructionResult, Direction, HitResult, ProgressUpdates, SlideType, StatusUpdate,
     TextureType,
 };
 
-pub trait Block {
+/// Blocks are Send and Sync so that boards can be kept in a Bevy resource
+pub trait Block: Send + Sync {
     fn can_enter(&self, _direction: Direction) -> bool {
         false
     }
@@ -54,6 +57,9 @@ pub trait Block {
 
     /// Turns a block into a table such that feeding the table to Block_factory produces the block again
     fn to_table(&self) -> Table;
+
+    /// Creates an independent copy of the block, including the agents currently on it
+    fn clone_box(&self) -> Box<dyn Block>;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -73,6 +79,10 @@ impl Block for Air {
         ret.insert(String::from("type"), Value::String(String::from("air")));
         ret
     }
+
+    fn clone_box(&self) -> Box<dyn Block> {
+        Box::new(self.clone())
+    }
 }
 
 impl Air {
@@ -148,9 +158,9 @@ impl Block for BasicBlock {
         }
 
         if self.passable {
-            return TextureType::BasicBlock;
+            TextureType::BasicBlock
         } else {
-            return TextureType::BasicImpassable;
+            TextureType::BasicImpassable
         }
     }
 
@@ -167,10 +177,10 @@ impl Block for BasicBlock {
         match self.default_slide {
             SlideType::FastSlide => (),
             SlideType::SlowSlide(i) => {
-                tags.insert(String::from("default_slide"), Value::Integer(i as i64));
+                tags.insert(String::from("slide"), Value::Integer(i as i64));
             }
             SlideType::NoSlide => {
-                tags.insert(String::from("default_slide"), Value::Integer(0));
+                tags.insert(String::from("slide"), Value::Integer(0));
             }
         };
         if self.num_goal_agents > 0 {
@@ -184,13 +194,16 @@ impl Block for BasicBlock {
         }
         ret
     }
+
+    fn clone_box(&self) -> Box<dyn Block> {
+        Box::new(self.clone())
+    }
 }
 
 impl BasicBlock {
-    #[allow(dead_code)]
     pub fn new(
         passable: bool,
-        starting_agents: &Vec<AgentID>,
+        starting_agents: &[AgentID],
         default_slide: SlideType,
         num_goal_agents: u8,
     ) -> Self {
@@ -201,65 +214,12 @@ impl BasicBlock {
             num_goal_agents,
         }
     }
-
-    pub fn from_table(block_table: &Table) -> Result<Box<dyn Block>, String> {
-        let mut block: BasicBlock = BasicBlock {
-            passable: true,
-            agents: HashSet::new(),
-            default_slide: SlideType::FastSlide,
-            num_goal_agents: 0,
-        };
-        match block_table.get("tags") {
-            None => return Ok(Box::new(block)),
-            Some(tags_wrapped) => {
-                let tags: &Table;
-                match tags_wrapped {
-                    Value::Table(t) => tags = t,
-                    _ => return Err(String::from("Missing tags block")),
-                }
-
-                if let Some(wrapped) = tags.get(&String::from("passable")) {
-                    if let Value::Boolean(val) = wrapped {
-                        print!("Placing block with passable value: {}\n", val);
-                        block.passable = *val;
-                    }
-                }
-                if let Some(wrapped) = tags.get(&String::from("num_goal_agents")) {
-                    if let Value::Integer(val) = wrapped {
-                        block.num_goal_agents = *val as u8;
-                    }
-                }
-                if let Some(wrapped) = tags.get(&String::from("slide")) {
-                    match wrapped {
-                        Value::String(s) if s.eq("fast") => {
-                            block.default_slide = SlideType::FastSlide
-                        }
-                        Value::Integer(i) => {
-                            if *i == 0 {
-                                block.default_slide = SlideType::NoSlide;
-                            } else {
-                                block.default_slide = SlideType::SlowSlide(*i as u8);
-                            }
-                        }
-                        _ => return Err(String::from("Block movement type not recognized")),
-                    }
-                }
-                Ok(Box::new(block))
-            }
-        }
-    }
 }
 
+/// Builds the block described by a table, as written by Block::to_table or found in a level file
 pub fn block_factory(block_table: &Table) -> Result<Box<dyn Block>, String> {
-    match block_table.get(&String::from("type")) {
-        None => Err(String::from("Block type not specified")),
-        Some(block_type) => match block_type {
-            Value::String(s) if s.eq("basic_block") => match BasicBlock::from_table(block_table) {
-                Ok(block) => Ok(block),
-                Err(msg) => Err(msg),
-            },
-            Value::String(s) if s.eq("air") => Ok(Box::new(Air::new())),
-            _ => Err(String::from("Block type not recognized")),
-        },
+    match BlockSpec::from_table(block_table) {
+        Ok(spec) => spec.build(),
+        Err(e) => Err(e.to_string()),
     }
 }
@@ -0,0 +1,747 @@
+/// Editing of levels, shared by the level editors of the frontends
+///
+/// The level is kept as a LevelFile, so that it is saved in the usual level format and can be played by
+/// building a board from it at any time. Every edit can be undone and redone, and the warnings point out
+/// levels that cannot be played or won.
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::Path;
+
+use toml::Table;
+
+use super::io::{level_to_string, read_level_file, write_atomic};
+use super::level_file::{
+    AgentSpec, BlockSpec, BlockTags, BlockType, GoalSpec, LevelFile, LevelMeta,
+};
+use super::migration::CURRENT_FORMAT_VERSION;
+use super::solver::search_start;
+use super::utils_backend::{AgentID, Coordinate, Index, OUT_OF_BOUND, TextureType};
+use super::{Board, GameState};
+
+pub const MAX_LEVEL_SIZE: Index = 32;
+/// The victory condition counting the goals that have exactly as many agents as they need
+pub const GOALS_CONDITION: &str = "BlocksSatisfied";
+/// Edits that can be undone, older ones are forgotten
+const MAX_UNDO_STEPS: usize = 200;
+const NEW_LEVEL_SIZE: (Index, Index) = (7, 5);
+/// Positions explored when looking for goals that cannot be reached, bigger levels are not checked for them
+const CHECK_MAX_STATES: usize = 5_000;
+
+/// The kinds of block the editors paint with, all but air are basic blocks with different tags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteBlock {
+    Air,
+    Block,
+    Wall,
+    Goal,
+}
+
+impl PaletteBlock {
+    pub const ALL: [PaletteBlock; 4] = [
+        PaletteBlock::Air,
+        PaletteBlock::Block,
+        PaletteBlock::Wall,
+        PaletteBlock::Goal,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteBlock::Air => "Air",
+            PaletteBlock::Block => "Block",
+            PaletteBlock::Wall => "Wall",
+            PaletteBlock::Goal => "Goal",
+        }
+    }
+
+    /// The block this paints, None for air
+    pub fn spec(self) -> Option<BlockSpec> {
+        let tags: Option<BlockTags> = match self {
+            PaletteBlock::Air => return None,
+            PaletteBlock::Block => None,
+            PaletteBlock::Wall => Some(BlockTags {
+                passable: Some(false),
+                ..BlockTags::default()
+            }),
+            PaletteBlock::Goal => Some(BlockTags {
+                num_goal_agents: Some(1),
+                ..BlockTags::default()
+            }),
+        };
+        Some(BlockSpec {
+            block_type: BlockType::BasicBlock,
+            tags,
+        })
+    }
+
+    /// The palette entry closest to a block, goals win over walls
+    pub fn of(spec: Option<&BlockSpec>) -> Self {
+        let Some(spec) = spec.filter(|spec| spec.block_type != BlockType::Air) else {
+            return PaletteBlock::Air;
+        };
+        let tags: BlockTags = spec.tags.clone().unwrap_or_default();
+        if tags.num_goal_agents.unwrap_or(0) > 0 {
+            PaletteBlock::Goal
+        } else if tags.passable == Some(false) {
+            PaletteBlock::Wall
+        } else {
+            PaletteBlock::Block
+        }
+    }
+}
+
+/// Whether agents can start on the block
+pub fn holds_agents(spec: Option<&BlockSpec>) -> bool {
+    spec.is_some_and(|spec| {
+        spec.block_type == BlockType::BasicBlock
+            && spec
+                .tags
+                .as_ref()
+                .and_then(|tags| tags.passable)
+                .unwrap_or(true)
+    })
+}
+
+/// How the block looks on the board, like the texture of the block built from it
+pub fn block_texture(spec: Option<&BlockSpec>) -> TextureType {
+    match (
+        PaletteBlock::of(spec),
+        spec.and_then(|spec| spec.tags.as_ref()),
+    ) {
+        (PaletteBlock::Air, _) => TextureType::None,
+        (PaletteBlock::Goal, Some(tags)) => TextureType::Goal(tags.num_goal_agents.unwrap_or(0)),
+        (PaletteBlock::Wall, _) => TextureType::BasicImpassable,
+        _ => TextureType::BasicBlock,
+    }
+}
+
+fn block_at(level: &LevelFile, coordinate: Coordinate) -> Option<&BlockSpec> {
+    level
+        .block
+        .get(&coordinate.x.to_string())?
+        .get(&coordinate.y.to_string())
+}
+
+fn set_block_at(level: &mut LevelFile, coordinate: Coordinate, spec: Option<BlockSpec>) {
+    let x: String = coordinate.x.to_string();
+    let row = level.block.entry(x.clone()).or_default();
+    match spec {
+        Some(spec) if spec.block_type != BlockType::Air => {
+            row.insert(coordinate.y.to_string(), spec);
+        }
+        _ => {
+            row.remove(&coordinate.y.to_string());
+        }
+    }
+    if row.is_empty() {
+        level.block.remove(&x);
+    }
+}
+
+fn agent_start(level: &LevelFile, agent: AgentID) -> Option<Coordinate> {
+    level.agent.get(&agent.to_string()).map(|spec| Coordinate {
+        x: spec.start[0],
+        y: spec.start[1],
+    })
+}
+
+fn agents_at(level: &LevelFile, coordinate: Coordinate) -> Vec<AgentID> {
+    (0..level.num_agents)
+        .filter(|agent| agent_start(level, *agent) == Some(coordinate))
+        .collect()
+}
+
+/// Fails if the block cannot hold the agents starting on its square
+fn check_agents_fit(level: &LevelFile, coordinate: Coordinate) -> Result<(), String> {
+    match agents_at(level, coordinate).first() {
+        Some(agent) if !holds_agents(block_at(level, coordinate)) => Err(format!(
+            "Agent {} starts there, move it to another square first",
+            agent
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Number of goal blocks with an agent on them
+fn occupied_goals(board: &Board) -> usize {
+    let goals: HashSet<Coordinate> = (0..board.get_num_agents())
+        .map(|agent| board.get_agent_position(agent))
+        .filter(|position| {
+            *position != OUT_OF_BOUND
+                && matches!(
+                    board.read_block(*position).get_texture(),
+                    TextureType::Goal(_)
+                )
+        })
+        .collect();
+    goals.len()
+}
+
+/// The most goals agents can stand on at the same time and whether the level can be won, None if there
+/// are too many positions to find out
+fn explore_goals(board: &Board) -> Option<(usize, bool)> {
+    let start: Board = search_start(board);
+    let mut visited: HashSet<_> = HashSet::from([start.state_key()]);
+    let mut queue: VecDeque<Board> = VecDeque::from([start]);
+    let mut most: usize = 0;
+    let mut won: bool = false;
+
+    while let Some(current) = queue.pop_front() {
+        match current.get_game_state() {
+            GameState::Lost => continue,
+            GameState::Won => won = true,
+            GameState::Running => (),
+        }
+        most = most.max(occupied_goals(&current));
+        for input in current.legal_inputs() {
+            let mut next: Board = current.clone();
+            next.apply_input(input);
+            if !visited.insert(next.state_key()) {
+                continue;
+            }
+            if visited.len() > CHECK_MAX_STATES {
+                return None;
+            }
+            queue.push_back(next);
+        }
+    }
+    Some((most, won))
+}
+
+pub struct LevelEditor {
+    level: LevelFile,
+    undo_stack: Vec<LevelFile>,
+    redo_stack: Vec<LevelFile>,
+    modified: bool,
+}
+
+impl Default for LevelEditor {
+    fn default() -> Self {
+        LevelEditor::new()
+    }
+}
+
+impl LevelEditor {
+    /// A small level to start from: a floor of blocks with an agent in one corner and a goal in the other
+    pub fn new() -> Self {
+        let (x_size, y_size) = NEW_LEVEL_SIZE;
+        let mut level: LevelFile = LevelFile {
+            format_version: CURRENT_FORMAT_VERSION,
+            x_size,
+            y_size,
+            num_agents: 1,
+            num_agents_must_finish: None,
+            victory_conditions: BTreeMap::from([(
+                String::from(GOALS_CONDITION),
+                GoalSpec::Exactly(1),
+            )]),
+            agent: BTreeMap::from([(String::from("0"), AgentSpec { start: [0, 0] })]),
+            block: BTreeMap::new(),
+            meta: LevelMeta::default(),
+        };
+        for x in 0..x_size {
+            for y in 0..y_size {
+                set_block_at(&mut level, Coordinate { x, y }, PaletteBlock::Block.spec());
+            }
+        }
+        set_block_at(
+            &mut level,
+            Coordinate {
+                x: x_size - 1,
+                y: y_size - 1,
+            },
+            PaletteBlock::Goal.spec(),
+        );
+
+        LevelEditor {
+            level,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            modified: false,
+        }
+    }
+
+    /// Edits a level, which has to be valid so that every block and agent has a square, and have an agent to
+    /// select
+    pub fn open(level: LevelFile) -> Result<Self, String> {
+        if level.num_agents == 0 {
+            return Err(String::from("A level needs at least one agent"));
+        }
+        if let Err(e) = Board::from_level_file(&level) {
+            return Err(e.to_string());
+        }
+
+        // Keys like 01 are valid but would not be found by the editor, and air needs no entry
+        let mut normalized: LevelFile = LevelFile {
+            block: BTreeMap::new(),
+            agent: BTreeMap::new(),
+            ..level.clone()
+        };
+        for (x, row) in &level.block {
+            for (y, spec) in row {
+                if let (Ok(x), Ok(y)) = (x.parse::<Index>(), y.parse::<Index>()) {
+                    set_block_at(&mut normalized, Coordinate { x, y }, Some(spec.clone()));
+                }
+            }
+        }
+        for (agent, spec) in &level.agent {
+            if let Ok(agent) = agent.parse::<AgentID>() {
+                normalized.agent.insert(agent.to_string(), spec.clone());
+            }
+        }
+
+        Ok(LevelEditor {
+            level: normalized,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            modified: false,
+        })
+    }
+
+    /// Opens a level file in any of the supported layouts
+    pub fn load(path: &str) -> Result<Self, String> {
+        match read_level_file(path) {
+            Ok(level) => LevelEditor::open(level),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn level(&self) -> &LevelFile {
+        &self.level
+    }
+
+    /// Whether there are edits that are not saved
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn size(&self) -> (Index, Index) {
+        (self.level.x_size, self.level.y_size)
+    }
+
+    pub fn in_bounds(&self, coordinate: Coordinate) -> bool {
+        coordinate.x >= 0
+            && coordinate.y >= 0
+            && coordinate.x < self.level.x_size
+            && coordinate.y < self.level.y_size
+    }
+
+    /// The block on a square, None for air
+    pub fn block(&self, coordinate: Coordinate) -> Option<&BlockSpec> {
+        block_at(&self.level, coordinate)
+    }
+
+    pub fn agent_start(&self, agent: AgentID) -> Option<Coordinate> {
+        agent_start(&self.level, agent)
+    }
+
+    pub fn agents_at(&self, coordinate: Coordinate) -> Vec<AgentID> {
+        agents_at(&self.level, coordinate)
+    }
+
+    /// Number of goals that must be satisfied to win
+    pub fn goals_to_satisfy(&self) -> u8 {
+        self.level
+            .victory_conditions
+            .get(GOALS_CONDITION)
+            .map_or(0, GoalSpec::num)
+    }
+
+    /// Applies a change to a copy of the level and keeps it if it worked, returns whether the level changed.
+    /// A merged change is undone together with the edit before it, e.g. the squares painted in one stroke.
+    fn edit(
+        &mut self,
+        merge: bool,
+        change: impl FnOnce(&mut LevelFile) -> Result<(), String>,
+    ) -> Result<bool, String> {
+        let mut level: LevelFile = self.level.clone();
+        change(&mut level)?;
+        if level == self.level {
+            return Ok(false);
+        }
+
+        let before: LevelFile = std::mem::replace(&mut self.level, level);
+        if !merge || self.undo_stack.is_empty() {
+            self.undo_stack.push(before);
+            if self.undo_stack.len() > MAX_UNDO_STEPS {
+                self.undo_stack.remove(0);
+            }
+        }
+        self.redo_stack.clear();
+        self.modified = true;
+        Ok(true)
+    }
+
+    /// An edit of its own for undo
+    fn change(
+        &mut self,
+        change: impl FnOnce(&mut LevelFile) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.edit(false, change)?;
+        Ok(())
+    }
+
+    /// Paints a square, returns whether that changed it. Merged paints are undone with the edit before.
+    pub fn paint(
+        &mut self,
+        coordinate: Coordinate,
+        block: PaletteBlock,
+        merge: bool,
+    ) -> Result<bool, String> {
+        if !self.in_bounds(coordinate) {
+            return Err(String::from("The square is not on the board"));
+        }
+        self.edit(merge, |level| {
+            set_block_at(level, coordinate, block.spec());
+            check_agents_fit(level, coordinate)
+        })
+    }
+
+    /// Replaces the tags of a block, tags that are all unset are left out of the level
+    pub fn set_tags(&mut self, coordinate: Coordinate, tags: BlockTags) -> Result<(), String> {
+        let Some(spec) = self.block(coordinate) else {
+            return Err(String::from("Air has no tags, paint a block first"));
+        };
+        let spec: BlockSpec = BlockSpec {
+            block_type: spec.block_type,
+            tags: if tags == BlockTags::default() {
+                None
+            } else {
+                Some(tags)
+            },
+        };
+        self.change(|level| {
+            set_block_at(level, coordinate, Some(spec));
+            check_agents_fit(level, coordinate)
+        })
+    }
+
+    /// Changes the size of the board, blocks outside of it are removed but agents have to be moved first
+    pub fn resize(&mut self, x_size: Index, y_size: Index) -> Result<(), String> {
+        if !(1..=MAX_LEVEL_SIZE).contains(&x_size) || !(1..=MAX_LEVEL_SIZE).contains(&y_size) {
+            return Err(format!(
+                "The board must be from 1x1 to {}x{}",
+                MAX_LEVEL_SIZE, MAX_LEVEL_SIZE
+            ));
+        }
+        self.change(|level| {
+            for agent in 0..level.num_agents {
+                if let Some(start) = agent_start(level, agent)
+                    && (start.x >= x_size || start.y >= y_size)
+                {
+                    return Err(format!(
+                        "Agent {} would be left outside, move it first",
+                        agent
+                    ));
+                }
+            }
+            level.x_size = x_size;
+            level.y_size = y_size;
+            for row in level.block.values_mut() {
+                row.retain(|y, _| y.parse::<Index>().is_ok_and(|y| y < y_size));
+            }
+            level
+                .block
+                .retain(|x, row| !row.is_empty() && x.parse::<Index>().is_ok_and(|x| x < x_size));
+            Ok(())
+        })
+    }
+
+    /// Moves the start of an agent, agents only start on passable blocks
+    pub fn place_agent(&mut self, agent: AgentID, coordinate: Coordinate) -> Result<(), String> {
+        if agent >= self.level.num_agents {
+            return Err(format!("There is no agent {}", agent));
+        }
+        if !self.in_bounds(coordinate) || !holds_agents(self.block(coordinate)) {
+            return Err(String::from("Agents can only start on passable blocks"));
+        }
+        self.change(|level| {
+            level.agent.insert(
+                agent.to_string(),
+                AgentSpec {
+                    start: [coordinate.x, coordinate.y],
+                },
+            );
+            Ok(())
+        })
+    }
+
+    /// Adds an agent starting on the square and returns its ID
+    pub fn add_agent(&mut self, coordinate: Coordinate) -> Result<AgentID, String> {
+        let agent: AgentID = self.level.num_agents;
+        if agent == AgentID::MAX {
+            return Err(String::from("There are as many agents as there can be"));
+        }
+        if !self.in_bounds(coordinate) || !holds_agents(self.block(coordinate)) {
+            return Err(String::from("Agents can only start on passable blocks"));
+        }
+        self.change(|level| {
+            level.num_agents += 1;
+            level.agent.insert(
+                agent.to_string(),
+                AgentSpec {
+                    start: [coordinate.x, coordinate.y],
+                },
+            );
+            Ok(())
+        })?;
+        Ok(agent)
+    }
+
+    /// Removes an agent, the agents after it move down one ID
+    pub fn remove_agent(&mut self, agent: AgentID) -> Result<(), String> {
+        if agent >= self.level.num_agents {
+            return Err(format!("There is no agent {}", agent));
+        }
+        if self.level.num_agents == 1 {
+            return Err(String::from("A level needs at least one agent"));
+        }
+        self.change(|level| {
+            let starts: Vec<AgentSpec> = (0..level.num_agents)
+                .filter(|other| *other != agent)
+                .filter_map(|other| level.agent.get(&other.to_string()).cloned())
+                .collect();
+            level.num_agents -= 1;
+            level.agent = starts
+                .into_iter()
+                .enumerate()
+                .map(|(id, spec)| (id.to_string(), spec))
+                .collect();
+            if let Some(must_finish) = level.num_agents_must_finish {
+                level.num_agents_must_finish = Some(must_finish.min(level.num_agents));
+            }
+            Ok(())
+        })
+    }
+
+    pub fn set_goals_to_satisfy(&mut self, goals: u8) -> Result<(), String> {
+        self.change(|level| {
+            // A bound stays a bound, only its value changes
+            let goal: GoalSpec = level
+                .victory_conditions
+                .get(GOALS_CONDITION)
+                .map_or(GoalSpec::Exactly(goals), |goal| goal.with_num(goals));
+            level
+                .victory_conditions
+                .insert(String::from(GOALS_CONDITION), goal);
+            Ok(())
+        })
+    }
+
+    /// Sets how many agents have to survive, None for all of them
+    pub fn set_agents_must_finish(&mut self, agents: Option<AgentID>) -> Result<(), String> {
+        if agents.is_some_and(|agents| agents > self.level.num_agents) {
+            return Err(String::from(
+                "More agents cannot finish than there are agents",
+            ));
+        }
+        self.change(|level| {
+            level.num_agents_must_finish = agents;
+            Ok(())
+        })
+    }
+
+    pub fn set_meta(&mut self, meta: LevelMeta) -> Result<(), String> {
+        self.change(|level| {
+            level.meta = meta;
+            Ok(())
+        })
+    }
+
+    /// Goes back one edit, returns false when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(level) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack
+            .push(std::mem::replace(&mut self.level, level));
+        self.modified = true;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(level) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack
+            .push(std::mem::replace(&mut self.level, level));
+        self.modified = true;
+        true
+    }
+
+    /// The board for playing the level as it is now
+    pub fn to_board(&self) -> Result<Board, String> {
+        match Board::from_level_file(&self.level) {
+            Ok(board) => Ok(board),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// The level file in the canonical layout of the level tools
+    pub fn to_text(&self) -> Result<String, String> {
+        match Table::try_from(&self.level) {
+            Ok(table) => Ok(level_to_string(&table)),
+            Err(e) => Err(format!("Could not serialize the level: {}", e)),
+        }
+    }
+
+    /// Problems of the level that do not stop it from being saved, e.g. goals that need more agents than
+    /// there are or that no agent can reach
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = vec![];
+        let agents: usize = self.level.num_agents as usize;
+        let to_satisfy: usize = self.goals_to_satisfy() as usize;
+
+        let mut goals: Vec<(Coordinate, BlockTags)> = vec![];
+        for x in 0..self.level.x_size {
+            for y in 0..self.level.y_size {
+                let coordinate: Coordinate = Coordinate { x, y };
+                if let Some(tags) = self.block(coordinate).and_then(|spec| spec.tags.as_ref())
+                    && tags.num_goal_agents.unwrap_or(0) > 0
+                {
+                    goals.push((coordinate, tags.clone()));
+                }
+            }
+        }
+
+        if to_satisfy == 0 {
+            warnings.push(String::from(
+                "No goal has to be satisfied, the level is won at the start",
+            ));
+        } else if to_satisfy > goals.len() {
+            warnings.push(format!(
+                "{} goals have to be satisfied but the level has {}",
+                to_satisfy,
+                goals.len()
+            ));
+        }
+        for (coordinate, tags) in &goals {
+            let needed: usize = tags.num_goal_agents.unwrap_or(0) as usize;
+            if tags.passable == Some(false) {
+                warnings.push(format!(
+                    "The goal at ({}, {}) is impassable, no agent can reach it",
+                    coordinate.x, coordinate.y
+                ));
+            } else if needed > agents {
+                warnings.push(format!(
+                    "The goal at ({}, {}) needs {} agents but the level has {}",
+                    coordinate.x, coordinate.y, needed, agents
+                ));
+            }
+        }
+        let mut needs: Vec<usize> = goals
+            .iter()
+            .map(|(_, tags)| tags.num_goal_agents.unwrap_or(0) as usize)
+            .collect();
+        needs.sort_unstable();
+        let fewest: usize = needs.iter().take(to_satisfy).sum();
+        if to_satisfy <= goals.len() && fewest > agents {
+            warnings.push(format!(
+                "Satisfying {} goals needs at least {} agents but the level has {}",
+                to_satisfy, fewest, agents
+            ));
+        }
+
+        // Searching only tells more when the counts above leave a chance to win
+        match self.to_board() {
+            Err(msg) => warnings.push(format!("The level cannot be played. {}", msg)),
+            Ok(board) if warnings.is_empty() => match explore_goals(&board) {
+                Some((0, _)) => warnings.push(String::from("No agent can reach a goal")),
+                Some((most, _)) if most < to_satisfy => warnings.push(format!(
+                    "Agents can only reach {} goals at the same time, {} have to be satisfied",
+                    most, to_satisfy
+                )),
+                Some((_, false)) => warnings.push(String::from("The level cannot be won")),
+                Some((_, true)) | None => (),
+            },
+            Ok(_) => (),
+        }
+        warnings
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        write_atomic(path, &self.to_text()?)?;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn edits_undo_and_survive_saving() {
+        let mut editor: LevelEditor = LevelEditor::new();
+        let corner: Coordinate = Coordinate { x: 0, y: 0 };
+        let middle: Coordinate = Coordinate { x: 3, y: 2 };
+
+        assert!(editor.paint(corner, PaletteBlock::Wall, false).is_err());
+        editor.paint(middle, PaletteBlock::Wall, false).unwrap();
+        editor.paint(middle, PaletteBlock::Air, true).unwrap();
+        assert_eq!(editor.add_agent(Coordinate { x: 1, y: 0 }), Ok(1));
+        editor.remove_agent(0).unwrap();
+        assert_eq!(editor.agent_start(0), Some(Coordinate { x: 1, y: 0 }));
+
+        assert!(editor.undo() && editor.undo());
+        assert_eq!(editor.level().num_agents, 1);
+        // Both squares of the stroke are undone together
+        assert!(editor.undo());
+        assert_eq!(PaletteBlock::of(editor.block(middle)), PaletteBlock::Block);
+        assert!(!editor.undo());
+        assert!(editor.redo());
+        assert_eq!(editor.block(middle), None);
+
+        assert!(editor.resize(1, 1).is_ok());
+        assert!(editor.resize(0, 3).is_err());
+        editor.undo();
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("editor_test_{}.toml", std::process::id()));
+        editor.save(&path).unwrap();
+        let loaded: LevelEditor = LevelEditor::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.level(), editor.level());
+        assert!(loaded.to_board().is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn levels_without_agents_are_not_opened() {
+        let mut level: LevelFile = LevelEditor::new().level().clone();
+        level.num_agents = 0;
+        level.agent.clear();
+        assert!(Board::from_level_file(&level).is_ok());
+        assert_eq!(
+            LevelEditor::open(level).err(),
+            Some(String::from("A level needs at least one agent"))
+        );
+    }
+
+    #[test]
+    fn warnings_point_out_goals_that_cannot_be_reached() {
+        let mut editor: LevelEditor = LevelEditor::new();
+        let goal: Coordinate = Coordinate { x: 2, y: 1 };
+        editor.resize(3, 2).unwrap();
+        editor.paint(goal, PaletteBlock::Goal, false).unwrap();
+        assert_eq!(editor.warnings(), Vec::<String>::new());
+
+        editor.set_goals_to_satisfy(2).unwrap();
+        assert_eq!(editor.warnings().len(), 1);
+        editor.undo();
+        // Only the block of the agent is left besides the goal, and every slide throws it off the board
+        for square in [(1, 0), (2, 0), (0, 1), (1, 1)] {
+            let square: Coordinate = Coordinate {
+                x: square.0,
+                y: square.1,
+            };
+            editor.paint(square, PaletteBlock::Air, true).unwrap();
+        }
+        assert_eq!(
+            editor.warnings(),
+            vec![String::from("No agent can reach a goal")]
+        );
+        let mut tags: BlockTags = editor.block(goal).unwrap().tags.clone().unwrap();
+        tags.num_goal_agents = Some(2);
+        editor.set_tags(goal, tags).unwrap();
+        assert!(editor.warnings()[0].contains("needs 2 agents"));
+    }
+}
@@ -0,0 +1,315 @@
+/// Procedural generation of levels with a solution length in a requested range
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use super::Board;
+use super::blocks::{BasicBlock, Block};
+use super::migration::{CURRENT_FORMAT_VERSION, FORMAT_VERSION_KEY};
+use super::solver::{SearchLimits, SearchResult, solve};
+use super::utils_backend::{Direction, Index, PlayerInput, SlideType};
+
+const DEFAULT_MAX_ATTEMPTS: usize = 1000;
+const DEFAULT_MAX_STATES: usize = 200_000;
+const MAX_SLOW_SLIDE_LENGTH: u64 = 3;
+
+/// How many blocks of each kind a generated level contains
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BlockMix {
+    /// Passable blocks that slide until stopped
+    pub basic: u16,
+    /// Passable blocks that slide a random number of squares
+    pub slow: u16,
+    /// Passable blocks that do not slide at all
+    pub fixed: u16,
+    /// Blocks agents can not enter
+    pub impassable: u16,
+    /// Passable blocks that slide until stopped and need agents placed on them
+    pub goals: u16,
+}
+
+impl BlockMix {
+    fn total(&self) -> u16 {
+        self.basic + self.slow + self.fixed + self.impassable + self.goals
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeneratorConfig {
+    pub x_size: Index,
+    pub y_size: Index,
+    pub num_agents: u8,
+    pub blocks: BlockMix,
+    pub min_solution_length: usize,
+    pub max_solution_length: usize,
+    pub seed: u64,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+    /// Limits the exhaustive search that checks each candidate
+    #[serde(default = "default_max_states")]
+    pub max_states: usize,
+}
+
+fn default_max_attempts() -> usize {
+    DEFAULT_MAX_ATTEMPTS
+}
+
+fn default_max_states() -> usize {
+    DEFAULT_MAX_STATES
+}
+
+pub struct GeneratedLevel {
+    /// The level in the same layout Board::from_file reads
+    pub level: Table,
+    /// One of the optimal solutions of the level
+    pub solution: Vec<PlayerInput>,
+    /// Number of candidates that were tried, including the accepted one
+    pub attempts: usize,
+}
+
+/// Small SplitMix64 generator so that a seed produces the same level on every platform and build
+struct LevelRng {
+    state: u64,
+}
+
+impl LevelRng {
+    fn new(seed: u64) -> Self {
+        LevelRng { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z: u64 = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in 0..bound
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j: usize = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+impl GeneratorConfig {
+    fn check(&self) -> Result<(), String> {
+        if self.x_size <= 0 || self.y_size <= 0 {
+            return Err(String::from("Board dimensions must be positive"));
+        }
+        if self.blocks.total() as i32 > self.x_size as i32 * self.y_size as i32 {
+            return Err(String::from(
+                "More blocks requested than the board has squares",
+            ));
+        }
+        if self.num_agents == 0 {
+            return Err(String::from("A level needs at least one agent"));
+        }
+        if self.blocks.basic + self.blocks.slow + self.blocks.fixed + self.blocks.goals == 0 {
+            return Err(String::from(
+                "Agents need at least one passable block to start on",
+            ));
+        }
+        if self.blocks.goals == 0 {
+            return Err(String::from("A level needs at least one goal block"));
+        }
+        if self.blocks.goals > self.num_agents as u16 {
+            return Err(String::from("Every goal block needs at least one agent"));
+        }
+        if self.min_solution_length > self.max_solution_length {
+            return Err(String::from(
+                "Minimum solution length is larger than the maximum solution length",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Generates levels from the seeded random generator until one has an optimal solution in the requested range
+pub fn generate_level(config: &GeneratorConfig) -> Result<GeneratedLevel, String> {
+    config.check()?;
+
+    let mut rng: LevelRng = LevelRng::new(config.seed);
+    let limits: SearchLimits = SearchLimits {
+        max_depth: config.max_solution_length,
+        max_states: config.max_states,
+    };
+
+    for attempt in 1..=config.max_attempts {
+        let level: Table = random_level(config, &mut rng);
+
+        let board: Board = match Board::from_table(&level) {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+
+        if let SearchResult::Solved(solution) = solve(&board, limits)
+            && solution.len() >= config.min_solution_length
+        {
+            return Ok(GeneratedLevel {
+                level,
+                solution,
+                attempts: attempt,
+            });
+        }
+    }
+
+    Err(format!(
+        "No level with an optimal solution between {} and {} moves found in {} attempts",
+        config.min_solution_length, config.max_solution_length, config.max_attempts
+    ))
+}
+
+fn random_level(config: &GeneratorConfig, rng: &mut LevelRng) -> Table {
+    let mut cells: Vec<(Index, Index)> = vec![];
+    for x in 0..config.x_size {
+        for y in 0..config.y_size {
+            cells.push((x, y));
+        }
+    }
+    rng.shuffle(&mut cells);
+
+    // Split the agents as evenly as possible between the goals
+    let mut goal_sizes: Vec<u8> = vec![0; config.blocks.goals as usize];
+    let num_goals: usize = goal_sizes.len();
+    for agent in 0..config.num_agents as usize {
+        goal_sizes[agent % num_goals] += 1;
+    }
+
+    let mut blocks: Vec<Box<dyn Block>> = vec![];
+    for _ in 0..config.blocks.basic {
+        blocks.push(Box::new(BasicBlock::new(
+            true,
+            &[],
+            SlideType::FastSlide,
+            0,
+        )));
+    }
+    for _ in 0..config.blocks.slow {
+        let length: u8 = (rng.below(MAX_SLOW_SLIDE_LENGTH) + 1) as u8;
+        blocks.push(Box::new(BasicBlock::new(
+            true,
+            &[],
+            SlideType::SlowSlide(length),
+            0,
+        )));
+    }
+    for _ in 0..config.blocks.fixed {
+        blocks.push(Box::new(BasicBlock::new(true, &[], SlideType::NoSlide, 0)));
+    }
+    for _ in 0..config.blocks.impassable {
+        blocks.push(Box::new(BasicBlock::new(false, &[], SlideType::NoSlide, 0)));
+    }
+    for size in &goal_sizes {
+        blocks.push(Box::new(BasicBlock::new(
+            true,
+            &[],
+            SlideType::FastSlide,
+            *size,
+        )));
+    }
+
+    let mut level: Table = Table::new();
+    level.insert(
+        String::from(FORMAT_VERSION_KEY),
+        Value::Integer(CURRENT_FORMAT_VERSION),
+    );
+    level.insert(String::from("x_size"), Value::Integer(config.x_size as i64));
+    level.insert(String::from("y_size"), Value::Integer(config.y_size as i64));
+    level.insert(
+        String::from("num_agents"),
+        Value::Integer(config.num_agents as i64),
+    );
+    level.insert(
+        String::from("num_agents_must_finish"),
+        Value::Integer(config.num_agents as i64),
+    );
+
+    let mut victory_conditions: Table = Table::new();
+    victory_conditions.insert(
+        String::from("BlocksSatisfied"),
+        Value::Integer(goal_sizes.len() as i64),
+    );
+    level.insert(
+        String::from("victory_conditions"),
+        Value::Table(victory_conditions),
+    );
+
+    let mut passable_cells: Vec<(Index, Index)> = vec![];
+    let mut block_section: Table = Table::new();
+    for (block, (x, y)) in blocks.iter().zip(cells.iter()) {
+        if block.can_enter(Direction::None) {
+            passable_cells.push((*x, *y));
+        }
+        let row: &mut Value = block_section
+            .entry(x.to_string())
+            .or_insert(Value::Table(Table::new()));
+        if let Value::Table(row_table) = row {
+            row_table.insert(y.to_string(), Value::Table(block.to_table()));
+        }
+    }
+    level.insert(String::from("block"), Value::Table(block_section));
+
+    let mut agent_section: Table = Table::new();
+    for agent in 0..config.num_agents {
+        let (x, y) = passable_cells[rng.below(passable_cells.len() as u64) as usize];
+        let mut agent_table: Table = Table::new();
+        agent_table.insert(
+            String::from("start"),
+            Value::Array(vec![Value::Integer(x as i64), Value::Integer(y as i64)]),
+        );
+        agent_section.insert(agent.to_string(), Value::Table(agent_table));
+    }
+    level.insert(String::from("agent"), Value::Table(agent_section));
+
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_give_the_same_level_with_a_solution_in_range() {
+        let config: GeneratorConfig = GeneratorConfig {
+            x_size: 4,
+            y_size: 4,
+            num_agents: 1,
+            blocks: BlockMix {
+                basic: 4,
+                goals: 1,
+                ..BlockMix::default()
+            },
+            min_solution_length: 2,
+            max_solution_length: 4,
+            seed: 7,
+            max_attempts: 200,
+            max_states: 20_000,
+        };
+        let first: GeneratedLevel = generate_level(&config).unwrap();
+        let second: GeneratedLevel = generate_level(&config).unwrap();
+        assert_eq!(first.level, second.level);
+        assert_eq!(first.solution, second.solution);
+        assert!((2..=4).contains(&first.solution.len()));
+
+        let board: Board = Board::from_table(&first.level).unwrap();
+        let limits: SearchLimits = SearchLimits {
+            max_depth: 4,
+            max_states: 20_000,
+        };
+        assert_eq!(solve(&board, limits), SearchResult::Solved(first.solution));
+
+        let impossible: GeneratorConfig = GeneratorConfig {
+            min_solution_length: 5,
+            max_solution_length: 4,
+            ..config
+        };
+        assert!(generate_level(&impossible).is_err());
+    }
+}
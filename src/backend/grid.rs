@@ -0,0 +1,504 @@
+/// Levels drawn as a grid of characters
+///
+/// Instead of one `[block.x.y]` table per block, a level can contain a `grid` string with one line per row
+/// (y grows downwards, x to the right) and a `[legend]` that maps every character to a block table. A legend
+/// entry may list the agents that start on the block, e.g. `S = { type = "basic_block", agents = [0, 1] }`.
+/// `.` and space are air and `#` is a basic block unless the legend says otherwise.
+///
+/// The same can be written as a plain `.txt` file: the grid lines, a line `---`, and the rest of the level
+/// (legend, victory conditions, ...) as TOML.
+use toml::{Table, Value};
+
+use super::io::{coordinate_to_value, toml_key};
+use super::utils_backend::{AgentID, Coordinate, Index};
+
+pub const GRID_TEXT_EXTENSION: &str = ".txt";
+const GRID_TEXT_SEPARATOR: &str = "---";
+
+// Characters handed out to blocks when a level is turned into a grid, after the preferred ones
+const SYMBOL_POOL: &str = "ABCDEFHIJKLMNOPQRTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789@$%&*+=?";
+
+fn default_legend(symbol: char) -> Option<Table> {
+    match symbol {
+        '.' | ' ' => Some(block_of_type("air")),
+        '#' => Some(block_of_type("basic_block")),
+        _ => None,
+    }
+}
+
+fn block_of_type(name: &str) -> Table {
+    let mut ret: Table = Table::new();
+    ret.insert(String::from("type"), Value::String(String::from(name)));
+    ret
+}
+
+fn is_air(block: &Table) -> bool {
+    matches!(block.get("type"), Some(Value::String(s)) if s == "air")
+}
+
+/// Whether a parsed level uses the grid layout instead of block tables
+pub fn is_grid_level(level: &Table) -> bool {
+    level.contains_key("grid")
+}
+
+/// Turns a level in the grid layout into the block table layout Board::from_table reads
+pub fn grid_to_level(level: &Table) -> Result<Table, String> {
+    let grid: &str = match level.get("grid") {
+        Some(Value::String(s)) => s,
+        Some(_) => return Err(String::from("grid must be a string")),
+        None => return Err(String::from("Level has no grid")),
+    };
+    if level.contains_key("block") || level.contains_key("agent") {
+        return Err(String::from(
+            "A level with a grid can not also have [block] or [agent] sections",
+        ));
+    }
+
+    let legend: Table = match level.get("legend") {
+        None => Table::new(),
+        Some(Value::Table(t)) => t.clone(),
+        Some(_) => return Err(String::from("legend must be a table")),
+    };
+    for symbol in legend.keys() {
+        if symbol.chars().count() != 1 {
+            return Err(format!(
+                "Legend key '{}' must be a single character",
+                symbol
+            ));
+        }
+    }
+
+    let rows: Vec<&str> = grid.lines().collect();
+    let y_size: usize = rows.len();
+    let x_size: usize = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut ret: Table = level.clone();
+    ret.remove("grid");
+    ret.remove("legend");
+    // Editors like to strip trailing spaces, so short rows are filled up with air and sizes may be given explicitly
+    for (key, size) in [("x_size", x_size), ("y_size", y_size)] {
+        match ret.get(key) {
+            None => {
+                ret.insert(String::from(key), Value::Integer(size as i64));
+            }
+            Some(Value::Integer(given)) if *given >= size as i64 => (),
+            Some(_) => {
+                return Err(format!(
+                    "{} must be an integer of at least {} to fit the grid",
+                    key, size
+                ));
+            }
+        }
+    }
+
+    let mut blocks: Table = Table::new();
+    let mut agent_starts: Vec<Option<Coordinate>> = vec![];
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, symbol) in row.chars().enumerate() {
+            let mut block: Table = match legend.get(&symbol.to_string()) {
+                Some(Value::Table(t)) => t.clone(),
+                Some(_) => return Err(format!("Legend entry '{}' must be a table", symbol)),
+                None => match default_legend(symbol) {
+                    Some(t) => t,
+                    None => {
+                        return Err(format!(
+                            "Character '{}' in row {} of the grid is not in the legend",
+                            symbol, y
+                        ));
+                    }
+                },
+            };
+            let coordinate: Coordinate = Coordinate {
+                x: x as Index,
+                y: y as Index,
+            };
+
+            if let Some(agents) = block.remove("agents") {
+                for agent in agent_list(&agents, symbol)? {
+                    let index: usize = agent as usize;
+                    if agent_starts.len() <= index {
+                        agent_starts.resize(index + 1, None);
+                    }
+                    if agent_starts[index].is_some() {
+                        return Err(format!(
+                            "Agent {} starts on more than one square of the grid",
+                            agent
+                        ));
+                    }
+                    agent_starts[index] = Some(coordinate);
+                }
+            }
+
+            if is_air(&block) {
+                continue;
+            }
+            if let Value::Table(column) = blocks
+                .entry(x.to_string())
+                .or_insert_with(|| Value::Table(Table::new()))
+            {
+                column.insert(y.to_string(), Value::Table(block));
+            }
+        }
+    }
+
+    let mut agents: Table = Table::new();
+    for (agent, start) in agent_starts.iter().enumerate() {
+        let Some(start) = start else {
+            return Err(format!(
+                "Agent {} does not start anywhere in the grid",
+                agent
+            ));
+        };
+        let mut agent_table: Table = Table::new();
+        agent_table.insert(String::from("start"), coordinate_to_value(*start));
+        agents.insert(agent.to_string(), Value::Table(agent_table));
+    }
+
+    if !ret.contains_key("num_agents") {
+        ret.insert(
+            String::from("num_agents"),
+            Value::Integer(agents.len() as i64),
+        );
+    }
+    ret.insert(String::from("agent"), Value::Table(agents));
+    ret.insert(String::from("block"), Value::Table(blocks));
+
+    Ok(ret)
+}
+
+fn agent_list(value: &Value, symbol: char) -> Result<Vec<AgentID>, String> {
+    let error: String = format!(
+        "agents of legend entry '{}' must be a list of agent IDs",
+        symbol
+    );
+    let Value::Array(arr) = value else {
+        return Err(error);
+    };
+
+    let mut ret: Vec<AgentID> = vec![];
+    for entry in arr {
+        match entry.as_integer().map(AgentID::try_from) {
+            Some(Ok(agent)) => ret.push(agent),
+            _ => return Err(error),
+        }
+    }
+    Ok(ret)
+}
+
+/// Turns a level in the block table layout into the grid layout, choosing a character for every distinct block
+pub fn level_to_grid(level: &Table) -> Result<Table, String> {
+    let size = |key: &str| -> Result<usize, String> {
+        match level.get(key) {
+            Some(Value::Integer(num)) if *num >= 0 => Ok(*num as usize),
+            _ => Err(format!("Level needs a non-negative integer {}", key)),
+        }
+    };
+    let x_size: usize = size("x_size")?;
+    let y_size: usize = size("y_size")?;
+
+    // Every square starts as air and gets the block table and starting agents from the level
+    let mut squares: Vec<Table> = vec![block_of_type("air"); x_size * y_size];
+
+    if let Some(Value::Table(columns)) = level.get("block") {
+        for (x, column) in columns {
+            let Value::Table(column) = column else {
+                return Err(String::from("Blocks not properly specified"));
+            };
+            for (y, block) in column {
+                let index: usize = square_index(x, y, x_size, y_size)?;
+                match block {
+                    Value::Table(t) => squares[index] = t.clone(),
+                    _ => return Err(String::from("Block has no entries")),
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Table(agents)) = level.get("agent") {
+        // Go through the agents in numeric order so that the lists in the legend are sorted
+        let mut ids: Vec<AgentID> = vec![];
+        for agent in agents.keys() {
+            match agent.parse::<AgentID>() {
+                Ok(id) => ids.push(id),
+                Err(_) => return Err(String::from("Invalid Agent ID (not an int)")),
+            }
+        }
+        ids.sort();
+
+        for id in ids {
+            let start: Option<&Vec<Value>> = agents
+                .get(&id.to_string())
+                .and_then(|agent| agent.get("start"))
+                .and_then(|start| start.as_array());
+            let index: usize = match start.map(|arr| arr.as_slice()) {
+                Some([x, y]) => square_index(&x.to_string(), &y.to_string(), x_size, y_size)?,
+                _ => {
+                    return Err(String::from(
+                        "Agent start position must be array of two ints",
+                    ));
+                }
+            };
+
+            let agents_here: &mut Value = squares[index]
+                .entry("agents")
+                .or_insert_with(|| Value::Array(vec![]));
+            if let Value::Array(arr) = agents_here {
+                arr.push(Value::Integer(id as i64));
+            }
+        }
+    }
+
+    let mut legend: Vec<(char, Table)> = vec![('.', block_of_type("air"))];
+    let mut grid: String = String::new();
+
+    for y in 0..y_size {
+        for x in 0..x_size {
+            let square: &Table = &squares[y * x_size + x];
+            let symbol: char = match legend.iter().find(|(_, block)| block == square) {
+                Some((symbol, _)) => *symbol,
+                None => {
+                    let symbol: char = choose_symbol(square, &legend)?;
+                    legend.push((symbol, square.clone()));
+                    symbol
+                }
+            };
+            grid.push(symbol);
+        }
+        grid.push('\n');
+    }
+
+    let mut ret: Table = level.clone();
+    for key in ["x_size", "y_size", "num_agents", "agent", "block"] {
+        ret.remove(key);
+    }
+    ret.insert(String::from("grid"), Value::String(grid));
+
+    let mut legend_table: Table = Table::new();
+    for (symbol, block) in legend {
+        // Air and plain basic blocks match the default legend and do not need to be listed
+        if default_legend(symbol).as_ref() != Some(&block) {
+            legend_table.insert(symbol.to_string(), Value::Table(block));
+        }
+    }
+    if !legend_table.is_empty() {
+        ret.insert(String::from("legend"), Value::Table(legend_table));
+    }
+
+    Ok(ret)
+}
+
+fn square_index(x: &str, y: &str, x_size: usize, y_size: usize) -> Result<usize, String> {
+    match (x.parse::<usize>(), y.parse::<usize>()) {
+        (Ok(x), Ok(y)) if x < x_size && y < y_size => Ok(y * x_size + x),
+        (Ok(_), Ok(_)) => Err(format!("Square {}, {} is out of bounds", x, y)),
+        _ => Err(String::from("Coordinate not specified correctly")),
+    }
+}
+
+/// Picks a readable character that is not used yet: # for basic blocks, G for goals and S for starts if possible
+fn choose_symbol(block: &Table, legend: &[(char, Table)]) -> Result<char, String> {
+    let free = |symbol: &char| legend.iter().all(|(used, _)| used != symbol);
+
+    let mut preferred: Vec<char> = vec![];
+    if block.contains_key("agents") {
+        preferred.push('S');
+    }
+    if let Some(Value::Table(tags)) = block.get("tags")
+        && tags.contains_key("num_goal_agents")
+    {
+        preferred.push('G');
+    }
+    if block == &block_of_type("basic_block") {
+        preferred.push('#');
+    }
+
+    match preferred.into_iter().chain(SYMBOL_POOL.chars()).find(free) {
+        Some(symbol) => Ok(symbol),
+        None => Err(String::from(
+            "Level has too many different blocks to draw it as a grid",
+        )),
+    }
+}
+
+/// Reads a `.txt` grid level: the grid lines, then `---` and the rest of the level as TOML
+pub fn parse_grid_text(text: &str) -> Result<Table, String> {
+    let mut grid: String = String::new();
+    let mut rest: String = String::new();
+    let mut in_grid: bool = true;
+
+    for line in text.lines() {
+        if in_grid && line.trim_end() == GRID_TEXT_SEPARATOR {
+            in_grid = false;
+        } else if in_grid {
+            grid.push_str(line);
+            grid.push('\n');
+        } else {
+            rest.push_str(line);
+            rest.push('\n');
+        }
+    }
+
+    let mut ret: Table = match rest.parse::<Table>() {
+        Ok(t) => t,
+        Err(e) => return Err(format!("Invalid TOML after the grid: {}", e)),
+    };
+    if ret.contains_key("grid") {
+        return Err(String::from(
+            "A grid file can not contain a grid key after the grid",
+        ));
+    }
+    ret.insert(String::from("grid"), Value::String(grid));
+    Ok(ret)
+}
+
+/// Writes a level in the grid layout as a `.txt` grid level
+pub fn grid_text(level: &Table) -> String {
+    let mut ret: String = match level.get("grid") {
+        Some(Value::String(grid)) => grid.clone(),
+        _ => String::new(),
+    };
+    ret.push_str(GRID_TEXT_SEPARATOR);
+    ret.push('\n');
+
+    let mut rest: Table = level.clone();
+    rest.remove("grid");
+    ret.push_str(&grid_rest_to_string(&rest));
+    ret
+}
+
+/// Writes a level in the grid layout as TOML, with the grid as a multi-line string
+pub fn grid_level_to_string(level: &Table) -> String {
+    let mut rest: Table = level.clone();
+    let grid: Option<Value> = rest.remove("grid");
+
+    let mut ret: String = String::new();
+    if let Some(Value::String(grid)) = grid {
+        // Literal strings keep backslashes and quotes in the grid as they are
+        let quotes: &str = if grid.contains('\\') || grid.contains('"') {
+            "'''"
+        } else {
+            "\"\"\""
+        };
+        ret.push_str(&format!("grid = {}\n{}{}\n", quotes, grid, quotes));
+    }
+    let text: String = grid_rest_to_string(&rest);
+    if !text.is_empty() && !text.starts_with('\n') && !ret.is_empty() {
+        ret.push('\n');
+    }
+    ret.push_str(&text);
+    ret
+}
+
+/// Writes the keys of a grid level next to the grid, with one inline table per legend entry
+fn grid_rest_to_string(level: &Table) -> String {
+    let mut rest: Table = level.clone();
+    let legend: Option<Value> = rest.remove("legend");
+
+    let mut ret: String = super::io::level_to_string(&rest);
+    if let Some(Value::Table(legend)) = legend {
+        ret.push_str("\n[legend]\n");
+        for (symbol, block) in &legend {
+            ret.push_str(&format!("{} = {}\n", toml_key(symbol), block));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_level(text: &str) -> Result<Table, String> {
+        grid_to_level(&text.parse::<Table>().unwrap())
+    }
+
+    #[test]
+    fn rows_are_y_and_columns_are_x() {
+        let level: Table = grid_level(
+            "grid = \"#S\\n\\n..G\"\nlegend.S = { type = \"basic_block\", agents = [0] }\n\
+             legend.G = { type = \"basic_block\", tags.num_goal_agents = 1 }\n",
+        )
+        .unwrap();
+        // The longest row gives the width, short and empty rows are air at the end
+        assert_eq!(level["x_size"].as_integer(), Some(3));
+        assert_eq!(level["y_size"].as_integer(), Some(3));
+        assert_eq!(level["num_agents"].as_integer(), Some(1));
+        assert_eq!(
+            level["agent"]["0"]["start"],
+            coordinate_to_value(Coordinate { x: 1, y: 0 })
+        );
+
+        let blocks: &Table = level["block"].as_table().unwrap();
+        let squares: Vec<(String, String)> = blocks
+            .iter()
+            .flat_map(|(x, column)| {
+                column
+                    .as_table()
+                    .unwrap()
+                    .keys()
+                    .map(move |y| (x.clone(), y.clone()))
+            })
+            .collect();
+        let expected: Vec<(String, String)> = [("0", "0"), ("1", "0"), ("2", "2")]
+            .iter()
+            .map(|(x, y)| (String::from(*x), String::from(*y)))
+            .collect();
+        assert_eq!(squares, expected);
+        assert!(blocks["2"]["2"]["tags"].get("num_goal_agents").is_some());
+        assert!(blocks["1"]["0"].get("agents").is_none());
+    }
+
+    #[test]
+    fn sizes_and_positions_must_fit_the_board() {
+        let drawn: &str =
+            "grid = \"S.\\n..\"\nlegend.S = { type = \"basic_block\", agents = [0] }\n";
+        assert!(grid_level(&format!("x_size = 4\n{}", drawn)).is_ok());
+        assert!(grid_level(&format!("x_size = 1\n{}", drawn)).is_err());
+        assert!(grid_level(&format!("y_size = -1\n{}", drawn)).is_err());
+        // Agents must start exactly once
+        assert!(
+            grid_level("grid = \"SS\"\nlegend.S = { type = \"basic_block\", agents = [0] }\n")
+                .is_err()
+        );
+        assert!(
+            grid_level("grid = \"S\"\nlegend.S = { type = \"basic_block\", agents = [1] }\n")
+                .is_err()
+        );
+        assert!(grid_level("grid = \"#X\"\n").is_err());
+
+        assert_eq!(square_index("2", "1", 3, 2), Ok(5));
+        assert!(square_index("3", "0", 3, 2).is_err());
+        assert!(square_index("0", "2", 3, 2).is_err());
+        assert!(square_index("-1", "0", 3, 2).is_err());
+
+        let outside: Table = "x_size = 2\ny_size = 2\nblock.2.0 = { type = \"basic_block\" }\n"
+            .parse::<Table>()
+            .unwrap();
+        assert!(level_to_grid(&outside).is_err());
+        let outside: Table = "x_size = 2\ny_size = 2\nagent.0.start = [0, 2]\n"
+            .parse::<Table>()
+            .unwrap();
+        assert!(level_to_grid(&outside).is_err());
+    }
+
+    #[test]
+    fn block_levels_survive_the_grid() {
+        let level: Table =
+            "x_size = 3\ny_size = 2\nnum_agents = 2\nvictory_conditions.BlocksSatisfied = 1\n\
+             agent.0.start = [0, 1]\nagent.1.start = [0, 1]\n\
+             block.0.1 = { type = \"basic_block\" }\nblock.2.0 = { type = \"basic_block\" }\n\
+             block.2.1 = { type = \"basic_block\", tags.num_goal_agents = 2 }\n"
+                .parse::<Table>()
+                .unwrap();
+        let grid: Table = level_to_grid(&level).unwrap();
+        assert_eq!(grid["grid"].as_str(), Some("..#\nS.G\n"));
+        assert_eq!(grid_to_level(&grid), Ok(level.clone()));
+        let text: Table = parse_grid_text(&grid_text(&grid)).unwrap();
+        assert_eq!(grid_to_level(&text), Ok(level));
+    }
+}
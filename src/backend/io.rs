@@ -1,30 +1,87 @@
 use super::Board;
 use super::GameState;
-use super::blocks::{Air, block_factory};
+use super::blocks::Air;
+use super::grid::{GRID_TEXT_EXTENSION, grid_to_level, is_grid_level, parse_grid_text};
+use super::level_file::{GoalSpec, LevelFile, LevelFileError};
+use super::migration::{
+    CURRENT_FORMAT_VERSION, FORMAT_VERSION_KEY, migrate_level, needs_migration,
+};
 use super::utils_backend::{
     AgentID, Coordinate, Direction, GoalType, Index, OUT_OF_BOUND, StatusUpdate,
 };
 
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use toml::{self, Table, Value};
 
+#[derive(Debug)]
 pub enum BoardLoadingError {
-    FileNotFound,
-    FileReadingError,
-    TOMLParsingError,
+    FileNotFound(io::Error),
+    FileReadingError(io::Error),
+    TOMLParsingError(toml::de::Error),
+    /// The level does not match the level file schema, e.g. a misspelled key
+    SchemaError(LevelFileError),
+    /// Every problem found in a level that matches the schema, e.g. blocks out of bounds
+    InvalidLevel(Vec<LevelFileError>),
     BoardDescriptionError(String),
 }
 
+impl fmt::Display for BoardLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardLoadingError::FileNotFound(e) => write!(f, "File not found: {}", e),
+            BoardLoadingError::FileReadingError(e) => write!(f, "File could not be read: {}", e),
+            BoardLoadingError::TOMLParsingError(e) => write!(f, "TOML parsing failed: {}", e),
+            BoardLoadingError::SchemaError(e) => write!(f, "{}", e),
+            BoardLoadingError::InvalidLevel(problems) => {
+                write!(f, "The level has {} problem(s):", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+            BoardLoadingError::BoardDescriptionError(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Error for BoardLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BoardLoadingError::FileNotFound(e) | BoardLoadingError::FileReadingError(e) => Some(e),
+            BoardLoadingError::TOMLParsingError(e) => Some(e),
+            BoardLoadingError::SchemaError(e) => Some(e),
+            BoardLoadingError::InvalidLevel(_) | BoardLoadingError::BoardDescriptionError(_) => {
+                None
+            }
+        }
+    }
+}
+
 impl super::Board {
+    /// Loads a level in the block table or grid layout, `.txt` files are read as grid text
     pub fn from_file(path: &str) -> Result<Self, BoardLoadingError> {
-        let mut file: File;
-        match File::open(path) {
-            Err(_) => return Err(BoardLoadingError::FileNotFound),
-            Ok(f) => file = f,
+        Board::from_level_file(&read_level_file(path)?)
+    }
+
+    /// Builds a board from an already parsed level table, upgrading it from older format versions if needed
+    pub fn from_table(table: &Table) -> Result<Self, BoardLoadingError> {
+        let table: Table = match migrate_level(table) {
+            Ok(t) => t,
+            Err(msg) => return Err(BoardLoadingError::BoardDescriptionError(msg)),
+        };
+        match LevelFile::from_table(&table) {
+            Ok(level) => Board::from_level_file(&level),
+            Err(e) => Err(BoardLoadingError::SchemaError(e)),
         }
+    }
 
+    /// Builds a board from a level description, reporting all problems of the level at once
+    pub fn from_level_file(level: &LevelFile) -> Result<Self, BoardLoadingError> {
         let mut board: Board = Board {
             board: vec![],
             board_start_configuration: vec![],
@@ -40,130 +97,83 @@ impl super::Board {
             agent_start_positions: vec![],
             game_state: GameState::Running,
             action_stack: vec![],
+            meta: level.meta.clone(),
         };
 
-        let mut contend: String = String::new();
-        match file.read_to_string(&mut contend) {
-            Err(_) => return Err(BoardLoadingError::FileReadingError),
-            _ => (),
-        }
-        let table: Table;
-        match contend.parse::<Table>() {
-            Err(_) => return Err(BoardLoadingError::TOMLParsingError),
-            Ok(t) => table = t,
-        }
+        let mut problems: Vec<LevelFileError> = vec![];
 
-        match board.load_victory_conditions(&table) {
-            Err(msg) => return Err(BoardLoadingError::BoardDescriptionError(msg)),
-            _ => (),
+        if level.format_version != CURRENT_FORMAT_VERSION {
+            problems.push(LevelFileError::new(
+                FORMAT_VERSION_KEY,
+                &format!(
+                    "Level has format version {} but must be migrated to {} first",
+                    level.format_version, CURRENT_FORMAT_VERSION
+                ),
+            ));
         }
 
-        match board.load_blocks(&table) {
-            Err(msg) => return Err(BoardLoadingError::BoardDescriptionError(msg)),
-            _ => (),
+        board.load_victory_conditions(level);
+        let failed_blocks: Vec<Coordinate> = board.load_blocks(level, &mut problems);
+        // Without a board there is nothing to place the agents on
+        if board.board.is_empty() {
+            return Err(BoardLoadingError::InvalidLevel(problems));
         }
+        board.load_agents(level, &failed_blocks, &mut problems);
 
-        match board.load_agents(&table) {
-            Err(msg) => return Err(BoardLoadingError::BoardDescriptionError(msg)),
-            _ => (),
+        if !problems.is_empty() {
+            return Err(BoardLoadingError::InvalidLevel(problems));
         }
-
+        // A level can already be won by the start positions of its agents
+        board.check_victory();
         Ok(board)
     }
 
-    fn load_blocks(&mut self, level_table: &Table) -> Result<(), String> {
-        match level_table.get("x_size") {
-            None => return Err(String::from("Missing x dimension\n")),
-            Some(val) => match val {
-                Value::Integer(val_int) => self.x_size = *val_int as Index,
-                _ => return Err(String::from("x dimension was not an integer")),
-            },
-        }
-        match level_table.get("y_size") {
-            None => return Err(String::from("Missing y dimension\n")),
-            Some(val) => match val {
-                Value::Integer(val_int) => self.y_size = *val_int as Index,
-                _ => return Err(String::from("y dimension was not an integer")),
-            },
+    /// Returns the squares whose block could not be built, they are left as air
+    fn load_blocks(
+        &mut self,
+        level: &LevelFile,
+        problems: &mut Vec<LevelFileError>,
+    ) -> Vec<Coordinate> {
+        let mut failed: Vec<Coordinate> = vec![];
+        if level.x_size <= 0 || level.y_size <= 0 {
+            problems.push(LevelFileError::new(
+                "",
+                "x_size and y_size must be positive",
+            ));
+            return failed;
         }
+        self.x_size = level.x_size;
+        self.y_size = level.y_size;
 
         for _ in 0..self.x_size * self.y_size {
             self.board.push(Box::new(Air::new()));
         }
 
-        match level_table.get("block") {
-            None => return Err(String::from("No blocks found")),
-            Some(blocks) => match blocks {
-                Value::Table(block_table) => {
-                    let x_coords: Vec<String> = block_table.keys().cloned().collect();
-                    for x in x_coords {
-                        match block_table.get(&x) {
-                            None => return Err(String::from("Block coordinate error")),
-                            Some(row_wrapped) => match row_wrapped {
-                                Value::Table(row) => {
-                                    let y_coords: Vec<String> = row.keys().cloned().collect();
-                                    for y in y_coords {
-                                        let block: &Table;
-                                        match row.get(&y) {
-                                            None => {
-                                                return Err(String::from("Block has no entries"));
-                                            }
-                                            Some(block_wrapped) => match block_wrapped {
-                                                Value::Table(t) => block = t,
-                                                _ => {
-                                                    return Err(String::from(
-                                                        "Block has no entries",
-                                                    ));
-                                                }
-                                            },
-                                        }
-                                        let x_as_int: Index;
-                                        let y_as_int: Index;
-
-                                        match x.parse::<Index>() {
-                                            Ok(num) => x_as_int = num,
-                                            Err(_) => {
-                                                return Err(String::from(
-                                                    "Coordinate not specified correctly",
-                                                ));
-                                            }
-                                        }
-                                        match y.parse::<Index>() {
-                                            Ok(num) => y_as_int = num,
-                                            Err(_) => {
-                                                return Err(String::from(
-                                                    "Coordinate not specified correctly",
-                                                ));
-                                            }
-                                        }
-
-                                        if self.out_of_bounds(Coordinate {
-                                            x: x_as_int,
-                                            y: y_as_int,
-                                        }) {
-                                            return Err(String::from("Block out of bounds"));
-                                        }
-                                        match block_factory(block) {
-                                            Err(msg) => return Err(msg),
-                                            Ok(b) => {
-                                                self.set_block(
-                                                    Coordinate {
-                                                        x: x_as_int,
-                                                        y: y_as_int,
-                                                    },
-                                                    b,
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => return Err(String::from("Blocks not properly specified")),
-                            },
-                        }
+        for (x, row) in &level.block {
+            for (y, spec) in row {
+                let path: String = format!("block.{}.{}", x, y);
+                let coordinate: Coordinate = match (x.parse::<Index>(), y.parse::<Index>()) {
+                    (Ok(x), Ok(y)) => Coordinate { x, y },
+                    _ => {
+                        problems.push(LevelFileError::new(
+                            &path,
+                            "Coordinate not specified correctly",
+                        ));
+                        continue;
                     }
+                };
+                if self.out_of_bounds(coordinate) {
+                    problems.push(LevelFileError::new(&path, "Block out of bounds"));
+                    continue;
                 }
-                _ => return Err(String::from("Blocks not properly specified")),
-            },
+                match spec.build() {
+                    Err(msg) => {
+                        problems.push(LevelFileError::new(&path, &msg));
+                        failed.push(coordinate);
+                    }
+                    Ok(b) => self.set_block(coordinate, b),
+                }
+            }
         }
 
         // Store the initial state of the board so it can be reconstructed for undo later
@@ -178,188 +188,537 @@ impl super::Board {
                 self.board_start_configuration.push(table);
             }
         }
-
-        Ok(())
+        failed
     }
 
-    fn load_agents(&mut self, table: &Table) -> Result<(), String> {
-        match table.get("num_agents") {
-            None => {
-                return Err(String::from("Missing number of agents dimension"));
-            }
-            Some(num_unwrapped) => match num_unwrapped {
-                Value::Integer(num) => self.num_agents = *num as u8,
-                _ => {
-                    return Err(String::from("number of agents must be an integer"));
-                }
-            },
-        }
-
+    /// Agents starting on a block that failed to build are not checked further, the block error covers them
+    fn load_agents(
+        &mut self,
+        level: &LevelFile,
+        failed_blocks: &[Coordinate],
+        problems: &mut Vec<LevelFileError>,
+    ) {
+        self.num_agents = level.num_agents;
         self.num_agents_alive = self.num_agents;
+        self.num_agents_must_finish = level.num_agents_must_finish.unwrap_or(self.num_agents);
         self.agent_positions
             .resize(self.num_agents as usize, OUT_OF_BOUND);
         self.agent_start_positions
             .resize(self.num_agents as usize, OUT_OF_BOUND);
 
-        match table.get("num_agents_must_finish") {
-            None => self.num_agents_must_finish = self.num_agents,
-            Some(num_unwrapped) => match num_unwrapped {
-                Value::Integer(num) => self.num_agents_must_finish = *num as u8,
-                _ => {
-                    return Err(String::from("number of agents must be an integer"));
+        if self.num_agents_must_finish > self.num_agents {
+            problems.push(LevelFileError::new(
+                "num_agents_must_finish",
+                "More agents must finish than there are agents",
+            ));
+        }
+        if level.agent.len() != self.num_agents as usize {
+            problems.push(LevelFileError::new(
+                "agent",
+                &format!(
+                    "{} agents specified but num_agents is {}",
+                    level.agent.len(),
+                    self.num_agents
+                ),
+            ));
+        }
+
+        let mut placed: Vec<Option<String>> = vec![None; self.num_agents as usize];
+        for (agent, spec) in &level.agent {
+            let path: String = format!("agent.{}", agent);
+            let agent_id: AgentID = match agent.parse::<AgentID>() {
+                Err(_) => {
+                    problems.push(LevelFileError::new(&path, "Invalid Agent ID (not an int)"));
+                    continue;
+                }
+                Ok(num) if num >= self.num_agents => {
+                    problems.push(LevelFileError::new(&path, "Invalid Agent ID (too large)"));
+                    continue;
                 }
-            },
+                Ok(num) => num,
+            };
+            // Keys like 1 and 01 are different keys for TOML, but the same agent
+            if let Some(other) = &placed[agent_id as usize] {
+                problems.push(LevelFileError::new(
+                    &path,
+                    &format!("Agent {} is already specified as agent.{}", agent_id, other),
+                ));
+                continue;
+            }
+            placed[agent_id as usize] = Some(agent.clone());
+
+            let start: Coordinate = Coordinate {
+                x: spec.start[0],
+                y: spec.start[1],
+            };
+            let start_path: String = format!("{}.start", path);
+            if self.out_of_bounds(start) {
+                problems.push(LevelFileError::new(
+                    &start_path,
+                    "Agent start position is out of bounds",
+                ));
+                continue;
+            }
+            if failed_blocks.contains(&start) {
+                continue;
+            }
+            if !self.get_block(start).can_enter(Direction::None) {
+                problems.push(LevelFileError::new(
+                    &start_path,
+                    "Agent start placed on block that does not support agents",
+                ));
+                continue;
+            }
+
+            let update: StatusUpdate = self.get_block(start).enter_agent(agent_id);
+            self.agent_positions[agent_id as usize] = start;
+            self.agent_start_positions[agent_id as usize] = start;
+            self.process_update(update);
         }
+    }
 
-        let agents: &Table;
+    fn load_victory_conditions(&mut self, level: &LevelFile) {
+        for (condition, value) in &level.victory_conditions {
+            self.game_progress.insert(condition.clone(), 0);
+            self.game_goal
+                .insert(condition.clone(), GoalType::from(*value));
+        }
 
-        match table.get("agent") {
-            None => return Err(String::from("No agents in level")),
-            Some(agents_unwrapped) => match agents_unwrapped {
-                Value::Table(t) => agents = t,
-                _ => return Err(String::from("Error in loading agents")),
-            },
+        self.game_progress_start = self.game_progress.clone();
+    }
+
+    /// Describes the start of the level in the same layout from_table reads
+    pub fn to_level_table(&self) -> Table {
+        let mut ret: Table = Table::new();
+        ret.insert(
+            String::from(FORMAT_VERSION_KEY),
+            Value::Integer(CURRENT_FORMAT_VERSION),
+        );
+        ret.insert(String::from("x_size"), Value::Integer(self.x_size as i64));
+        ret.insert(String::from("y_size"), Value::Integer(self.y_size as i64));
+        ret.insert(
+            String::from("num_agents"),
+            Value::Integer(self.num_agents as i64),
+        );
+        ret.insert(
+            String::from("num_agents_must_finish"),
+            Value::Integer(self.num_agents_must_finish as i64),
+        );
+
+        let mut victory_conditions: Table = Table::new();
+        for (name, goal) in &self.game_goal {
+            let value: Value = match GoalSpec::from(goal) {
+                GoalSpec::Exactly(num) => Value::Integer(num as i64),
+                bound => Value::String(bound.to_string()),
+            };
+            victory_conditions.insert(name.clone(), value);
         }
+        ret.insert(
+            String::from("victory_conditions"),
+            Value::Table(victory_conditions),
+        );
+
+        let mut agents: Table = Table::new();
+        for (agent, position) in self.agent_start_positions.iter().enumerate() {
+            let mut agent_table: Table = Table::new();
+            agent_table.insert(String::from("start"), coordinate_to_value(*position));
+            agents.insert(agent.to_string(), Value::Table(agent_table));
+        }
+        ret.insert(String::from("agent"), Value::Table(agents));
 
-        let agent_ids_as_strings = agents.keys();
+        ret.insert(
+            String::from("block"),
+            Value::Table(self.blocks_to_table(&self.board_start_configuration)),
+        );
 
-        if agent_ids_as_strings.len() != self.num_agents as usize {
-            return Err(String::from(
-                "Number of agents specified does not match number of agents declared",
-            ));
+        if !self.meta.is_empty()
+            && let Ok(meta) = Table::try_from(&self.meta)
+        {
+            ret.insert(String::from("meta"), Value::Table(meta));
         }
-        for agent in agent_ids_as_strings {
-            let agent_info: &Table;
-
-            match agents.get(agent) {
-                None => {
-                    return Err(String::from(
-                        "Some weird error happend when trying to read in an agent",
-                    ));
+
+        ret
+    }
+
+    /// Turns one table per square into the nested [block.x.y] layout, leaving out air
+    pub(super) fn blocks_to_table(&self, blocks: &[Table]) -> Table {
+        let mut ret: Table = Table::new();
+
+        for x in 0..self.x_size {
+            let mut row: Table = Table::new();
+            for y in 0..self.y_size {
+                let block: &Table = &blocks[self.coordinate_to_index(Coordinate { x, y })];
+                if let Some(Value::String(s)) = block.get("type")
+                    && s.eq("air")
+                {
+                    continue;
                 }
-                Some(agent_info_unwrapped) => match agent_info_unwrapped {
-                    Value::Table(t) => agent_info = t,
-                    _ => return Err(String::from("Agent missing info tags")),
-                },
+                row.insert(y.to_string(), Value::Table(block.clone()));
             }
+            if !row.is_empty() {
+                ret.insert(x.to_string(), Value::Table(row));
+            }
+        }
 
-            let agent_id: AgentID;
+        ret
+    }
 
-            match agent.parse::<AgentID>() {
-                Err(_) => return Err(String::from("Invalid Agent ID (not an int)")),
-                Ok(num) => {
-                    if num < self.num_agents {
-                        agent_id = num;
-                    } else {
-                        return Err(String::from("Invalid Agent ID (too large)"));
-                    }
-                }
+    /// Writes the start of the level to a file that from_file reads back into the same board
+    pub fn write_level(&self, path: &str) -> Result<(), String> {
+        match fs::write(path, level_to_string(&self.to_level_table())) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Could not write {}: {}", path, e)),
+        }
+    }
+}
+
+/// Replaces the file in one step: the text goes to a temporary file next to it that is then renamed, so a
+/// crash leaves either the old or the new contents behind but never a mix
+pub fn write_atomic(path: &Path, text: &str) -> Result<(), String> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path: PathBuf = PathBuf::from(temp_name);
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(text.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Could not write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+pub(super) fn coordinate_to_value(coordinate: Coordinate) -> Value {
+    Value::Array(vec![
+        Value::Integer(coordinate.x as i64),
+        Value::Integer(coordinate.y as i64),
+    ])
+}
+
+fn read_text(path: &str) -> Result<String, BoardLoadingError> {
+    let mut file: File;
+    match File::open(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(BoardLoadingError::FileNotFound(e));
+        }
+        Err(e) => return Err(BoardLoadingError::FileReadingError(e)),
+        Ok(f) => file = f,
+    }
+
+    let mut contend: String = String::new();
+    if let Err(e) = file.read_to_string(&mut contend) {
+        return Err(BoardLoadingError::FileReadingError(e));
+    }
+    Ok(contend)
+}
+
+/// Reads and parses a TOML file
+pub(super) fn read_table(path: &str) -> Result<Table, BoardLoadingError> {
+    parse_table(&read_text(path)?)
+}
+
+/// Reads a level file in any of the supported layouts into the typed level description
+///
+/// Up to date levels in the block table layout are deserialized straight from the text, so that errors know
+/// their line and column. Only grid and older levels go through a table to be converted first.
+pub fn read_level_file(path: &str) -> Result<LevelFile, BoardLoadingError> {
+    let text: String = read_text(path)?;
+    if path.ends_with(GRID_TEXT_EXTENSION) {
+        return level_file_from_table(&level_from_source(grid_source(&text)?)?);
+    }
+
+    let error: LevelFileError = match text.parse::<LevelFile>() {
+        Ok(level) if level.format_version == CURRENT_FORMAT_VERSION => return Ok(level),
+        Ok(level) => match Table::try_from(&level) {
+            Ok(table) => return level_file_from_table(&level_from_source(table)?),
+            Err(e) => {
+                return Err(BoardLoadingError::BoardDescriptionError(format!(
+                    "Could not convert the level: {}",
+                    e
+                )));
             }
+        },
+        Err(e) => e,
+    };
+    // The text may still be a grid or an older level that only matches the schema once it is converted
+    let table: Table = parse_table(&text)?;
+    match needs_migration(&table) {
+        Ok(false) if !is_grid_level(&table) => Err(BoardLoadingError::SchemaError(error)),
+        Ok(_) => level_file_from_table(&level_from_source(table)?),
+        Err(msg) => Err(BoardLoadingError::BoardDescriptionError(msg)),
+    }
+}
 
-            match agent_info.get("start") {
-                None => return Err(String::from("Agent missing start position")),
-                Some(position_unwrapped) => match position_unwrapped {
-                    Value::Array(arr) => {
-                        if arr.len() != 2 {
-                            return Err(String::from(
-                                "Agent start position must be array of two ints",
-                            ));
-                        }
+fn level_file_from_table(level: &Table) -> Result<LevelFile, BoardLoadingError> {
+    match LevelFile::from_table(level) {
+        Ok(l) => Ok(l),
+        Err(e) => Err(BoardLoadingError::SchemaError(e)),
+    }
+}
+
+fn parse_table(text: &str) -> Result<Table, BoardLoadingError> {
+    match text.parse::<Table>() {
+        Err(e) => Err(BoardLoadingError::TOMLParsingError(e)),
+        Ok(t) => Ok(t),
+    }
+}
+
+fn grid_source(text: &str) -> Result<Table, BoardLoadingError> {
+    match parse_grid_text(text) {
+        Ok(t) => Ok(t),
+        Err(msg) => Err(BoardLoadingError::BoardDescriptionError(msg)),
+    }
+}
+
+/// Reads a level file in any of the supported layouts as it is written, without migrating it
+pub fn read_level_source(path: &str) -> Result<Table, BoardLoadingError> {
+    if path.ends_with(GRID_TEXT_EXTENSION) {
+        grid_source(&read_text(path)?)
+    } else {
+        read_table(path)
+    }
+}
+
+/// Reads a level file in any of the supported layouts and returns it in the block table layout of the current
+/// format version
+pub fn read_level(path: &str) -> Result<Table, BoardLoadingError> {
+    level_from_source(read_level_source(path)?)
+}
+
+/// Brings a level as it is written to the block table layout of the current format version
+fn level_from_source(source: Table) -> Result<Table, BoardLoadingError> {
+    let level: Table = match migrate_level(&source) {
+        Ok(t) => t,
+        Err(msg) => return Err(BoardLoadingError::BoardDescriptionError(msg)),
+    };
+
+    if !is_grid_level(&level) {
+        return Ok(level);
+    }
+    match grid_to_level(&level) {
+        Ok(t) => Ok(t),
+        Err(msg) => Err(BoardLoadingError::BoardDescriptionError(msg)),
+    }
+}
+
+// Top level keys that are always written first, in this order
+const LEVEL_HEADER_KEYS: [&str; 5] = [
+    FORMAT_VERSION_KEY,
+    "x_size",
+    "y_size",
+    "num_agents",
+    "num_agents_must_finish",
+];
+
+/// Writes a level table in a canonical layout: the header keys, the other sections, then agents and
+/// blocks in numeric order. Blocks use dotted keys for their tags like the hand written levels do.
+pub fn level_to_string(level: &Table) -> String {
+    let mut ret: String = String::new();
+
+    for key in LEVEL_HEADER_KEYS {
+        if let Some(value) = level.get(key) {
+            ret.push_str(&format!("{} = {}\n", key, value));
+        }
+    }
+    for (key, value) in level {
+        if !LEVEL_HEADER_KEYS.contains(&key.as_str()) && !value.is_table() {
+            ret.push_str(&format!("{} = {}\n", toml_key(key), value));
+        }
+    }
+
+    for (key, value) in level {
+        if key == "agent" || key == "block" {
+            continue;
+        }
+        if let Value::Table(section) = value {
+            ret.push_str(&format!("\n[{}]\n", toml_key(key)));
+            write_dotted(&mut ret, "", section);
+        }
+    }
+
+    if let Some(Value::Table(agents)) = level.get("agent") {
+        for (agent, agent_table) in numeric_order(agents) {
+            if let Value::Table(t) = agent_table {
+                ret.push_str(&format!("\n[agent.{}]\n", toml_key(agent)));
+                write_dotted(&mut ret, "", t);
+            }
+        }
+    }
 
-                        if let Value::Integer(x) = arr[0] {
-                            if let Value::Integer(y) = arr[1] {
-                                if self.out_of_bounds(Coordinate {
-                                    x: x as Index,
-                                    y: y as Index,
-                                }) {
-                                    return Err(String::from(
-                                        "Agent start position is out of bounds",
-                                    ));
-                                }
-                                if self
-                                    .get_block(Coordinate {
-                                        x: x as Index,
-                                        y: y as Index,
-                                    })
-                                    .can_enter(Direction::None)
-                                {
-                                    let update: StatusUpdate = self
-                                        .get_block(Coordinate {
-                                            x: x as Index,
-                                            y: y as Index,
-                                        })
-                                        .enter_agent(agent_id);
-                                    self.agent_positions[agent_id as usize] = Coordinate {
-                                        x: x as Index,
-                                        y: y as Index,
-                                    };
-                                    self.agent_start_positions[agent_id as usize] = Coordinate {
-                                        x: x as Index,
-                                        y: y as Index,
-                                    };
-                                    self.process_update(update);
-                                } else {
-                                    return Err(String::from(
-                                        "Agent start placed on block that does not support agents",
-                                    ));
-                                }
-                            } else {
-                                return Err(String::from("Y coordinate of agent must be integer"));
-                            }
-                        } else {
-                            return Err(String::from("X coordinate must be integer"));
+    if let Some(Value::Table(blocks)) = level.get("block") {
+        for (x, row) in numeric_order(blocks) {
+            if let Value::Table(row_table) = row {
+                for (y, block) in numeric_order(row_table) {
+                    if let Value::Table(t) = block {
+                        ret.push_str(&format!("\n[block.{}.{}]\n", toml_key(x), toml_key(y)));
+                        // The type says what all the other keys mean, so it comes first
+                        let mut rest: Table = t.clone();
+                        if let Some(block_type) = rest.remove("type") {
+                            ret.push_str(&format!("type = {}\n", block_type));
                         }
+                        write_dotted(&mut ret, "", &rest);
                     }
-                    _ => {
-                        return Err(String::from(
-                            "Agent start position must be array of two ints",
-                        ));
-                    }
-                },
+                }
             }
         }
+    }
+
+    ret
+}
 
-        Ok(())
+fn write_dotted(out: &mut String, prefix: &str, table: &Table) {
+    for (key, value) in table {
+        let full_key: String = format!("{}{}", prefix, toml_key(key));
+        match value {
+            Value::Table(inner) => write_dotted(out, &format!("{}.", full_key), inner),
+            _ => out.push_str(&format!("{} = {}\n", full_key, value)),
+        }
     }
+}
 
-    fn load_victory_conditions(&mut self, table: &Table) -> Result<(), String> {
-        let victory_conditions: &Table;
+pub(super) fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        String::from(key)
+    } else {
+        Value::String(String::from(key)).to_string()
+    }
+}
+
+/// Sorts keys that are numbers by their value, all other keys come after them in their usual order
+fn numeric_order(table: &Table) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<(&String, &Value)> = table.iter().collect();
+    entries.sort_by_key(|(key, _)| (key.parse::<i64>().unwrap_or(i64::MAX), (*key).clone()));
+    entries
+}
 
-        match table.get("victory_conditions") {
-            None => return Err(String::from("No victory conditions in level")),
-            Some(victory_conditions_unwrapped) => match victory_conditions_unwrapped {
-                Value::Table(t) => victory_conditions = t,
-                _ => return Err(String::from("Error in loading victory conditions")),
-            },
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::notation::parse_moves;
+    use crate::backend::utils_backend::PlayerInput;
+
+    #[test]
+    fn written_levels_load_back_into_the_same_board() {
+        let path: &str = "levels/testing_levels/example1.toml";
+        let moves: Vec<PlayerInput> = parse_moves("0R 0r 0r 0U 0u 0l 0D 0L 1r 1U 1u").unwrap();
+        let start: Board = Board::from_file(path).unwrap();
+        let mut played: Board = Board::from_file(path).unwrap();
+        for input in &moves {
+            played.apply_input(*input);
         }
 
-        let condition_names = victory_conditions.keys();
+        // A game in progress still writes the start of its level
+        let out: PathBuf =
+            std::env::temp_dir().join(format!("io_test_{}.toml", std::process::id()));
+        let out: &str = out.to_str().unwrap();
+        played.write_level(out).unwrap();
+        let mut reloaded: Board = Board::from_file(out).unwrap();
+        assert_eq!(reloaded.state_key(), start.state_key());
+        assert_eq!(reloaded.game_state, start.game_state);
+        assert_eq!(reloaded.get_goal_progress(), start.get_goal_progress());
+        assert!(reloaded.action_stack.is_empty());
+        assert_eq!(reloaded.to_level_table(), start.to_level_table());
+
+        for input in &moves {
+            reloaded.apply_input(*input);
+        }
+        assert_eq!(reloaded.state_key(), played.state_key());
+        assert_eq!(reloaded.action_stack, played.action_stack);
+        assert_eq!(reloaded.agent_positions, played.agent_positions);
+        assert_eq!(reloaded.get_goal_progress(), played.get_goal_progress());
+
+        // Writing the reloaded level again gives the same text
+        let text: String = fs::read_to_string(out).unwrap();
+        reloaded.write_level(out).unwrap();
+        assert_eq!(fs::read_to_string(out).unwrap(), text);
+        fs::remove_file(out).unwrap();
+    }
 
-        for condition in condition_names {
-            let cond_value: u8;
-            match victory_conditions.get(condition) {
-                None => {
-                    return Err(String::from(
-                        "Some weird error in loading victory conditions",
-                    ));
-                }
-                Some(num_unwrapped) => match num_unwrapped {
-                    Value::Integer(num) => cond_value = *num as u8,
-                    _ => {
-                        return Err(String::from(
-                            "Victory condition must be assigned integer value",
-                        ));
-                    }
-                },
+    fn level_file_of(name: &str, text: &str) -> Result<LevelFile, BoardLoadingError> {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("io_test_{}_{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let ret = read_level_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        ret
+    }
+
+    #[test]
+    fn versioned_and_unversioned_levels_read_alike() {
+        let v2: LevelFile = read_level_file("tests/fixtures/format_versions/v2.toml").unwrap();
+        let v1: LevelFile = read_level_file("tests/fixtures/format_versions/v1.toml").unwrap();
+        let v1_grid: LevelFile =
+            read_level_file("tests/fixtures/format_versions/v1_grid.toml").unwrap();
+        assert_eq!(v1.format_version, CURRENT_FORMAT_VERSION);
+        assert_eq!(v1, v2);
+        assert_eq!(v1_grid.block, v2.block);
+        assert!(level_file_of("v3.toml", "format_version = 3\n").is_err());
+    }
+
+    #[test]
+    fn schema_errors_know_their_position() {
+        let level: &str = "format_version = 2\nx_size = 2\ny_size = 1\nnum_agents = 1\n\
+            victory_conditions.BlocksSatisfied = 1\nagent.0.start = [0, 0]\n\n\
+            [block.0.0]\ntype = \"basic_block\"\ntags.slid = 2\n";
+        match level_file_of("typo.toml", level) {
+            Err(BoardLoadingError::SchemaError(e)) => {
+                assert_eq!(e.path, "block.0.0.tags.slid");
+                assert_eq!((e.line, e.column), (Some(10), Some(6)));
             }
-            //TODO shpuld also read in min and max type conditions
-            self.game_progress.insert(condition.clone(), 0);
-            self.game_goal
-                .insert(condition.clone(), GoalType::Exactly(cond_value));
+            other => panic!("Expected a schema error, got {:?}", other),
         }
 
-        self.game_progress_start = self.game_progress.clone();
+        // Unversioned levels are converted before they are checked, so the error only knows the key
+        match level_file_of(
+            "typo_v1.toml",
+            level.trim_start_matches("format_version = 2\n"),
+        ) {
+            Err(BoardLoadingError::SchemaError(e)) => {
+                assert_eq!(e.path, "block.0.0.tags.slid");
+                assert_eq!(e.line, None);
+            }
+            other => panic!("Expected a schema error, got {:?}", other),
+        }
+
+        assert!(matches!(
+            level_file_of("broken.toml", "x_size = = 2\n"),
+            Err(BoardLoadingError::TOMLParsingError(_))
+        ));
+    }
 
-        Ok(())
+    #[test]
+    fn all_problems_are_reported_together() {
+        let level: LevelFile = "format_version = 2\nx_size = 3\ny_size = 1\nnum_agents = 2\n\
+            num_agents_must_finish = 3\nvictory_conditions.BlocksSatisfied = 1\n\
+            agent.0.start = [0, 0]\nagent.01.start = [1, 0]\nagent.1.start = [2, 0]\n\
+            block.0.0 = { type = \"air\", tags.passable = true }\n\
+            block.1.0 = { type = \"basic_block\", tags.passable = false }\n\
+            block.2.0 = { type = \"basic_block\" }\nblock.3.0 = { type = \"basic_block\" }\n"
+            .parse::<LevelFile>()
+            .unwrap();
+        let problems: Vec<LevelFileError> = match Board::from_level_file(&level) {
+            Err(BoardLoadingError::InvalidLevel(problems)) => problems,
+            other => panic!("Expected the problems of the level, got {:?}", other.err()),
+        };
+        let paths: Vec<&str> = problems.iter().map(|p| p.path.as_str()).collect();
+        // Agent 0 starts on the broken air block, that is only reported once for the block. agent.01 and agent.1
+        // are the same agent
+        assert_eq!(
+            paths,
+            vec![
+                "block.0.0",
+                "block.3.0",
+                "num_agents_must_finish",
+                "agent",
+                "agent.01.start",
+                "agent.1",
+            ]
+        );
     }
 }
@@ -0,0 +1,380 @@
+/// Typed description of a level file
+///
+/// Level files are deserialized straight into these structs, so misspelled or misplaced keys are reported
+/// instead of silently ignored. Errors name the key path (e.g. `block.3.1.tags.slide`) and, when the level
+/// was read from text, the line and column.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use toml::{Table, Value};
+
+use super::blocks::{Air, BasicBlock, Block};
+use super::utils_backend::{AgentID, GoalType, Index, SlideType};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelFile {
+    /// Version of the level format, see the migration module. Files without one are version 1
+    #[serde(default = "first_format_version")]
+    pub format_version: i64,
+    pub x_size: Index,
+    pub y_size: Index,
+    pub num_agents: AgentID,
+    /// Defaults to all agents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_agents_must_finish: Option<AgentID>,
+    pub victory_conditions: BTreeMap<String, GoalSpec>,
+    /// Agents by ID
+    pub agent: BTreeMap<String, AgentSpec>,
+    /// Blocks by x and then y coordinate, squares without a block are air
+    #[serde(default)]
+    pub block: BTreeMap<String, BTreeMap<String, BlockSpec>>,
+    #[serde(default, skip_serializing_if = "LevelMeta::is_empty")]
+    pub meta: LevelMeta,
+}
+
+fn first_format_version() -> i64 {
+    1
+}
+
+/// Information about a level for players and level pack tools, none of it changes how the level plays
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Number of moves a good solution needs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<usize>,
+    /// Difficulty rating, higher is harder. The analyze tool computes a score on the same scale
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl LevelMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == LevelMeta::default()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentSpec {
+    pub start: [Index; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockSpec {
+    #[serde(rename = "type")]
+    pub block_type: BlockType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<BlockTags>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockType {
+    Air,
+    BasicBlock,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockTags {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slide: Option<SlideSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_goal_agents: Option<u8>,
+}
+
+/// How far a block slides: "fast" slides until it hits something, a number gives the number of squares
+/// and 0 means the block does not slide at all
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlideSpec {
+    Fast,
+    Squares(u8),
+}
+
+impl Serialize for SlideSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SlideSpec::Fast => serializer.serialize_str("fast"),
+            SlideSpec::Squares(num) => serializer.serialize_i64(*num as i64),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SlideSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SlideVisitor;
+
+        impl Visitor<'_> for SlideVisitor {
+            type Value = SlideSpec;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "\"fast\" or a number of squares from 0 to 255")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<SlideSpec, E> {
+                match s {
+                    "fast" => Ok(SlideSpec::Fast),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, num: i64) -> Result<SlideSpec, E> {
+                match u8::try_from(num) {
+                    Ok(num) => Ok(SlideSpec::Squares(num)),
+                    Err(_) => Err(E::invalid_value(de::Unexpected::Signed(num), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(SlideVisitor)
+    }
+}
+
+impl From<SlideSpec> for SlideType {
+    fn from(spec: SlideSpec) -> Self {
+        match spec {
+            SlideSpec::Fast => SlideType::FastSlide,
+            SlideSpec::Squares(0) => SlideType::NoSlide,
+            SlideSpec::Squares(num) => SlideType::SlowSlide(num),
+        }
+    }
+}
+
+/// Value a victory condition has to reach: a number means exactly that value, ">=2" and "<=2" give a bound
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoalSpec {
+    Exactly(u8),
+    AtLeast(u8),
+    AtMost(u8),
+}
+
+impl GoalSpec {
+    pub fn num(&self) -> u8 {
+        match self {
+            GoalSpec::Exactly(num) | GoalSpec::AtLeast(num) | GoalSpec::AtMost(num) => *num,
+        }
+    }
+
+    /// The same kind of goal for another value
+    pub fn with_num(&self, num: u8) -> GoalSpec {
+        match self {
+            GoalSpec::Exactly(_) => GoalSpec::Exactly(num),
+            GoalSpec::AtLeast(_) => GoalSpec::AtLeast(num),
+            GoalSpec::AtMost(_) => GoalSpec::AtMost(num),
+        }
+    }
+}
+
+impl fmt::Display for GoalSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoalSpec::Exactly(num) => write!(f, "{}", num),
+            GoalSpec::AtLeast(num) => write!(f, ">={}", num),
+            GoalSpec::AtMost(num) => write!(f, "<={}", num),
+        }
+    }
+}
+
+impl Serialize for GoalSpec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            GoalSpec::Exactly(num) => serializer.serialize_i64(*num as i64),
+            bound => serializer.collect_str(bound),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GoalSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GoalVisitor;
+
+        impl Visitor<'_> for GoalVisitor {
+            type Value = GoalSpec;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "a number from 0 to 255, optionally as \">=num\" or \"<=num\""
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<GoalSpec, E> {
+                let goal: Option<GoalSpec> = if let Some(num) = s.strip_prefix(">=") {
+                    num.trim().parse::<u8>().ok().map(GoalSpec::AtLeast)
+                } else if let Some(num) = s.strip_prefix("<=") {
+                    num.trim().parse::<u8>().ok().map(GoalSpec::AtMost)
+                } else {
+                    None
+                };
+                goal.ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+            }
+
+            fn visit_i64<E: de::Error>(self, num: i64) -> Result<GoalSpec, E> {
+                match u8::try_from(num) {
+                    Ok(num) => Ok(GoalSpec::Exactly(num)),
+                    Err(_) => Err(E::invalid_value(de::Unexpected::Signed(num), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(GoalVisitor)
+    }
+}
+
+impl From<GoalSpec> for GoalType {
+    fn from(spec: GoalSpec) -> Self {
+        match spec {
+            GoalSpec::Exactly(num) => GoalType::Exactly(num),
+            GoalSpec::AtLeast(num) => GoalType::AtLeast(num),
+            GoalSpec::AtMost(num) => GoalType::AtMost(num),
+        }
+    }
+}
+
+impl From<&GoalType> for GoalSpec {
+    fn from(goal: &GoalType) -> Self {
+        match goal {
+            GoalType::Exactly(num) => GoalSpec::Exactly(*num),
+            GoalType::AtLeast(num) => GoalSpec::AtLeast(*num),
+            GoalType::AtMost(num) => GoalSpec::AtMost(*num),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelFileError {
+    /// Dotted path of the offending key, empty for the top level
+    pub path: String,
+    /// Position in the file counted from 1, only known when the level was read from text
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for LevelFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}", self.path)?;
+        }
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            if !self.path.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "(line {}, column {})", line, column)?;
+        }
+        if !self.path.is_empty() || self.line.is_some() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for LevelFileError {}
+
+impl LevelFileError {
+    /// A problem at the given key path that has no known position in the file
+    pub fn new(path: &str, message: &str) -> Self {
+        LevelFileError {
+            path: String::from(path),
+            line: None,
+            column: None,
+            message: String::from(message),
+        }
+    }
+}
+
+/// Turns a byte offset into a line and column, both counted from 1
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before: &str = &text[..offset.min(text.len())];
+    let line: usize = before.matches('\n').count() + 1;
+    let line_start: usize = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+impl FromStr for LevelFile {
+    type Err = LevelFileError;
+
+    fn from_str(text: &str) -> Result<Self, LevelFileError> {
+        match serde_path_to_error::deserialize(toml::Deserializer::new(text)) {
+            Ok(level) => Ok(level),
+            Err(e) => {
+                let path: String = e.path().to_string();
+                let inner: &toml::de::Error = e.inner();
+                let position: Option<(usize, usize)> =
+                    inner.span().map(|span| line_and_column(text, span.start));
+                Err(LevelFileError {
+                    path: if path == "." { String::new() } else { path },
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    message: String::from(inner.message()),
+                })
+            }
+        }
+    }
+}
+
+impl LevelFile {
+    pub fn from_table(table: &Table) -> Result<Self, LevelFileError> {
+        from_value(Value::Table(table.clone()))
+    }
+}
+
+impl BlockSpec {
+    pub fn from_table(table: &Table) -> Result<Self, LevelFileError> {
+        from_value(Value::Table(table.clone()))
+    }
+
+    pub fn build(&self) -> Result<Box<dyn Block>, String> {
+        match self.block_type {
+            BlockType::Air => match self.tags {
+                None => Ok(Box::new(Air::new())),
+                Some(_) => Err(String::from("Air blocks do not have tags")),
+            },
+            BlockType::BasicBlock => {
+                let tags: BlockTags = self.tags.clone().unwrap_or_default();
+                Ok(Box::new(BasicBlock::new(
+                    tags.passable.unwrap_or(true),
+                    &[],
+                    tags.slide.unwrap_or(SlideSpec::Fast).into(),
+                    tags.num_goal_agents.unwrap_or(0),
+                )))
+            }
+        }
+    }
+}
+
+fn from_value<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, LevelFileError> {
+    match serde_path_to_error::deserialize(value) {
+        Ok(t) => Ok(t),
+        Err(e) => {
+            let path: String = e.path().to_string();
+            Err(LevelFileError {
+                path: if path == "." { String::new() } else { path },
+                line: None,
+                column: None,
+                message: e.inner().to_string(),
+            })
+        }
+    }
+}
@@ -0,0 +1,194 @@
+/// Upgrading level files written for older versions of the level format
+///
+/// Every level carries a `format_version`, files without one are version 1. Each entry of MIGRATIONS
+/// upgrades a level table by exactly one version, so a level of any older version is brought up to date by
+/// running the steps after its version in order.
+use toml::{Table, Value};
+
+pub const CURRENT_FORMAT_VERSION: i64 = 2;
+pub const FORMAT_VERSION_KEY: &str = "format_version";
+
+type Migration = fn(&mut Table) -> Result<(), String>;
+
+// MIGRATIONS[i] turns a level of version i + 1 into one of version i + 2
+const MIGRATIONS: [Migration; (CURRENT_FORMAT_VERSION - 1) as usize] = [rename_default_slide];
+
+pub fn format_version(level: &Table) -> Result<i64, String> {
+    match level.get(FORMAT_VERSION_KEY) {
+        None => Ok(1),
+        Some(Value::Integer(version)) if *version >= 1 => Ok(*version),
+        Some(_) => Err(format!("{} must be a positive integer", FORMAT_VERSION_KEY)),
+    }
+}
+
+pub fn needs_migration(level: &Table) -> Result<bool, String> {
+    Ok(format_version(level)? < CURRENT_FORMAT_VERSION)
+}
+
+/// Returns the level upgraded to the current format version, levels that are up to date are returned as they are
+pub fn migrate_level(level: &Table) -> Result<Table, String> {
+    let version: i64 = format_version(level)?;
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(format!(
+            "Level has format version {}, but only versions up to {} are supported",
+            version, CURRENT_FORMAT_VERSION
+        ));
+    }
+
+    let mut ret: Table = level.clone();
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip((version - 1) as usize) {
+        if let Err(msg) = migration(&mut ret) {
+            return Err(format!(
+                "Could not upgrade the level from version {} to {}: {}",
+                step + 1,
+                step + 2,
+                msg
+            ));
+        }
+    }
+    ret.insert(
+        String::from(FORMAT_VERSION_KEY),
+        Value::Integer(CURRENT_FORMAT_VERSION),
+    );
+    Ok(ret)
+}
+
+/// Calls f on every block table of the level, both in the block sections and in the legend of grid levels
+fn for_each_block(
+    level: &mut Table,
+    mut f: impl FnMut(&str, &mut Table) -> Result<(), String>,
+) -> Result<(), String> {
+    if let Some(Value::Table(columns)) = level.get_mut("block") {
+        for (x, column) in columns.iter_mut() {
+            if let Value::Table(column) = column {
+                for (y, block) in column.iter_mut() {
+                    if let Value::Table(block) = block {
+                        f(&format!("block.{}.{}", x, y), block)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Table(legend)) = level.get_mut("legend") {
+        for (symbol, block) in legend.iter_mut() {
+            if let Value::Table(block) = block {
+                f(&format!("legend.{}", symbol), block)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Version 1 to 2: Block::to_table used to write the slide of basic blocks as `default_slide`
+fn rename_default_slide(level: &mut Table) -> Result<(), String> {
+    for_each_block(level, |path, block| {
+        let Some(Value::Table(tags)) = block.get_mut("tags") else {
+            return Ok(());
+        };
+        let Some(slide) = tags.remove("default_slide") else {
+            return Ok(());
+        };
+        if tags.contains_key("slide") {
+            return Err(format!(
+                "{} has both tags.slide and tags.default_slide",
+                path
+            ));
+        }
+        tags.insert(String::from("slide"), slide);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::backend::Board;
+    use crate::backend::io::read_level;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/format_versions")
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn raw_table(name: &str) -> Table {
+        std::fs::read_to_string(fixture(name))
+            .unwrap()
+            .parse::<Table>()
+            .unwrap()
+    }
+
+    /// Every fixture describes the same level, so after migrating they must all be identical
+    #[test]
+    fn all_versions_migrate_to_the_current_one() {
+        let current: Table = raw_table(&format!("v{}.toml", CURRENT_FORMAT_VERSION));
+        assert_eq!(format_version(&current), Ok(CURRENT_FORMAT_VERSION));
+        assert_eq!(migrate_level(&current).unwrap(), current);
+
+        for version in 1..CURRENT_FORMAT_VERSION {
+            let old: Table = raw_table(&format!("v{}.toml", version));
+            assert_eq!(format_version(&old), Ok(version));
+            assert_eq!(needs_migration(&old), Ok(true));
+            assert_eq!(migrate_level(&old).unwrap(), current, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn old_levels_load_like_current_ones() {
+        let current: Board = match Board::from_file(&fixture("v2.toml")) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        };
+        for name in ["v1.toml", "v1_grid.toml"] {
+            let old: Board = match Board::from_file(&fixture(name)) {
+                Ok(b) => b,
+                Err(e) => panic!("{}: {}", name, e),
+            };
+            assert!(old.state_key() == current.state_key(), "{}", name);
+        }
+        assert_eq!(
+            read_level(&fixture("v1_grid.toml")).unwrap(),
+            read_level(&fixture("v2.toml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_newer_and_conflicting_levels() {
+        let mut newer: Table = raw_table("v2.toml");
+        newer.insert(
+            String::from(FORMAT_VERSION_KEY),
+            Value::Integer(CURRENT_FORMAT_VERSION + 1),
+        );
+        assert!(migrate_level(&newer).is_err());
+
+        let mut conflicting: Table = raw_table("v1.toml");
+        for_each_block(&mut conflicting, |_, block| {
+            if let Some(Value::Table(tags)) = block.get_mut("tags") {
+                tags.insert(String::from("slide"), Value::Integer(1));
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert!(migrate_level(&conflicting).is_err());
+    }
+
+    /// Exported levels are of the current version, so the slide of a block has to be under its new key
+    #[test]
+    fn exported_levels_load_without_migrating() {
+        let board: Board = Board::from_file(&fixture("v1.toml")).unwrap();
+        let exported: Table = board.to_level_table();
+        assert_eq!(needs_migration(&exported), Ok(false));
+        let reloaded: Board = match Board::from_table(&exported) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(reloaded.state_key() == board.state_key());
+    }
+}
@@ -0,0 +1,229 @@
+/// Removes blocks from a level that do not matter for solving it
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use super::Board;
+use super::blocks::block_factory;
+use super::level_file::GoalSpec;
+use super::solver::{SearchLimits, SearchResult, solve};
+
+pub struct MinimizedLevel {
+    pub level: Table,
+    pub blocks_removed: usize,
+    pub goals_removed: usize,
+    pub optimal_length: usize,
+}
+
+enum Simplification {
+    RemoveBlock(String, String),
+    RemoveGoal(String, String),
+}
+
+/// Tries every simplification of the level and keeps those after which the optimal solution length stays the same
+pub fn minimize_level(level: &Table, limits: SearchLimits) -> Result<MinimizedLevel, String> {
+    let optimal_length: usize = match optimal_length_of(level, limits)? {
+        Some(length) => length,
+        None => {
+            return Err(String::from(
+                "Level can not be solved within the search limits",
+            ));
+        }
+    };
+    let check_limits: SearchLimits = SearchLimits {
+        max_depth: optimal_length,
+        max_states: limits.max_states,
+    };
+
+    let mut current: Table = level.clone();
+    let mut blocks_removed: usize = 0;
+    let mut goals_removed: usize = 0;
+
+    // Removing one block can make another one irrelevant, so repeat until nothing changes anymore
+    let mut changed: bool = true;
+    while changed {
+        changed = false;
+
+        for simplification in simplifications(&current) {
+            let candidate: Table = match apply(&current, &simplification) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            if let Ok(Some(length)) = optimal_length_of(&candidate, check_limits)
+                && length == optimal_length
+            {
+                current = candidate;
+                changed = true;
+                match simplification {
+                    Simplification::RemoveBlock(_, _) => blocks_removed += 1,
+                    Simplification::RemoveGoal(_, _) => goals_removed += 1,
+                }
+            }
+        }
+    }
+
+    Ok(MinimizedLevel {
+        level: normalize_blocks(&current)?,
+        blocks_removed,
+        goals_removed,
+        optimal_length,
+    })
+}
+
+fn optimal_length_of(level: &Table, limits: SearchLimits) -> Result<Option<usize>, String> {
+    let board: Board = match Board::from_table(level) {
+        Ok(b) => b,
+        Err(_) => return Err(String::from("Level could not be loaded")),
+    };
+
+    match solve(&board, limits) {
+        SearchResult::Solved(solution) => Ok(Some(solution.len())),
+        SearchResult::Unsolvable | SearchResult::LimitReached => Ok(None),
+    }
+}
+
+/// Lists all simplifications of the level in a fixed order, goals before whole blocks
+fn simplifications(level: &Table) -> Vec<Simplification> {
+    let mut goals: Vec<Simplification> = vec![];
+    let mut blocks: Vec<Simplification> = vec![];
+
+    if let Some(Value::Table(block_section)) = level.get("block") {
+        for (x, row) in block_section {
+            if let Value::Table(row_table) = row {
+                for (y, block) in row_table {
+                    if let Some(Value::Table(tags)) = block.get("tags")
+                        && tags.contains_key("num_goal_agents")
+                    {
+                        goals.push(Simplification::RemoveGoal(x.clone(), y.clone()));
+                    }
+                    blocks.push(Simplification::RemoveBlock(x.clone(), y.clone()));
+                }
+            }
+        }
+    }
+
+    goals.extend(blocks);
+    goals
+}
+
+/// Returns the simplified level, or None if the simplification can not be applied
+fn apply(level: &Table, simplification: &Simplification) -> Option<Table> {
+    let mut ret: Table = level.clone();
+
+    match simplification {
+        Simplification::RemoveBlock(x, y) => {
+            if agent_starts_on(level, x, y) {
+                return None;
+            }
+            let row: &mut Table = block_row(&mut ret, x)?;
+            let block: Value = row.remove(y)?;
+            let row_empty: bool = row.is_empty();
+            if row_empty && let Some(Value::Table(block_section)) = ret.get_mut("block") {
+                block_section.remove(x);
+            }
+            if let Some(Value::Table(tags)) = block.get("tags")
+                && tags.contains_key("num_goal_agents")
+            {
+                decrease_blocks_satisfied(&mut ret)?;
+            }
+        }
+        Simplification::RemoveGoal(x, y) => {
+            let row: &mut Table = block_row(&mut ret, x)?;
+            let block: &mut Table = row.get_mut(y)?.as_table_mut()?;
+            let tags: &mut Table = block.get_mut("tags")?.as_table_mut()?;
+            tags.remove("num_goal_agents")?;
+            if tags.is_empty() {
+                block.remove("tags");
+            }
+            decrease_blocks_satisfied(&mut ret)?;
+        }
+    }
+
+    Some(ret)
+}
+
+fn block_row<'a>(level: &'a mut Table, x: &str) -> Option<&'a mut Table> {
+    level
+        .get_mut("block")?
+        .as_table_mut()?
+        .get_mut(x)?
+        .as_table_mut()
+}
+
+fn decrease_blocks_satisfied(level: &mut Table) -> Option<()> {
+    let conditions: &mut Table = level.get_mut("victory_conditions")?.as_table_mut()?;
+    let value: &mut Value = conditions.get_mut("BlocksSatisfied")?;
+    let goal: GoalSpec = GoalSpec::deserialize(value.clone()).ok()?;
+    if goal.num() == 0 {
+        return None;
+    }
+    *value = Value::try_from(goal.with_num(goal.num() - 1)).ok()?;
+    Some(())
+}
+
+fn agent_starts_on(level: &Table, x: &str, y: &str) -> bool {
+    let agents: &Table = match level.get("agent") {
+        Some(Value::Table(t)) => t,
+        _ => return false,
+    };
+
+    for agent in agents.values() {
+        if let Some(Value::Array(start)) = agent.get("start")
+            && start.len() == 2
+            && start[0].as_integer().map(|v| v.to_string()).as_deref() == Some(x)
+            && start[1].as_integer().map(|v| v.to_string()).as_deref() == Some(y)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Rewrites every block through block_factory and Block::to_table so the output only contains what the blocks use
+fn normalize_blocks(level: &Table) -> Result<Table, String> {
+    let mut ret: Table = level.clone();
+
+    if let Some(Value::Table(block_section)) = ret.get_mut("block") {
+        for (_, row) in block_section.iter_mut() {
+            if let Value::Table(row_table) = row {
+                for (_, block) in row_table.iter_mut() {
+                    if let Value::Table(block_table) = block {
+                        *block_table = block_factory(block_table)?.to_table();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: SearchLimits = SearchLimits {
+        max_depth: 10,
+        max_states: 10_000,
+    };
+
+    #[test]
+    fn drops_blocks_the_solution_does_not_need() {
+        // The agent walks right twice to the goal, the block in the bottom right corner is never used
+        let level: Table = "format_version = 2\nx_size = 4\ny_size = 2\nnum_agents = 1\n\
+            victory_conditions.BlocksSatisfied = 1\nagent.0.start = [0, 0]\n\
+            block.0.0 = { type = \"basic_block\" }\nblock.1.0 = { type = \"basic_block\" }\n\
+            block.2.0 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n\
+            block.3.1 = { type = \"basic_block\" }\n"
+            .parse::<Table>()
+            .unwrap();
+
+        let minimized: MinimizedLevel = minimize_level(&level, LIMITS).unwrap();
+        assert_eq!(minimized.optimal_length, 2);
+        // Without its only goal the level would be won at the start
+        assert_eq!(minimized.goals_removed, 0);
+        assert!(minimized.blocks_removed >= 1);
+        assert!(minimized.level["block"].get("3").is_none());
+        assert_eq!(optimal_length_of(&minimized.level, LIMITS), Ok(Some(2)));
+    }
+}
@@ -7,14 +7,28 @@ use utils_backend::{
     AgentID, Coordinate, Direction, GoalType, HitResult, Index, OUT_OF_BOUND, PlayerInput,
     ProgressUpdates, SlideType, StatusUpdate,
 };
+pub mod analysis;
+pub mod editor;
+pub mod generator;
+pub mod grid;
 pub mod io;
-
+pub mod level_file;
+pub mod migration;
+pub mod minimizer;
+pub mod notation;
+pub mod packs;
+pub mod profile;
+pub mod savegame;
+pub mod solution;
+pub mod solver;
+
+use level_file::LevelMeta;
 use std::collections::{HashMap, HashSet};
 use toml::{self, Table};
 
 const MAXIMUM_STEP_NUMBER: usize = 100;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameState {
     Running,
     Won,
@@ -37,26 +51,74 @@ pub struct Board {
     agent_start_positions: Vec<Coordinate>,
     game_state: GameState,
     action_stack: Vec<PlayerInput>,
+    meta: LevelMeta,
+}
+
+/// Everything an input changed on the board in the order it happened, for frontends that animate it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ActionLog {
+    pub steps: Vec<ActionStep>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionStep {
+    /// An agent walked onto a neighbouring block
+    AgentMoved {
+        agent: AgentID,
+        from: Coordinate,
+        to: Coordinate,
+    },
+    /// A sliding block moved with the agents on it, usually by one square
+    BlockMoved {
+        from: Coordinate,
+        to: Coordinate,
+        slide: SlideType,
+    },
+    /// A sliding block left the board with the agents on it
+    BlockFell {
+        from: Coordinate,
+        direction: Direction,
+    },
 }
 
-pub struct ActionLog {}
+impl Clone for Board {
+    fn clone(&self) -> Self {
+        Board {
+            board: self.board.iter().map(|block| block.clone_box()).collect(),
+            board_start_configuration: self.board_start_configuration.clone(),
+            x_size: self.x_size,
+            y_size: self.y_size,
+            num_agents: self.num_agents,
+            num_agents_alive: self.num_agents_alive,
+            num_agents_must_finish: self.num_agents_must_finish,
+            game_progress: self.game_progress.clone(),
+            game_progress_start: self.game_progress_start.clone(),
+            game_goal: self.game_goal.clone(),
+            agent_positions: self.agent_positions.clone(),
+            agent_start_positions: self.agent_start_positions.clone(),
+            game_state: self.game_state,
+            action_stack: self.action_stack.clone(),
+            meta: self.meta.clone(),
+        }
+    }
+}
 
 impl Board {
     pub fn get_game_state(&self) -> GameState {
         self.game_state
     }
 
-    pub fn read_block(&self, coordinate: Coordinate) -> &Box<dyn Block> {
+    pub fn read_block(&self, coordinate: Coordinate) -> &dyn Block {
         assert!(!self.out_of_bounds(coordinate));
         let index = self.coordinate_to_index(coordinate);
-        &self.board[index]
+        self.board[index].as_ref()
     }
 
     pub fn get_dimensions(&self) -> (Index, Index) {
         (self.x_size, self.y_size)
     }
 
-    pub fn undo(&mut self) -> () {
+    pub fn undo(&mut self) {
         if self.action_stack.is_empty() {
             return;
         }
@@ -67,16 +129,55 @@ impl Board {
 
         self.reset_game();
 
-        print!("Num agents alive: {}\n", self.num_agents_alive);
-
         self.action_stack.clear();
 
         for action in action_stack_frozen {
-            print!("Num agents alive: {}\n", self.num_agents_alive);
-            match action {
-                PlayerInput::Move(agent, direction) => self.move_agent(agent, direction),
-                PlayerInput::Slide(agent, direction) => self.slide_agent(agent, direction),
-            };
+            self.apply_input(action);
+        }
+    }
+
+    /// Goes back to the start of the level and forgets all actions
+    pub fn restart(&mut self) {
+        self.reset_game();
+        self.action_stack.clear();
+    }
+
+    pub fn get_num_agents(&self) -> AgentID {
+        self.num_agents
+    }
+
+    /// Square the agent stands on, OUT_OF_BOUND once it has left the board
+    pub fn get_agent_position(&self, agent: AgentID) -> Coordinate {
+        self.agent_positions[agent as usize]
+    }
+
+    /// Progress towards every victory condition by name, with the goal it has to reach
+    pub fn get_goal_progress(&self) -> Vec<(String, u8, GoalType)> {
+        let mut ret: Vec<(String, u8, GoalType)> = self
+            .game_goal
+            .iter()
+            .map(|(name, goal)| {
+                let current: u8 = self.game_progress.get(name).copied().unwrap_or(0);
+                (name.clone(), current, goal.clone())
+            })
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    pub fn get_meta(&self) -> &LevelMeta {
+        &self.meta
+    }
+
+    /// Number of inputs given since the start of the level, undone ones do not count
+    pub fn move_count(&self) -> usize {
+        self.action_stack.len()
+    }
+
+    pub fn apply_input(&mut self, input: PlayerInput) -> ActionLog {
+        match input {
+            PlayerInput::Move(agent, direction) => self.move_agent(agent, direction),
+            PlayerInput::Slide(agent, direction) => self.slide_agent(agent, direction),
         }
     }
 
@@ -98,6 +199,7 @@ impl Board {
         assert!(self.game_state == GameState::Running);
 
         self.action_stack.push(PlayerInput::Move(agent, direction));
+        let mut log: ActionLog = ActionLog::default();
 
         let current_coordinate: Coordinate = self.agent_positions[agent as usize];
         let target_coordinate: Coordinate =
@@ -113,11 +215,16 @@ impl Board {
             update = self.get_block(target_coordinate).enter_agent(agent);
             self.process_update(update);
             self.agent_positions[agent as usize] = target_coordinate;
+            log.steps.push(ActionStep::AgentMoved {
+                agent,
+                from: current_coordinate,
+                to: target_coordinate,
+            });
         }
 
         self.check_victory();
 
-        ActionLog {}
+        log
     }
 
     pub fn slide_agent(&mut self, start_agent: AgentID, direction: Direction) -> ActionLog {
@@ -126,6 +233,7 @@ impl Board {
 
         self.action_stack
             .push(PlayerInput::Slide(start_agent, direction));
+        let mut log: ActionLog = ActionLog::default();
 
         let mut current_coordinate: Coordinate = self.agent_positions[start_agent as usize];
 
@@ -143,6 +251,10 @@ impl Board {
 
             if self.out_of_bounds(target_coordinate) {
                 self.move_block(current_coordinate, OUT_OF_BOUND);
+                log.steps.push(ActionStep::BlockFell {
+                    from: current_coordinate,
+                    direction: current_direction,
+                });
                 current_coordinate = OUT_OF_BOUND;
                 current_sliding = SlideType::NoSlide;
             } else {
@@ -155,11 +267,28 @@ impl Board {
                     HitResult::NoResistance => {
                         //TODO Check if the block does something special when destroyed
                         self.move_block(current_coordinate, target_coordinate);
+                        log.steps.push(ActionStep::BlockMoved {
+                            from: current_coordinate,
+                            to: target_coordinate,
+                            slide: current_sliding,
+                        });
                         current_coordinate = target_coordinate;
                     }
                     HitResult::MoveTo(new_position) => {
                         //TODO Write code to move block to new position
                         self.move_block(current_coordinate, new_position);
+                        log.steps.push(if self.out_of_bounds(new_position) {
+                            ActionStep::BlockFell {
+                                from: current_coordinate,
+                                direction: current_direction,
+                            }
+                        } else {
+                            ActionStep::BlockMoved {
+                                from: current_coordinate,
+                                to: new_position,
+                                slide: current_sliding,
+                            }
+                        });
                         current_coordinate = new_position;
                     }
                 }
@@ -167,18 +296,17 @@ impl Board {
             steps_so_far += 1;
 
             if steps_so_far > MAXIMUM_STEP_NUMBER {
-                print!("Lost due to too many steps\n");
                 self.game_state = GameState::Lost;
-                return ActionLog {};
+                return log;
             }
 
             self.check_victory();
         }
 
-        ActionLog {}
+        log
     }
 
-    fn move_block(&mut self, start: Coordinate, mut end: Coordinate) -> () {
+    fn move_block(&mut self, start: Coordinate, mut end: Coordinate) {
         if self.out_of_bounds(end) {
             end = OUT_OF_BOUND;
         }
@@ -194,10 +322,6 @@ impl Board {
 
             if self.num_agents_alive < self.num_agents_must_finish {
                 //TODO write logic for loosing the game
-                print!(
-                    "Lost due to killing too many agents. Agents alive {} and {} agents must finish\n",
-                    self.num_agents_alive, self.num_agents
-                );
                 self.game_state = GameState::Lost;
             }
 
@@ -248,15 +372,13 @@ impl Board {
         //TODO write signal handeling
     }
 
-    fn check_victory(&mut self) -> () {
+    fn check_victory(&mut self) {
         let mut all_satisfied: bool = true;
 
         for key in self.game_goal.keys() {
-            let current: u8;
-
-            match self.game_progress.get(key) {
+            let current: u8 = match self.game_progress.get(key) {
                 None => panic!("Game has goal that is not tracked"),
-                Some(val) => current = *val,
+                Some(val) => *val,
             };
 
             match self.game_goal.get(key) {
@@ -285,7 +407,6 @@ impl Board {
         }
 
         if all_satisfied && self.game_state == GameState::Running {
-            print!("Won due to satisfying all conditions\n");
             self.game_state = GameState::Won;
         } else if self.game_state != GameState::Lost {
             self.game_state = GameState::Running;
@@ -334,6 +455,7 @@ impl Board {
             agent_start_positions: vec![],
             game_state: GameState::Running,
             action_stack: vec![],
+            meta: LevelMeta::default(),
         };
 
         ret.game_progress.insert(String::from("BlocksSatisfied"), 0);
@@ -346,23 +468,23 @@ impl Board {
 
         ret.set_block(
             Coordinate { x: 4, y: 0 },
-            Box::new(BasicBlock::new(true, &vec![0], SlideType::FastSlide, 0)),
+            Box::new(BasicBlock::new(true, &[0], SlideType::FastSlide, 0)),
         );
         ret.set_block(
             Coordinate { x: 0, y: 1 },
-            Box::new(BasicBlock::new(true, &vec![], SlideType::FastSlide, 0)),
+            Box::new(BasicBlock::new(true, &[], SlideType::FastSlide, 0)),
         );
         ret.set_block(
             Coordinate { x: 3, y: 1 },
-            Box::new(BasicBlock::new(true, &vec![], SlideType::FastSlide, 0)),
+            Box::new(BasicBlock::new(true, &[], SlideType::FastSlide, 0)),
         );
         ret.set_block(
             Coordinate { x: 2, y: 2 },
-            Box::new(BasicBlock::new(true, &vec![], SlideType::FastSlide, 2)),
+            Box::new(BasicBlock::new(true, &[], SlideType::FastSlide, 2)),
         );
         ret.set_block(
             Coordinate { x: 4, y: 4 },
-            Box::new(BasicBlock::new(true, &vec![1], SlideType::FastSlide, 0)),
+            Box::new(BasicBlock::new(true, &[1], SlideType::FastSlide, 0)),
         );
 
         ret.agent_positions = vec![Coordinate { x: 4, y: 0 }, Coordinate { x: 4, y: 4 }];
@@ -371,7 +493,7 @@ impl Board {
     }
 
     /// Resets the entire game except for the action stack back to the start of the level
-    fn reset_game(&mut self) -> () {
+    fn reset_game(&mut self) {
         self.game_progress = self.game_progress_start.clone();
 
         for x in 0..self.x_size {
@@ -402,5 +524,48 @@ impl Board {
         }
 
         self.game_state = GameState::Running;
+        self.check_victory();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn action_logs_list_every_step() {
+        let level =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("levels/testing_levels/example0.toml");
+        let mut board: Board = Board::from_file(level.to_str().unwrap()).unwrap();
+
+        let log: ActionLog = board.move_agent(0, Direction::Down);
+        assert!(log.steps.is_empty());
+
+        let log: ActionLog = board.slide_agent(1, Direction::Up);
+        let moved: Vec<(Coordinate, Coordinate)> = log
+            .steps
+            .iter()
+            .map(|step| match step {
+                ActionStep::BlockMoved { from, to, .. } => (*from, *to),
+                _ => panic!("unexpected step {:?}", step),
+            })
+            .collect();
+        assert_eq!(
+            moved,
+            [(4, 4), (4, 3), (4, 2)]
+                .map(|(x, y)| (Coordinate { x, y }, Coordinate { x, y: y - 1 }))
+        );
+
+        let log: ActionLog = board.slide_agent(1, Direction::Right);
+        assert_eq!(
+            log.steps.last(),
+            Some(&ActionStep::BlockFell {
+                from: Coordinate { x: 5, y: 1 },
+                direction: Direction::Right
+            })
+        );
+        assert_eq!(board.get_game_state(), GameState::Lost);
     }
 }
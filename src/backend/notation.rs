@@ -0,0 +1,187 @@
+/// Compact text notation for sequences of player inputs
+///
+/// Every move is the agent ID followed by a direction letter, moves are separated by spaces or commas.
+/// Lower case letters (u, d, l, r) walk the agent to the neighbouring block, upper case letters (U, D, L, R)
+/// slide the block the agent stands on, and n or N stay in place. For example `0r 1R 0u` walks agent 0
+/// right, slides agent 1 right and walks agent 0 up.
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use super::utils_backend::{AgentID, Direction, PlayerInput};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotationError {
+    /// Column of the offending move in the input, counted from 1
+    pub column: usize,
+    pub token: String,
+    pub message: String,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' at column {}: {}",
+            self.token, self.column, self.message
+        )
+    }
+}
+
+pub fn parse_move(token: &str) -> Result<PlayerInput, String> {
+    let digits: usize = token.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    if digits == 0 {
+        return Err(String::from("a move must start with the agent ID, e.g. 0r"));
+    }
+    let agent: AgentID = match token[..digits].parse::<AgentID>() {
+        Ok(a) => a,
+        Err(_) => return Err(format!("agent ID {} is too large", &token[..digits])),
+    };
+
+    let mut rest = token[digits..].chars();
+    let letter: char = match rest.next() {
+        Some(c) => c,
+        None => return Err(String::from("missing direction after the agent ID")),
+    };
+    if rest.next().is_some() {
+        return Err(String::from(
+            "expected exactly one direction letter after the agent ID",
+        ));
+    }
+
+    let direction: Direction = match letter.to_ascii_lowercase() {
+        'u' => Direction::Up,
+        'd' => Direction::Down,
+        'l' => Direction::Left,
+        'r' => Direction::Right,
+        'n' => Direction::None,
+        _ => {
+            return Err(format!(
+                "unknown direction '{}', use u, d, l, r to walk, U, D, L, R to slide or n, N to stay",
+                letter
+            ));
+        }
+    };
+
+    if letter.is_ascii_uppercase() {
+        Ok(PlayerInput::Slide(agent, direction))
+    } else {
+        Ok(PlayerInput::Move(agent, direction))
+    }
+}
+
+pub fn parse_moves(text: &str) -> Result<Vec<PlayerInput>, NotationError> {
+    let mut ret: Vec<PlayerInput> = vec![];
+    let mut start: Option<usize> = None;
+
+    // Walk through the text by hand so that errors can point at the column of the bad move
+    for (index, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        let separator: bool = c.is_whitespace() || c == ',';
+        match (start, separator) {
+            (None, false) => start = Some(index),
+            (Some(begin), true) => {
+                let token: &str = &text[begin..index];
+                match parse_move(token) {
+                    Ok(input) => ret.push(input),
+                    Err(message) => {
+                        return Err(NotationError {
+                            column: text[..begin].chars().count() + 1,
+                            token: String::from(token),
+                            message,
+                        });
+                    }
+                }
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(ret)
+}
+
+pub fn format_move(input: PlayerInput) -> String {
+    let (agent, direction, slide) = match input {
+        PlayerInput::Move(agent, direction) => (agent, direction, false),
+        PlayerInput::Slide(agent, direction) => (agent, direction, true),
+    };
+
+    let letter: char = match direction {
+        Direction::Up => 'u',
+        Direction::Down => 'd',
+        Direction::Left => 'l',
+        Direction::Right => 'r',
+        Direction::None => 'n',
+    };
+
+    if slide {
+        format!("{}{}", agent, letter.to_ascii_uppercase())
+    } else {
+        format!("{}{}", agent, letter)
+    }
+}
+
+pub fn format_moves(moves: &[PlayerInput]) -> String {
+    moves
+        .iter()
+        .map(|input| format_move(*input))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Stores a list of inputs as a notation string, for use with `#[serde(with = "...")]`
+pub mod serde_moves {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        moves: &[PlayerInput],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_moves(moves))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PlayerInput>, D::Error> {
+        let text: String = String::deserialize(deserializer)?;
+        parse_moves(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let moves: Vec<PlayerInput> = vec![
+            PlayerInput::Move(0, Direction::Right),
+            PlayerInput::Slide(1, Direction::Right),
+            PlayerInput::Move(12, Direction::Up),
+            PlayerInput::Slide(3, Direction::Down),
+            PlayerInput::Move(0, Direction::None),
+        ];
+        let text: String = format_moves(&moves);
+
+        assert_eq!(text, "0r 1R 12u 3D 0n");
+        assert_eq!(parse_moves(&text).unwrap(), moves);
+        assert_eq!(parse_moves(" 0r,1R ,, 12u\t3D\n0n").unwrap(), moves);
+    }
+
+    #[test]
+    fn errors_point_at_the_bad_move() {
+        let error: NotationError = parse_moves("0r 1R 0x").unwrap_err();
+        assert_eq!(error.column, 7);
+        assert_eq!(error.token, "0x");
+        assert!(error.message.contains("n, N"));
+
+        assert_eq!(parse_moves("0r r").unwrap_err().column, 4);
+        assert_eq!(parse_moves("0").unwrap_err().token, "0");
+        assert_eq!(parse_moves("0rr").unwrap_err().token, "0rr");
+        assert_eq!(parse_moves("300r").unwrap_err().token, "300r");
+    }
+}
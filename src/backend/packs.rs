@@ -0,0 +1,432 @@
+/// Level packs: directories of levels played in a fixed order
+///
+/// A pack is a directory with a `pack.toml` manifest that names the pack and lists its levels in order. Levels
+/// are unlocked by solving earlier levels of the pack. The rule `unlock = { solve = 3, of_previous = 5 }`
+/// unlocks a level once 3 of the 5 levels before it are solved, without `of_previous` all earlier levels count.
+/// The manifest can set a rule for all its levels and every level can override it. By default a level is
+/// unlocked by solving the one before it.
+///
+/// A directory of levels without a manifest is a loose pack named after the directory, with its level files in
+/// the order of their names and all of them unlocked.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::Board;
+use super::grid::GRID_TEXT_EXTENSION;
+use super::io::{BoardLoadingError, read_level_file, read_level_source};
+use super::level_file::LevelMeta;
+use super::solution::SOLUTION_EXTENSION;
+
+pub const PACK_MANIFEST: &str = "pack.toml";
+pub const DEFAULT_PACKS_DIR: &str = "levels";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnlockRule {
+    /// How many of the counted levels must be solved
+    pub solve: usize,
+    /// How many levels directly before this one are counted, all of them if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub of_previous: Option<usize>,
+}
+
+impl Default for UnlockRule {
+    fn default() -> Self {
+        UnlockRule {
+            solve: 1,
+            of_previous: Some(1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackLevel {
+    /// Path of the level file, relative to the pack directory
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlock: Option<UnlockRule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackManifest {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Rule for all levels that do not have their own
+    #[serde(default)]
+    pub unlock: UnlockRule,
+    #[serde(rename = "level", default)]
+    pub levels: Vec<PackLevel>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelPack {
+    /// Name of the pack directory, used as the first part of the level IDs
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: PackManifest,
+}
+
+/// A pack directory that has a manifest but could not be loaded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackError {
+    /// Name of the pack directory
+    pub id: String,
+    pub message: String,
+}
+
+/// The packs of a directory, broken packs are listed next to the good ones so that one bad manifest does not
+/// hide all other packs
+#[derive(Clone, Debug, Default)]
+pub struct PackListing {
+    pub packs: Vec<LevelPack>,
+    pub errors: Vec<PackError>,
+}
+
+/// Finds all packs in the direct subdirectories of dir, both sorted by their ID. Subdirectories without a
+/// manifest are only listed when they have levels.
+pub fn list_packs(dir: &Path) -> Result<PackListing, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => return Err(format!("Could not read {}: {}", dir.display(), e)),
+    };
+
+    let mut ret: PackListing = PackListing::default();
+    for entry in entries.flatten() {
+        let path: PathBuf = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if !path.join(PACK_MANIFEST).is_file() {
+            if let Ok(pack) = LevelPack::loose(&path)
+                && !pack.is_empty()
+            {
+                ret.packs.push(pack);
+            }
+            continue;
+        }
+        match LevelPack::load(&path) {
+            Ok(pack) => ret.packs.push(pack),
+            Err(message) => ret.errors.push(PackError {
+                id: entry.file_name().to_string_lossy().to_string(),
+                message,
+            }),
+        }
+    }
+    ret.packs.sort_by(|a, b| a.id.cmp(&b.id));
+    ret.errors.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(ret)
+}
+
+/// Finds a level by its ID, e.g. testing_levels/example0, among the packs in dir
+pub fn find_level(dir: &Path, level_id: &str) -> Result<(LevelPack, usize), String> {
+    let Some((pack_id, _)) = level_id.split_once('/') else {
+        return Err(format!("{} is not a level ID", level_id));
+    };
+    let pack: LevelPack = LevelPack::load(&dir.join(pack_id))?;
+    match (0..pack.len()).find(|index| pack.level_id(*index) == level_id) {
+        Some(index) => Ok((pack, index)),
+        None => Err(format!("Pack {} has no level {}", pack_id, level_id)),
+    }
+}
+
+/// Whether a file in a directory without a manifest is a level. Files that cannot be read count, so that their
+/// errors are shown when they are played.
+fn is_loose_level(path: &Path) -> bool {
+    let name: String = path.to_string_lossy().to_string();
+    if name.ends_with(SOLUTION_EXTENSION) || path.ends_with(PACK_MANIFEST) {
+        return false;
+    }
+    if !name.ends_with(".toml") && !name.ends_with(GRID_TEXT_EXTENSION) {
+        return false;
+    }
+    match read_level_source(&name) {
+        Ok(source) => source.contains_key("x_size") || source.contains_key("grid"),
+        Err(_) => true,
+    }
+}
+
+impl LevelPack {
+    /// Loads the pack in dir, a directory without a manifest is loaded as a loose pack
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let manifest_path: PathBuf = dir.join(PACK_MANIFEST);
+        if !manifest_path.is_file() {
+            return LevelPack::loose(dir);
+        }
+        let text: String = match fs::read_to_string(&manifest_path) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not read {}: {}", manifest_path.display(), e)),
+        };
+        let manifest: PackManifest = match toml::from_str(&text) {
+            Ok(m) => m,
+            Err(e) => return Err(format!("Invalid pack {}: {}", manifest_path.display(), e)),
+        };
+
+        let id: String = match dir.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(format!("{} is not a pack directory", dir.display())),
+        };
+        let pack: LevelPack = LevelPack {
+            id,
+            dir: dir.to_path_buf(),
+            manifest,
+        };
+
+        let mut ids: HashSet<String> = HashSet::new();
+        for index in 0..pack.len() {
+            if !pack.level_path(index).is_file() {
+                return Err(format!(
+                    "Level {} of pack {} does not exist",
+                    pack.manifest.levels[index].file, pack.id
+                ));
+            }
+            if !ids.insert(pack.level_id(index)) {
+                return Err(format!(
+                    "Pack {} lists the level {} twice",
+                    pack.id,
+                    pack.level_id(index)
+                ));
+            }
+        }
+
+        Ok(pack)
+    }
+
+    /// The level files of a directory without a manifest, in the order of their names and all unlocked
+    pub fn loose(dir: &Path) -> Result<Self, String> {
+        let id: String = match dir.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(format!("{} is not a pack directory", dir.display())),
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(e) => return Err(format!("Could not read {}: {}", dir.display(), e)),
+        };
+
+        let mut files: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_file() && is_loose_level(&entry.path()))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        // A level and its grid text version share the ID, only the first one is listed
+        let mut ids: HashSet<String> = HashSet::new();
+        files.retain(|file| {
+            ids.insert(
+                file.rsplit_once('.')
+                    .map_or(file.clone(), |(stem, _)| stem.to_string()),
+            )
+        });
+
+        Ok(LevelPack {
+            manifest: PackManifest {
+                name: id.clone(),
+                author: None,
+                description: None,
+                unlock: UnlockRule {
+                    solve: 0,
+                    of_previous: None,
+                },
+                levels: files
+                    .into_iter()
+                    .map(|file| PackLevel { file, unlock: None })
+                    .collect(),
+            },
+            id,
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.manifest.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.levels.is_empty()
+    }
+
+    /// ID of the level that stays the same wherever the levels directory is, e.g. testing_levels/example0
+    pub fn level_id(&self, index: usize) -> String {
+        let file: &str = &self.manifest.levels[index].file;
+        let stem: &str = Path::new(file)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(file);
+        format!("{}/{}", self.id, stem)
+    }
+
+    pub fn level_path(&self, index: usize) -> PathBuf {
+        self.dir.join(&self.manifest.levels[index].file)
+    }
+
+    pub fn load_level(&self, index: usize) -> Result<Board, BoardLoadingError> {
+        Board::from_file(&self.level_path(index).to_string_lossy())
+    }
+
+    pub fn level_meta(&self, index: usize) -> Result<LevelMeta, BoardLoadingError> {
+        Ok(read_level_file(&self.level_path(index).to_string_lossy())?.meta)
+    }
+
+    pub fn unlock_rule(&self, index: usize) -> UnlockRule {
+        self.manifest.levels[index]
+            .unlock
+            .unwrap_or(self.manifest.unlock)
+    }
+
+    /// Whether the level can be played, given the IDs of all levels the player has solved
+    pub fn is_unlocked(&self, index: usize, solved: &HashSet<String>) -> bool {
+        let rule: UnlockRule = self.unlock_rule(index);
+        let counted: usize = rule.of_previous.unwrap_or(index).min(index);
+
+        let num_solved: usize = (index - counted..index)
+            .filter(|previous| solved.contains(&self.level_id(*previous)))
+            .count();
+        // Near the start of a pack there may be fewer levels to count than the rule asks for
+        num_solved >= rule.solve.min(counted)
+    }
+
+    /// The first unlocked level after the given one (or from the start) that is not solved yet
+    pub fn next_level(&self, after: Option<usize>, solved: &HashSet<String>) -> Option<usize> {
+        let start: usize = after.map(|index| index + 1).unwrap_or(0);
+        (start..self.len()).find(|index| {
+            !solved.contains(&self.level_id(*index)) && self.is_unlocked(*index, solved)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_pack(rules: &[Option<UnlockRule>]) -> LevelPack {
+        LevelPack {
+            id: String::from("pack"),
+            dir: PathBuf::from("pack"),
+            manifest: PackManifest {
+                name: String::from("Test pack"),
+                author: None,
+                description: None,
+                unlock: UnlockRule::default(),
+                levels: rules
+                    .iter()
+                    .enumerate()
+                    .map(|(index, rule)| PackLevel {
+                        file: format!("{}.toml", index),
+                        unlock: *rule,
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    fn solved(levels: &[usize]) -> HashSet<String> {
+        levels
+            .iter()
+            .map(|level| format!("pack/{}", level))
+            .collect()
+    }
+
+    #[test]
+    fn levels_unlock_one_after_another_by_default() {
+        let pack: LevelPack = test_pack(&[None, None, None]);
+
+        assert!(pack.is_unlocked(0, &solved(&[])));
+        assert!(!pack.is_unlocked(1, &solved(&[])));
+        assert!(pack.is_unlocked(1, &solved(&[0])));
+        assert!(!pack.is_unlocked(2, &solved(&[0])));
+
+        assert_eq!(pack.next_level(None, &solved(&[])), Some(0));
+        assert_eq!(pack.next_level(Some(0), &solved(&[0])), Some(1));
+        assert_eq!(pack.next_level(None, &solved(&[0, 1, 2])), None);
+    }
+
+    #[test]
+    fn solve_some_of_the_previous_levels() {
+        let three_of_five: Option<UnlockRule> = Some(UnlockRule {
+            solve: 3,
+            of_previous: Some(5),
+        });
+        let pack: LevelPack = test_pack(&[
+            None,
+            Some(UnlockRule {
+                solve: 0,
+                of_previous: None,
+            }),
+            Some(UnlockRule {
+                solve: 0,
+                of_previous: None,
+            }),
+            None,
+            None,
+            None,
+            three_of_five,
+        ]);
+
+        assert!(pack.is_unlocked(2, &solved(&[])));
+        assert!(!pack.is_unlocked(6, &solved(&[0, 1, 2])));
+        assert!(pack.is_unlocked(6, &solved(&[1, 3, 5])));
+        // Level 0 is not one of the 5 levels before level 6
+        assert!(!pack.is_unlocked(6, &solved(&[0, 1, 5])));
+    }
+
+    #[test]
+    fn broken_packs_are_listed_next_to_the_good_ones() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("packs_test_{}", std::process::id()));
+        let write = |path: &str, text: &str| {
+            let path: PathBuf = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write(
+            "good/pack.toml",
+            "name = \"Good\"\n[[level]]\nfile = \"one.toml\"\n",
+        );
+        write("good/one.toml", "");
+        write("broken/pack.toml", "name = \"Broken\"\nlevels = 3\n");
+        write(
+            "missing/pack.toml",
+            "name = \"Missing\"\n[[level]]\nfile = \"gone.toml\"\n",
+        );
+        write("no_pack/one.toml", "");
+        write("loose/b.toml", "x_size = 1\n");
+        write("loose/b.solution.toml", "x_size = 1\n");
+        write("loose/a.txt", "#\n");
+        write("loose/notes.md", "");
+
+        let listing: PackListing = list_packs(&dir).unwrap();
+        let ids: Vec<&str> = listing.packs.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["good", "loose"]);
+        // Folders without a manifest list their levels by name, all unlocked
+        let loose: &LevelPack = &listing.packs[1];
+        assert_eq!(loose.level_id(0), "loose/a");
+        assert_eq!(loose.level_id(1), "loose/b");
+        assert_eq!(loose.len(), 2);
+        assert!(loose.is_unlocked(1, &HashSet::new()));
+        assert_eq!(find_level(&dir, "loose/b").unwrap().1, 1);
+        let errors: Vec<&str> = listing.errors.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(errors, vec!["broken", "missing"]);
+        assert!(listing.errors[1].message.contains("gone.toml"));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(list_packs(&dir).is_err());
+    }
+
+    /// Every folder in the levels directory is listed, so it must only hold playable levels
+    #[test]
+    fn the_levels_directory_only_holds_packs() {
+        let listing: PackListing = list_packs(Path::new(DEFAULT_PACKS_DIR)).unwrap();
+        assert_eq!(listing.errors, vec![]);
+        for pack in &listing.packs {
+            for index in 0..pack.len() {
+                assert!(pack.load_level(index).is_ok(), "{}", pack.level_id(index));
+            }
+        }
+    }
+}
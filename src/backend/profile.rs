@@ -0,0 +1,268 @@
+/// Player profiles that remember the progress through the levels
+///
+/// Every profile is a TOML file named after the profile in the profile directory. That directory is in the
+/// platform data directory (e.g. `~/.local/share/sliding_game_2/profiles` on Linux) and when the platform
+/// has none, in a fallback directory chosen by the caller. Progress is stored by level ID (see the packs
+/// module), so it stays valid when the levels directory moves.
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use toml::Table;
+
+use super::Board;
+use super::io::{BoardLoadingError, write_atomic};
+
+pub const DEFAULT_PROFILE: &str = "default";
+pub const DEFAULT_PROFILE_FALLBACK_DIR: &str = "profiles";
+const PROFILE_EXTENSION: &str = "toml";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelProgress {
+    #[serde(default)]
+    pub solved: bool,
+    /// Fewest moves of all solves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_moves: Option<usize>,
+    /// Shortest time of all solves in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_time_ms: Option<u64>,
+    /// Undos over all attempts
+    #[serde(default)]
+    pub undos: usize,
+    /// The game the player left unfinished most recently
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save: Option<InProgress>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InProgress {
+    /// Time played before the game was left, so the clock continues when it is resumed
+    pub time_ms: u64,
+    /// Same contents as a save file, see Board::save_table
+    pub game: Table,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    /// Progress by level ID
+    #[serde(rename = "level", default)]
+    pub levels: BTreeMap<String, LevelProgress>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Profile {
+            name: String::from(name),
+            levels: BTreeMap::new(),
+        }
+    }
+
+    pub fn progress(&self, level_id: &str) -> Option<&LevelProgress> {
+        self.levels.get(level_id)
+    }
+
+    /// IDs of all solved levels, as LevelPack::is_unlocked takes them
+    pub fn solved_levels(&self) -> HashSet<String> {
+        self.levels
+            .iter()
+            .filter(|(_, progress)| progress.solved)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Records a solve, time includes the time played before the game was saved and resumed
+    pub fn record_win(&mut self, level_id: &str, moves: usize, time: Duration, undos: usize) {
+        let progress: &mut LevelProgress = self.levels.entry(String::from(level_id)).or_default();
+        let time_ms: u64 = time.as_millis() as u64;
+
+        progress.solved = true;
+        progress.best_moves = Some(progress.best_moves.map_or(moves, |best| best.min(moves)));
+        progress.best_time_ms = Some(
+            progress
+                .best_time_ms
+                .map_or(time_ms, |best| best.min(time_ms)),
+        );
+        progress.undos += undos;
+        progress.save = None;
+    }
+
+    /// Records a game the player left, it replaces any earlier unfinished game of the level
+    pub fn record_unfinished(
+        &mut self,
+        level_id: &str,
+        board: &Board,
+        time: Duration,
+        undos: usize,
+    ) {
+        let progress: &mut LevelProgress = self.levels.entry(String::from(level_id)).or_default();
+
+        progress.undos += undos;
+        progress.save = Some(InProgress {
+            time_ms: time.as_millis() as u64,
+            game: board.save_table(),
+        });
+    }
+
+    /// The unfinished game of the level and the time already played on it
+    pub fn resume(&self, level_id: &str) -> Option<Result<(Board, Duration), BoardLoadingError>> {
+        let save: &InProgress = self.progress(level_id)?.save.as_ref()?;
+        Some(
+            Board::from_save_table(&save.game)
+                .map(|board| (board, Duration::from_millis(save.time_ms))),
+        )
+    }
+}
+
+/// Profile names become file names, so they may only use letters, digits, - and _
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The directory all profiles of a player are stored in
+#[derive(Clone, Debug)]
+pub struct ProfileStore {
+    pub dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(dir: &Path) -> Self {
+        ProfileStore {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Uses the platform data directory, or the fallback where the platform does not have one
+    pub fn locate(fallback: &Path) -> Self {
+        match ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
+            Some(dirs) => ProfileStore::new(&dirs.data_dir().join("profiles")),
+            None => ProfileStore::new(fallback),
+        }
+    }
+
+    fn profile_path(&self, name: &str) -> Result<PathBuf, String> {
+        if !is_valid_profile_name(name) {
+            return Err(format!(
+                "Invalid profile name \"{}\", use only letters, digits, - and _",
+                name
+            ));
+        }
+        Ok(self.dir.join(format!("{}.{}", name, PROFILE_EXTENSION)))
+    }
+
+    /// Names of all stored profiles in alphabetical order
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(e) => e,
+            // Nothing has been stored yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(format!("Could not read {}: {}", self.dir.display(), e)),
+        };
+
+        let mut ret: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == PROFILE_EXTENSION))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        ret.sort();
+        Ok(ret)
+    }
+
+    /// Loads the profile, a profile that was never stored starts without progress
+    pub fn load(&self, name: &str) -> Result<Profile, String> {
+        let path: PathBuf = self.profile_path(name)?;
+        let text: String = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Profile::new(name)),
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+
+        let mut profile: Profile = match toml::from_str(&text) {
+            Ok(p) => p,
+            Err(e) => return Err(format!("Invalid profile {}: {}", path.display(), e)),
+        };
+        profile.name = String::from(name);
+        Ok(profile)
+    }
+
+    pub fn store(&self, profile: &Profile) -> Result<(), String> {
+        let path: PathBuf = self.profile_path(&profile.name)?;
+        if let Err(e) = fs::create_dir_all(&self.dir) {
+            return Err(format!("Could not create {}: {}", self.dir.display(), e));
+        }
+        let text: String = match toml::to_string(profile) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not serialize the profile: {}", e)),
+        };
+        write_atomic(&path, &text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_board() -> Board {
+        let path: PathBuf =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("levels/testing_levels/example0.toml");
+        match Board::from_file(path.to_str().unwrap()) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn wins_keep_the_best_results() {
+        let mut profile: Profile = Profile::new("test");
+        let board: Board = test_board();
+
+        profile.record_unfinished("pack/0", &board, Duration::from_secs(30), 2);
+        assert!(profile.solved_levels().is_empty());
+        assert!(profile.resume("pack/0").is_some());
+
+        profile.record_win("pack/0", 12, Duration::from_secs(40), 1);
+        profile.record_win("pack/0", 10, Duration::from_secs(50), 0);
+
+        let progress: &LevelProgress = profile.progress("pack/0").unwrap();
+        assert_eq!(progress.best_moves, Some(10));
+        assert_eq!(progress.best_time_ms, Some(40_000));
+        assert_eq!(progress.undos, 3);
+        assert!(profile.resume("pack/0").is_none());
+        assert_eq!(
+            profile.solved_levels(),
+            HashSet::from([String::from("pack/0")])
+        );
+    }
+
+    #[test]
+    fn profiles_are_stored_by_name() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("profiles_test_{}", std::process::id()));
+        let store: ProfileStore = ProfileStore::new(&dir);
+
+        let mut profile: Profile = store.load("alice").unwrap();
+        assert!(profile.levels.is_empty());
+        profile.record_win("pack/0", 8, Duration::from_millis(1234), 0);
+        profile.record_unfinished("pack/1", &test_board(), Duration::from_secs(3), 1);
+        store.store(&profile).unwrap();
+        store.store(&Profile::new("bob")).unwrap();
+
+        assert_eq!(store.list().unwrap(), vec!["alice", "bob"]);
+        assert_eq!(store.load("alice").unwrap(), profile);
+        assert!(store.load("../alice").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+/// Saving a game in progress and resuming it later
+///
+/// A save embeds the start of the level, so it can be resumed (and undone back to the start) even if the
+/// level file changes or moves. Next to it the save stores the current position and the full action history.
+use std::fs;
+
+use toml::{Table, Value};
+
+use super::blocks::{Air, block_factory};
+use super::io::{BoardLoadingError, coordinate_to_value, read_table};
+use super::notation::{format_moves, parse_moves};
+use super::utils_backend::{Coordinate, Direction, Index, OUT_OF_BOUND};
+use super::{Board, GameState};
+
+pub fn game_state_name(state: GameState) -> &'static str {
+    match state {
+        GameState::Running => "running",
+        GameState::Won => "won",
+        GameState::Lost => "lost",
+    }
+}
+
+pub fn game_state_from_name(name: &str) -> Result<GameState, String> {
+    match name {
+        "running" => Ok(GameState::Running),
+        "won" => Ok(GameState::Won),
+        "lost" => Ok(GameState::Lost),
+        _ => Err(format!("Unknown game state {}", name)),
+    }
+}
+
+impl Board {
+    pub fn save_game(&self, path: &str) -> Result<(), String> {
+        let text: String = match toml::to_string(&self.save_table()) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not serialize the game: {}", e)),
+        };
+        match fs::write(path, text) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Could not write {}: {}", path, e)),
+        }
+    }
+
+    /// The contents of a save file, for storing a game somewhere else than in a file of its own
+    pub fn save_table(&self) -> Table {
+        let mut save: Table = Table::new();
+
+        save.insert(
+            String::from("history"),
+            Value::String(format_moves(&self.action_stack)),
+        );
+        save.insert(
+            String::from("game_state"),
+            Value::String(String::from(game_state_name(self.game_state))),
+        );
+        save.insert(
+            String::from("num_agents_alive"),
+            Value::Integer(self.num_agents_alive as i64),
+        );
+
+        let mut progress: Table = Table::new();
+        for (name, value) in &self.game_progress {
+            progress.insert(name.clone(), Value::Integer(*value as i64));
+        }
+        save.insert(String::from("progress"), Value::Table(progress));
+
+        // Agents that left the board have no position
+        let mut agents: Table = Table::new();
+        for (agent, position) in self.agent_positions.iter().enumerate() {
+            let mut agent_table: Table = Table::new();
+            if *position != OUT_OF_BOUND {
+                agent_table.insert(String::from("position"), coordinate_to_value(*position));
+            }
+            agents.insert(agent.to_string(), Value::Table(agent_table));
+        }
+        save.insert(String::from("agent"), Value::Table(agents));
+
+        let current_blocks: Vec<Table> = self.board.iter().map(|block| block.to_table()).collect();
+        save.insert(
+            String::from("block"),
+            Value::Table(self.blocks_to_table(&current_blocks)),
+        );
+
+        save.insert(String::from("level"), Value::Table(self.to_level_table()));
+        save
+    }
+
+    pub fn load_game(path: &str) -> Result<Self, BoardLoadingError> {
+        Board::from_save_table(&read_table(path)?)
+    }
+
+    pub fn from_save_table(save: &Table) -> Result<Self, BoardLoadingError> {
+        let mut board: Board = match save.get("level") {
+            Some(Value::Table(level)) => Board::from_table(level)?,
+            _ => {
+                return Err(BoardLoadingError::BoardDescriptionError(String::from(
+                    "Save does not contain the level it belongs to",
+                )));
+            }
+        };
+
+        match board.restore_state(save) {
+            Err(msg) => Err(BoardLoadingError::BoardDescriptionError(msg)),
+            Ok(()) => Ok(board),
+        }
+    }
+
+    /// Replaces the start position the board was loaded with by the position stored in a save
+    fn restore_state(&mut self, save: &Table) -> Result<(), String> {
+        for x in 0..self.x_size {
+            for y in 0..self.y_size {
+                self.set_block(Coordinate { x, y }, Box::new(Air::new()));
+            }
+        }
+
+        if let Some(Value::Table(block_section)) = save.get("block") {
+            for (x, row) in block_section {
+                let Value::Table(row_table) = row else {
+                    return Err(String::from("Blocks not properly specified"));
+                };
+                for (y, block) in row_table {
+                    let coordinate: Coordinate = match (x.parse::<Index>(), y.parse::<Index>()) {
+                        (Ok(x), Ok(y)) => Coordinate { x, y },
+                        _ => return Err(String::from("Coordinate not specified correctly")),
+                    };
+                    if self.out_of_bounds(coordinate) {
+                        return Err(String::from("Block out of bounds"));
+                    }
+                    let Value::Table(block_table) = block else {
+                        return Err(String::from("Block has no entries"));
+                    };
+                    self.set_block(coordinate, block_factory(block_table)?);
+                }
+            }
+        }
+
+        let agents: &Table = match save.get("agent") {
+            Some(Value::Table(t)) => t,
+            _ => return Err(String::from("Save has no agents")),
+        };
+        for agent in 0..self.num_agents {
+            let position: Coordinate = match agents
+                .get(&agent.to_string())
+                .and_then(|a| a.get("position"))
+            {
+                None => OUT_OF_BOUND,
+                Some(Value::Array(arr)) if arr.len() == 2 => {
+                    match (arr[0].as_integer(), arr[1].as_integer()) {
+                        (Some(x), Some(y)) => Coordinate {
+                            x: x as Index,
+                            y: y as Index,
+                        },
+                        _ => return Err(String::from("Agent position must be two integers")),
+                    }
+                }
+                Some(_) => return Err(String::from("Agent position must be two integers")),
+            };
+
+            if position != OUT_OF_BOUND {
+                if self.out_of_bounds(position)
+                    || !self.get_block(position).can_enter(Direction::None)
+                {
+                    return Err(format!(
+                        "Agent {} is not on a block that can hold it",
+                        agent
+                    ));
+                }
+                // The progress is restored from the save below, so the update is not needed
+                let _ = self.get_block(position).enter_agent(agent);
+            }
+            self.agent_positions[agent as usize] = position;
+        }
+
+        let progress: &Table = match save.get("progress") {
+            Some(Value::Table(t)) => t,
+            _ => return Err(String::from("Save has no game progress")),
+        };
+        for (name, value) in self.game_progress.iter_mut() {
+            match progress.get(name) {
+                Some(Value::Integer(num)) => *value = *num as u8,
+                _ => return Err(format!("Save has no progress for {}", name)),
+            }
+        }
+
+        match save.get("num_agents_alive") {
+            Some(Value::Integer(num)) => self.num_agents_alive = *num as u8,
+            _ => return Err(String::from("Save is missing the number of agents alive")),
+        }
+
+        match save.get("game_state") {
+            Some(Value::String(name)) => self.game_state = game_state_from_name(name)?,
+            _ => return Err(String::from("Save is missing the game state")),
+        }
+
+        match save.get("history") {
+            Some(Value::String(history)) => match parse_moves(history) {
+                Ok(moves) => self.action_stack = moves,
+                Err(e) => return Err(format!("Could not read the action history, {}", e)),
+            },
+            _ => return Err(String::from("Save is missing the action history")),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::level_file::LevelFile;
+    use crate::backend::utils_backend::GoalType;
+
+    // Either of the two goals at the right end is enough to win
+    const AT_LEAST_ONE_GOAL: &str = "format_version = 2\nx_size = 4\ny_size = 1\nnum_agents = 1\n\
+        victory_conditions.BlocksSatisfied = \">=1\"\nagent.0.start = [0, 0]\n\
+        block.0.0 = { type = \"basic_block\" }\nblock.1.0 = { type = \"basic_block\" }\n\
+        block.2.0 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n\
+        block.3.0 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n";
+
+    #[test]
+    fn resumed_games_keep_their_goals_and_undo_to_the_start() {
+        let level: LevelFile = AT_LEAST_ONE_GOAL.parse::<LevelFile>().unwrap();
+        let start: Board = Board::from_level_file(&level).unwrap();
+        let mut board: Board = Board::from_level_file(&level).unwrap();
+        board.move_agent(0, Direction::Right);
+
+        let text: String = toml::to_string(&board.save_table()).unwrap();
+        let mut resumed: Board = Board::from_save_table(&text.parse::<Table>().unwrap()).unwrap();
+        assert_eq!(resumed.state_key(), board.state_key());
+        assert_eq!(
+            resumed.get_goal_progress(),
+            vec![(String::from("BlocksSatisfied"), 0, GoalType::AtLeast(1))]
+        );
+
+        resumed.undo();
+        assert_eq!(resumed.move_count(), 0);
+        assert_eq!(resumed.state_key(), start.state_key());
+        assert_eq!(resumed.get_goal_progress(), start.get_goal_progress());
+
+        resumed.move_agent(0, Direction::Right);
+        resumed.move_agent(0, Direction::Right);
+        assert_eq!(resumed.get_game_state(), GameState::Won);
+    }
+}
@@ -0,0 +1,212 @@
+/// Solution files and replaying them against a level
+use std::fmt;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use super::notation::format_move;
+use super::utils_backend::{AgentID, OUT_OF_BOUND, PlayerInput};
+use super::{Board, GameState};
+
+pub const SOLUTION_EXTENSION: &str = ".solution.toml";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Solution {
+    /// Path of the level, relative to the solution file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Whether the solution is known to use as few moves as possible
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimal: Option<bool>,
+    /// The moves in compact notation, e.g. "0r 1R 0u"
+    #[serde(with = "super::notation::serde_moves")]
+    pub moves: Vec<PlayerInput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The move at the given step (counted from 1) can not be made in the position reached before it
+    IllegalMove {
+        step: usize,
+        input: PlayerInput,
+        reason: String,
+    },
+    /// The level was already won after the given step, but more moves follow
+    WonEarly { step: usize },
+    /// The level was lost by the given step
+    Lost { step: usize },
+    /// All moves were played without winning the level
+    NotWon,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::IllegalMove {
+                step,
+                input,
+                reason,
+            } => write!(
+                f,
+                "Step {} ({}) is illegal: {}",
+                step,
+                format_move(*input),
+                reason
+            ),
+            VerificationError::WonEarly { step } => write!(
+                f,
+                "The level is already won after step {}, but the solution continues",
+                step
+            ),
+            VerificationError::Lost { step } => write!(f, "The level is lost at step {}", step),
+            VerificationError::NotWon => {
+                write!(f, "All moves were played but the level is not won")
+            }
+        }
+    }
+}
+
+impl Solution {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text: String = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not read {}: {}", path, e)),
+        };
+        match toml::from_str(&text) {
+            Ok(solution) => Ok(solution),
+            Err(e) => Err(format!("Invalid solution file {}: {}", path, e)),
+        }
+    }
+
+    pub fn write_file(&self, path: &str) -> Result<(), String> {
+        let text: String = match toml::to_string(self) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not serialize solution: {}", e)),
+        };
+        match fs::write(path, text) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Could not write {}: {}", path, e)),
+        }
+    }
+}
+
+impl Board {
+    /// Checks whether the input can be given in the current position, without changing the board
+    pub fn check_input(&self, input: PlayerInput) -> Result<(), String> {
+        if self.game_state != GameState::Running {
+            return Err(String::from("the game is already over"));
+        }
+
+        let agent: AgentID = match input {
+            PlayerInput::Move(agent, _) | PlayerInput::Slide(agent, _) => agent,
+        };
+        if agent >= self.num_agents {
+            return Err(format!(
+                "there is no agent {}, the level has {} agents",
+                agent, self.num_agents
+            ));
+        }
+        if self.agent_positions[agent as usize] == OUT_OF_BOUND {
+            return Err(format!("agent {} has left the board", agent));
+        }
+
+        if let PlayerInput::Move(_, direction) = input
+            && !self.can_move_agent(agent, direction)
+        {
+            return Err(format!("agent {} can not move {:?}", agent, direction));
+        }
+
+        Ok(())
+    }
+}
+
+/// Replays the moves from the current position of the board and checks that they win the level exactly at the end
+pub fn verify_solution(board: &Board, moves: &[PlayerInput]) -> Result<(), VerificationError> {
+    let mut replay: Board = board.clone();
+
+    for (index, input) in moves.iter().enumerate() {
+        let step: usize = index + 1;
+
+        match replay.game_state {
+            GameState::Won => return Err(VerificationError::WonEarly { step: index }),
+            GameState::Lost => return Err(VerificationError::Lost { step: index }),
+            GameState::Running => (),
+        }
+
+        if let Err(reason) = replay.check_input(*input) {
+            return Err(VerificationError::IllegalMove {
+                step,
+                input: *input,
+                reason,
+            });
+        }
+
+        replay.apply_input(*input);
+    }
+
+    match replay.game_state {
+        GameState::Won => Ok(()),
+        GameState::Lost => Err(VerificationError::Lost { step: moves.len() }),
+        GameState::Running => Err(VerificationError::NotWon),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::backend::utils_backend::Direction;
+    use std::path::Path;
+
+    /// Every level shipped in the repository that has a solution file next to it must be solved by that file
+    #[test]
+    fn shipped_solutions_are_valid() {
+        let levels_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("levels");
+        let mut checked: usize = 0;
+        let mut dirs = vec![levels_dir];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let name: String = path.to_str().unwrap().to_string();
+                let Some(level) = name.strip_suffix(SOLUTION_EXTENSION) else {
+                    continue;
+                };
+
+                let solution: Solution = Solution::from_file(&name).unwrap();
+                let board: Board = match Board::from_file(&format!("{}.toml", level)) {
+                    Ok(b) => b,
+                    Err(_) => panic!("Level for solution {} could not be loaded", name),
+                };
+                if let Err(e) = verify_solution(&board, &solution.moves) {
+                    panic!("{}: {}", name, e);
+                }
+                checked += 1;
+            }
+        }
+
+        assert!(checked > 0, "No solution files found");
+    }
+
+    #[test]
+    fn reports_first_illegal_step() {
+        let board: Board = Board::new_test();
+        let moves: Vec<PlayerInput> = vec![
+            PlayerInput::Slide(1, Direction::Up),
+            PlayerInput::Slide(7, Direction::Up),
+        ];
+
+        match verify_solution(&board, &moves) {
+            Err(VerificationError::IllegalMove { step, .. }) => assert_eq!(step, 2),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+/// Exhaustive breadth first search over the positions reachable from a board
+use std::collections::{HashSet, VecDeque};
+
+use super::utils_backend::{Coordinate, Direction, OUT_OF_BOUND, PlayerInput, TextureType};
+use super::{Board, GameState};
+
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// Everything that distinguishes two positions of the same level from each other
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StateKey {
+    blocks: Vec<(usize, String)>,
+    agents: Vec<Coordinate>,
+    game_state: GameState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Longest solution that will be looked for
+    pub max_depth: usize,
+    /// Number of distinct positions after which the search gives up
+    pub max_states: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchResult {
+    /// A shortest sequence of inputs that wins the level
+    Solved(Vec<PlayerInput>),
+    /// Every reachable position was visited and none of them is won
+    Unsolvable,
+    /// The search hit one of its limits before it could decide the level
+    LimitReached,
+}
+
+impl Board {
+    /// Describes the current position such that equal keys mean equal futures
+    pub fn state_key(&self) -> StateKey {
+        let mut blocks: Vec<(usize, String)> = vec![];
+
+        for (index, block) in self.board.iter().enumerate() {
+            if block.get_texture() == TextureType::None {
+                continue;
+            }
+            blocks.push((index, format!("{:?}", block.to_table())));
+        }
+
+        StateKey {
+            blocks,
+            agents: self.agent_positions.clone(),
+            game_state: self.game_state,
+        }
+    }
+
+    /// Lists every input that can be given in the current position
+    pub fn legal_inputs(&self) -> Vec<PlayerInput> {
+        let mut ret: Vec<PlayerInput> = vec![];
+
+        if self.game_state != GameState::Running {
+            return ret;
+        }
+
+        for agent in 0..self.num_agents {
+            if self.agent_positions[agent as usize] == OUT_OF_BOUND {
+                continue;
+            }
+            for direction in DIRECTIONS {
+                if self.can_move_agent(agent, direction) {
+                    ret.push(PlayerInput::Move(agent, direction));
+                }
+                ret.push(PlayerInput::Slide(agent, direction));
+            }
+        }
+
+        ret
+    }
+}
+
+/// Copies the board into the root of a search, with an empty action history
+pub(super) fn search_start(board: &Board) -> Board {
+    let mut start: Board = board.clone();
+    start.action_stack.clear();
+    // Positions in the search are never reset, so the copies do not need to carry the start configuration
+    start.board_start_configuration.clear();
+    start
+}
+
+/// Searches for a shortest winning sequence of inputs starting from the current position of the board
+pub fn solve(board: &Board, limits: SearchLimits) -> SearchResult {
+    let start: Board = search_start(board);
+
+    if start.game_state == GameState::Won {
+        return SearchResult::Solved(vec![]);
+    }
+
+    let mut visited: HashSet<StateKey> = HashSet::new();
+    visited.insert(start.state_key());
+
+    let mut queue: VecDeque<Board> = VecDeque::new();
+    queue.push_back(start);
+
+    let mut hit_limit: bool = false;
+
+    while let Some(current) = queue.pop_front() {
+        if current.action_stack.len() >= limits.max_depth {
+            hit_limit = true;
+            continue;
+        }
+
+        for input in current.legal_inputs() {
+            let mut next: Board = current.clone();
+            next.apply_input(input);
+
+            if !visited.insert(next.state_key()) {
+                continue;
+            }
+
+            match next.game_state {
+                GameState::Won => return SearchResult::Solved(next.action_stack),
+                GameState::Lost => (),
+                GameState::Running => {
+                    if visited.len() > limits.max_states {
+                        return SearchResult::LimitReached;
+                    }
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    if hit_limit {
+        SearchResult::LimitReached
+    } else {
+        SearchResult::Unsolvable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::level_file::LevelFile;
+
+    const LIMITS: SearchLimits = SearchLimits {
+        max_depth: 10,
+        max_states: 10_000,
+    };
+
+    fn board(text: &str) -> Board {
+        Board::from_level_file(&text.parse::<LevelFile>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn levels_won_at_the_start_or_not_at_all() {
+        // The agent starts on the goal
+        let won: Board = board(
+            "format_version = 2\nx_size = 2\ny_size = 1\nnum_agents = 1\n\
+             victory_conditions.BlocksSatisfied = 1\nagent.0.start = [0, 0]\n\
+             block.0.0 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n\
+             block.1.0 = { type = \"basic_block\" }\n",
+        );
+        assert_eq!(won.get_game_state(), GameState::Won);
+        assert_eq!(solve(&won, LIMITS), SearchResult::Solved(vec![]));
+
+        // Every slide of the agent throws its block off the board before it reaches the goal
+        let stuck: Board = board(
+            "format_version = 2\nx_size = 3\ny_size = 2\nnum_agents = 1\n\
+             victory_conditions.BlocksSatisfied = 1\nagent.0.start = [0, 0]\n\
+             block.0.0 = { type = \"basic_block\" }\n\
+             block.2.1 = { type = \"basic_block\", tags.num_goal_agents = 1 }\n",
+        );
+        assert_eq!(stuck.get_game_state(), GameState::Running);
+        assert_eq!(solve(&stuck, LIMITS), SearchResult::Unsolvable);
+        assert_eq!(
+            solve(
+                &stuck,
+                SearchLimits {
+                    max_depth: 0,
+                    ..LIMITS
+                }
+            ),
+            SearchResult::LimitReached
+        );
+    }
+}
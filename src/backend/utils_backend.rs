@@ -2,7 +2,7 @@ pub type Index = i16;
 
 pub type AgentID = u8;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Coordinate {
     pub x: Index,
     pub y: Index,
@@ -27,14 +27,14 @@ impl Coordinate {
                 x: self.x + 1,
                 y: self.y,
             },
-            Direction::None => self.clone(),
+            Direction::None => *self,
         }
     }
 }
 
 pub const OUT_OF_BOUND: Coordinate = Coordinate { x: 1000, y: 1000 };
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     Up,
     Down,
@@ -43,7 +43,7 @@ pub enum Direction {
     None,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PlayerInput {
     Move(AgentID, Direction),
     Slide(AgentID, Direction),
@@ -67,9 +67,7 @@ pub enum HitResult {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GoalType {
-    #[allow(dead_code)]
     AtLeast(u8),
-    #[allow(dead_code)]
     AtMost(u8),
     Exactly(u8),
 }
@@ -0,0 +1,5 @@
+use sliding_game_2::frontend::start_game_frontend;
+
+fn main() {
+    start_game_frontend();
+}
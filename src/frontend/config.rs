@@ -0,0 +1,378 @@
+/// Settings of the graphical frontend
+///
+/// The console frontend reads them too, for the directory the profiles fall back to and the profile it offers
+/// first, so that both frontends share the progress of the player.
+/// They are stored as TOML in the platform config directory (e.g. `~/.config/sliding_game_2/settings.toml`
+/// on Linux) and when the platform has none, in a fallback file chosen by the caller. Every setting has a
+/// default, so the file only needs the ones that differ, and a setting that is not valid falls back to its
+/// default without affecting the others. Keys are named like Bevy's key codes, e.g. `KeyW`,
+/// `ArrowUp` or `ShiftLeft`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_path_to_error::{Path as FieldPath, Segment};
+use toml::{Table, Value};
+
+use crate::backend::io::write_atomic;
+use crate::backend::profile::{
+    DEFAULT_PROFILE, DEFAULT_PROFILE_FALLBACK_DIR, ProfileStore, is_valid_profile_name,
+};
+
+pub const CONFIG_FILE: &str = "settings.toml";
+pub const DEFAULT_CONFIG_FALLBACK: &str = "settings.toml";
+
+const MIN_WINDOW_WIDTH: u32 = 640;
+const MIN_WINDOW_HEIGHT: u32 = 480;
+const MAX_ANIMATION_SPEED: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Fills the screen without changing its video mode
+    Borderless,
+    Fullscreen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    pub mode: WindowMode,
+    /// Size of the window in logical pixels when it is not fullscreen
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            mode: WindowMode::Windowed,
+            width: 1600,
+            height: 900,
+        }
+    }
+}
+
+/// How the game scene shows inputs
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimationSettings {
+    /// Show the result of an input at once instead of animating it
+    pub instant: bool,
+    /// Multiplies the speed of all animations
+    pub speed: f32,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            instant: false,
+            speed: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Keys of every action in the game scene, an action can have several keys
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    /// Held together with a direction to slide instead of walking
+    pub slide: Vec<String>,
+    pub next_agent: Vec<String>,
+    pub undo: Vec<String>,
+    pub restart: Vec<String>,
+    pub toggle_animations: Vec<String>,
+    pub quit: Vec<String>,
+}
+
+fn keys(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| String::from(*name)).collect()
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            up: keys(&["KeyW", "ArrowUp"]),
+            down: keys(&["KeyS", "ArrowDown"]),
+            left: keys(&["KeyA", "ArrowLeft"]),
+            right: keys(&["KeyD", "ArrowRight"]),
+            slide: keys(&["ShiftLeft", "ShiftRight"]),
+            next_agent: keys(&["Tab"]),
+            undo: keys(&["KeyU", "Backspace"]),
+            restart: keys(&["KeyR"]),
+            toggle_animations: keys(&["KeyI"]),
+            quit: keys(&["Escape", "KeyQ"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with its name and keys, in the order the settings menu lists them
+    pub fn actions(&self) -> [(&'static str, &[String]); 10] {
+        [
+            ("Up", &self.up),
+            ("Down", &self.down),
+            ("Left", &self.left),
+            ("Right", &self.right),
+            ("Slide", &self.slide),
+            ("Next agent", &self.next_agent),
+            ("Undo", &self.undo),
+            ("Restart", &self.restart),
+            ("Animations", &self.toggle_animations),
+            ("Quit", &self.quit),
+        ]
+    }
+
+    /// Same as actions, for changing the keys
+    pub fn actions_mut(&mut self) -> [(&'static str, &mut Vec<String>); 10] {
+        [
+            ("Up", &mut self.up),
+            ("Down", &mut self.down),
+            ("Left", &mut self.left),
+            ("Right", &mut self.right),
+            ("Slide", &mut self.slide),
+            ("Next agent", &mut self.next_agent),
+            ("Undo", &mut self.undo),
+            ("Restart", &mut self.restart),
+            ("Animations", &mut self.toggle_animations),
+            ("Quit", &mut self.quit),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowSettings,
+    pub animation: AnimationSettings,
+    pub theme: Theme,
+    /// Ask before restarting a level with moves made
+    pub confirm_restart: bool,
+    pub keys: KeyBindings,
+    /// Profile the progress is kept in
+    pub profile: String,
+    /// Where the profiles are kept on platforms without a data directory
+    pub profile_fallback_dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            window: WindowSettings::default(),
+            animation: AnimationSettings::default(),
+            theme: Theme::default(),
+            confirm_restart: false,
+            keys: KeyBindings::default(),
+            profile: String::from(DEFAULT_PROFILE),
+            profile_fallback_dir: PathBuf::from(DEFAULT_PROFILE_FALLBACK_DIR),
+        }
+    }
+}
+
+impl Config {
+    /// The settings file in the platform config directory, or the fallback where the platform has none
+    pub fn locate(fallback: &Path) -> PathBuf {
+        match ProjectDirs::from("", "", env!("CARGO_PKG_NAME")) {
+            Some(dirs) => dirs.config_dir().join(CONFIG_FILE),
+            None => fallback.to_path_buf(),
+        }
+    }
+
+    /// Loads the settings together with what was wrong with them, a missing file gives the defaults. Settings
+    /// that are unknown or not valid are left out and the others are kept, only a file that cannot be read as
+    /// TOML is an error.
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), String> {
+        let text: String = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((Config::default(), vec![]));
+            }
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+        let mut table: Table = match text.parse::<Table>() {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Invalid settings {}: {}", path.display(), e)),
+        };
+
+        let mut problems: Vec<String> = vec![];
+        let mut config: Config = loop {
+            match serde_path_to_error::deserialize(Value::Table(table.clone())) {
+                Ok(c) => break c,
+                Err(e) => {
+                    problems.push(format!("{}: {}", e.path(), e.inner().message()));
+                    // Every round leaves out one more value, so this ends
+                    if !remove_value(&mut table, e.path()) {
+                        return Err(format!("Invalid settings {}: {}", path.display(), e));
+                    }
+                }
+            }
+        };
+        problems.extend(config.fall_back_to_defaults());
+        Ok((config, problems))
+    }
+
+    /// The profiles of the player, shared by all frontends
+    pub fn profile_store(&self) -> ProfileStore {
+        ProfileStore::locate(&self.profile_fallback_dir)
+    }
+
+    /// Puts the defaults back for the values out of range and says which they were
+    fn fall_back_to_defaults(&mut self) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+        if self.window.width < MIN_WINDOW_WIDTH || self.window.height < MIN_WINDOW_HEIGHT {
+            problems.push(format!(
+                "window: the window must be at least {}x{}",
+                MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT
+            ));
+            let default: WindowSettings = WindowSettings::default();
+            self.window.width = default.width;
+            self.window.height = default.height;
+        }
+        if !(self.animation.speed > 0.0 && self.animation.speed <= MAX_ANIMATION_SPEED) {
+            problems.push(format!(
+                "animation.speed: the animation speed must be above 0 and at most {}",
+                MAX_ANIMATION_SPEED
+            ));
+            self.animation.speed = AnimationSettings::default().speed;
+        }
+        if !is_valid_profile_name(&self.profile) {
+            problems.push(String::from(
+                "profile: a profile name may only use letters, digits, - and _",
+            ));
+            self.profile = String::from(DEFAULT_PROFILE);
+        }
+        problems
+    }
+
+    pub fn store(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent()
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            return Err(format!("Could not create {}: {}", dir.display(), e));
+        }
+        let text: String = match toml::to_string(self) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("Could not serialize the settings: {}", e)),
+        };
+        write_atomic(path, &text)
+    }
+}
+
+/// Removes the value at the path from the settings, false if there is no such value
+fn remove_value(table: &mut Table, path: &FieldPath) -> bool {
+    let segments: Vec<&Segment> = path.iter().collect();
+    let Some((last, parents)) = segments.split_last() else {
+        return false;
+    };
+
+    let mut value: Option<&mut Value> = None;
+    for segment in parents {
+        let next: Option<&mut Value> = match (value, segment) {
+            (None, Segment::Map { key }) => table.get_mut(key),
+            (Some(Value::Table(t)), Segment::Map { key }) => t.get_mut(key),
+            (Some(Value::Array(a)), Segment::Seq { index }) => a.get_mut(*index),
+            _ => None,
+        };
+        let Some(next) = next else {
+            return false;
+        };
+        value = Some(next);
+    }
+    match (value, last) {
+        (None, Segment::Map { key }) => table.remove(key).is_some(),
+        (Some(Value::Table(t)), Segment::Map { key }) => t.remove(key).is_some(),
+        (Some(Value::Array(a)), Segment::Seq { index }) if *index < a.len() => {
+            a.remove(*index);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_and_reject_bad_values() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("config_test_{}", std::process::id()));
+        let path: PathBuf = dir.join(CONFIG_FILE);
+        let load = |path: &Path| Config::load(path).unwrap().0;
+        assert_eq!(Config::load(&path).unwrap(), (Config::default(), vec![]));
+
+        let mut config: Config = Config {
+            theme: Theme::HighContrast,
+            ..Config::default()
+        };
+        config.animation.speed = 2.5;
+        config.keys.up = keys(&["KeyK"]);
+        config.store(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap(), (config, vec![]));
+
+        // Settings that are not given keep their defaults
+        fs::write(&path, "confirm_restart = true\n").unwrap();
+        assert_eq!(load(&path).keys, KeyBindings::default());
+        fs::write(&path, "profile_fallback_dir = \"saves\"\n").unwrap();
+        assert_eq!(load(&path).profile_fallback_dir, PathBuf::from("saves"));
+        assert_eq!(
+            Config::default().profile_fallback_dir,
+            PathBuf::from(DEFAULT_PROFILE_FALLBACK_DIR)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bad_settings_fall_back_one_by_one() {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("config_fallback_test_{}", std::process::id()));
+        let path: PathBuf = dir.join(CONFIG_FILE);
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(
+            &path,
+            "theme = \"purple\"\nconfirm_restart = true\ncolour = 1\nprofile = \"no way\"\n\
+             [window]\nwidth = 10\nmode = \"fullscreen\"\n\
+             [keys]\nup = [\"KeyK\", 3]\nundo = [\"KeyZ\"]\n",
+        )
+        .unwrap();
+        let (config, problems) = Config::load(&path).unwrap();
+        assert_eq!(config.theme, Theme::default());
+        assert!(config.confirm_restart);
+        assert_eq!(config.window.mode, WindowMode::Fullscreen);
+        assert_eq!(config.window.width, WindowSettings::default().width);
+        assert_eq!(config.keys.up, keys(&["KeyK"]));
+        assert_eq!(config.keys.undo, keys(&["KeyZ"]));
+        assert_eq!(config.profile, DEFAULT_PROFILE);
+        let fields: Vec<&str> = problems
+            .iter()
+            .map(|problem| problem.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            vec!["colour", "keys.up[1]", "theme", "window", "profile"]
+        );
+
+        // A file that is not TOML at all cannot be kept apart from the defaults
+        fs::write(&path, "theme = \n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
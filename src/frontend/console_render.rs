@@ -0,0 +1,329 @@
+/// Drawing boards as text for terminals and files
+///
+/// The full style draws every square as a box that has a fixed slot for each agent, so any number of
+/// agents fits, and shows the goal count on goal blocks. The compact style uses one character per square
+/// for boards too big for the screen. Colour and the box-drawing characters can both be turned off.
+use colored::{Color, Colorize};
+
+use crate::backend::Board;
+use crate::backend::utils_backend::{AgentID, Coordinate, Index, TextureType};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderStyle {
+    #[default]
+    Full,
+    /// One character per square
+    Compact,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub style: RenderStyle,
+    pub color: bool,
+    /// Only use ASCII characters, for terminals without box-drawing characters
+    pub ascii: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            style: RenderStyle::Full,
+            color: true,
+            ascii: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Full style without colour, for files and machine-readable output
+    pub fn plain() -> Self {
+        RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        }
+    }
+}
+
+const AGENT_COLORS: [Color; 12] = [
+    Color::Red,
+    Color::Green,
+    Color::Blue,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightBlue,
+    Color::BrightYellow,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+];
+
+pub(crate) fn agent_color(agent: AgentID) -> Color {
+    AGENT_COLORS[agent as usize % AGENT_COLORS.len()]
+}
+
+/// Character the block is drawn with and its colour
+pub(crate) fn block_glyph(texture: TextureType, ascii: bool) -> (char, Color) {
+    match (texture, ascii) {
+        (TextureType::None, _) => (' ', Color::White),
+        (TextureType::BasicBlock, false) => ('▒', Color::White),
+        (TextureType::BasicBlock, true) => ('#', Color::White),
+        (TextureType::BasicImpassable, false) => ('█', Color::BrightBlack),
+        (TextureType::BasicImpassable, true) => ('X', Color::BrightBlack),
+        (TextureType::Goal(_), false) => ('▚', Color::Yellow),
+        (TextureType::Goal(_), true) => ('*', Color::Yellow),
+    }
+}
+
+pub(crate) fn paint(text: &str, color: Color, options: RenderOptions) -> String {
+    if options.color {
+        text.color(color).to_string()
+    } else {
+        String::from(text)
+    }
+}
+
+pub(crate) fn paint_agent(text: &str, agent: AgentID, options: RenderOptions) -> String {
+    if options.color {
+        text.color(agent_color(agent)).bold().to_string()
+    } else {
+        String::from(text)
+    }
+}
+
+pub fn render_board(board: &Board, options: RenderOptions) -> String {
+    match options.style {
+        RenderStyle::Full => render_full(board, options),
+        RenderStyle::Compact => render_compact(board, options),
+    }
+}
+
+/// Size of the squares in the full style, which depends only on the number of agents so that it stays the
+/// same while playing
+struct CellLayout {
+    /// Agent slots per line
+    per_line: usize,
+    /// Width of the widest agent ID
+    label_width: usize,
+    agent_lines: usize,
+    inner_width: usize,
+    inner_height: usize,
+}
+
+impl CellLayout {
+    fn new(num_agents: AgentID) -> Self {
+        let num_agents: usize = (num_agents as usize).max(1);
+        let per_line: usize = num_agents.isqrt() + usize::from(!is_square(num_agents));
+        let label_width: usize = (num_agents - 1).to_string().len();
+        let agent_lines: usize = num_agents.div_ceil(per_line);
+
+        CellLayout {
+            per_line,
+            label_width,
+            agent_lines,
+            inner_width: (per_line * (label_width + 1) - 1).max(3),
+            // One more line for the goal count
+            inner_height: (agent_lines + 1).max(3),
+        }
+    }
+}
+
+fn is_square(num: usize) -> bool {
+    num.isqrt() * num.isqrt() == num
+}
+
+/// The lines inside the border of a square
+fn cell_inside(
+    board: &Board,
+    coordinate: Coordinate,
+    layout: &CellLayout,
+    options: RenderOptions,
+) -> Vec<String> {
+    let block = board.read_block(coordinate);
+    let agents = block.get_agents();
+    let blank_line: String = " ".repeat(layout.inner_width);
+
+    let mut lines: Vec<String> = vec![];
+    for line in 0..layout.agent_lines {
+        let mut text: String = String::new();
+        let mut width: usize = 0;
+        for slot in 0..layout.per_line {
+            let agent: usize = line * layout.per_line + slot;
+            if slot > 0 {
+                text.push(' ');
+                width += 1;
+            }
+            let label: String = format!("{:>width$}", agent, width = layout.label_width);
+            if agent <= AgentID::MAX as usize && agents.contains(&(agent as AgentID)) {
+                text.push_str(&paint_agent(&label, agent as AgentID, options));
+            } else {
+                text.push_str(&" ".repeat(layout.label_width));
+            }
+            width += layout.label_width;
+        }
+        text.push_str(&" ".repeat(layout.inner_width - width));
+        lines.push(text);
+    }
+
+    let goal_line: String = match block.get_texture() {
+        TextureType::Goal(num) => {
+            let text: String = format!(
+                "{:^width$}",
+                format!("{}/{}", agents.len(), num),
+                width = layout.inner_width
+            );
+            let color: Color = if agents.len() == num as usize {
+                Color::Green
+            } else {
+                Color::Yellow
+            };
+            paint(&text, color, options)
+        }
+        _ => blank_line.clone(),
+    };
+    lines.insert(layout.agent_lines.div_ceil(2), goal_line);
+
+    while lines.len() < layout.inner_height {
+        lines.push(blank_line.clone());
+    }
+    lines
+}
+
+fn render_full(board: &Board, options: RenderOptions) -> String {
+    let (x_size, y_size): (Index, Index) = board.get_dimensions();
+    let layout: CellLayout = CellLayout::new(board.get_num_agents());
+    let mut out: String = String::new();
+
+    for y in 0..y_size {
+        let mut rows: Vec<String> = vec![String::new(); layout.inner_height + 2];
+        for x in 0..x_size {
+            let coordinate: Coordinate = Coordinate { x, y };
+            let texture: TextureType = board.read_block(coordinate).get_texture();
+            let (glyph, color) = block_glyph(texture, options.ascii);
+            let edge: String = paint(&glyph.to_string(), color, options);
+            let border: String = paint(
+                &glyph.to_string().repeat(layout.inner_width + 2),
+                color,
+                options,
+            );
+
+            rows[0].push_str(&border);
+            for (line, inside) in cell_inside(board, coordinate, &layout, options)
+                .iter()
+                .enumerate()
+            {
+                rows[line + 1].push_str(&edge);
+                rows[line + 1].push_str(inside);
+                rows[line + 1].push_str(&edge);
+            }
+            rows[layout.inner_height + 1].push_str(&border);
+        }
+        for row in rows {
+            out.push_str(&row);
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Agents with IDs from 10 on are shown as letters, as long as there are letters left
+fn agent_char(agent: AgentID) -> char {
+    char::from_digit(agent as u32, 36).unwrap_or('@')
+}
+
+fn render_compact(board: &Board, options: RenderOptions) -> String {
+    let (x_size, y_size): (Index, Index) = board.get_dimensions();
+    let mut out: String = String::new();
+
+    for y in 0..y_size {
+        for x in 0..x_size {
+            let block = board.read_block(Coordinate { x, y });
+            let mut agents: Vec<AgentID> = block.get_agents().into_iter().collect();
+            agents.sort();
+
+            match agents[..] {
+                [] => {
+                    let (glyph, color) = match block.get_texture() {
+                        TextureType::None if options.ascii => ('.', Color::White),
+                        TextureType::None => ('·', Color::White),
+                        texture => block_glyph(texture, options.ascii),
+                    };
+                    out.push_str(&paint(&glyph.to_string(), color, options));
+                }
+                [agent] => {
+                    out.push_str(&paint_agent(&agent_char(agent).to_string(), agent, options))
+                }
+                _ => out.push_str(&paint("&", Color::White, options)),
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use toml::Table;
+
+    /// A row of three blocks with all agents on the middle one
+    fn crowded_board(num_agents: usize) -> Board {
+        let mut level: String = format!(
+            "format_version = 2\nx_size = 3\ny_size = 1\nnum_agents = {}\n\
+             [victory_conditions]\nBlocksSatisfied = 1\n\
+             [block.0.0]\ntype = \"basic_block\"\n\
+             [block.1.0]\ntype = \"basic_block\"\ntags.num_goal_agents = 2\n\
+             [block.2.0]\ntype = \"basic_block\"\ntags.passable = false\n",
+            num_agents
+        );
+        for agent in 0..num_agents {
+            level.push_str(&format!("[agent.{}]\nstart = [1, 0]\n", agent));
+        }
+        match Board::from_table(&level.parse::<Table>().unwrap()) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn every_agent_gets_a_slot() {
+        let board: Board = crowded_board(12);
+        let text: String = render_board(&board, RenderOptions::plain());
+
+        // Every agent is drawn exactly once, next to the goal label with the agents on it out of the needed
+        let mut labels: Vec<&str> = text
+            .split(|c: char| !c.is_ascii_digit() && c != '/')
+            .filter(|token| !token.is_empty())
+            .collect();
+        labels.sort_by_key(|label| label.parse::<usize>().unwrap_or(usize::MAX));
+        let mut expected: Vec<String> = (0..12).map(|agent| agent.to_string()).collect();
+        expected.push(String::from("12/2"));
+        assert_eq!(labels, expected);
+        assert!(render_board(&crowded_board(1), RenderOptions::plain()).contains("1/2"));
+        // All lines of a row of squares have the same width
+        let widths: Vec<usize> = text.lines().map(|line| line.chars().count()).collect();
+        assert!(widths.iter().all(|width| *width == widths[0]));
+    }
+
+    #[test]
+    fn compact_and_ascii_styles() {
+        let board: Board = crowded_board(1);
+        let compact: RenderOptions = RenderOptions {
+            style: RenderStyle::Compact,
+            color: false,
+            ascii: true,
+        };
+        assert_eq!(render_board(&board, compact), "#0X\n");
+        assert_eq!(render_board(&crowded_board(2), compact), "#&X\n");
+
+        let ascii: RenderOptions = RenderOptions {
+            ascii: true,
+            ..RenderOptions::plain()
+        };
+        assert!(render_board(&board, ascii).is_ascii());
+    }
+}
@@ -0,0 +1,467 @@
+/// Full-screen terminal level editor with keyboard input in raw mode
+///
+/// The arrow keys move the cursor, Space cycles the block under it through air, block, wall and goal and
+/// 1-4 paint one of them directly. t edits the tags of the block in a prompt, Tab chooses an agent, a moves
+/// its start to the cursor, n adds an agent there and x removes the chosen one. v edits the victory
+/// conditions and z the size of the board. u undoes, r redoes, w writes the level file and q or Esc quits.
+/// The problems the editor finds in the level are listed below the board after every edit.
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+use colored::Color;
+use crossterm::cursor::MoveTo;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{queue, style::Print};
+
+use crate::backend::editor::{LevelEditor, PaletteBlock, block_texture};
+use crate::backend::level_file::{BlockTags, LevelFile, SlideSpec};
+use crate::backend::utils_backend::{AgentID, Coordinate, Direction, Index};
+use crate::frontend::console_render::{RenderOptions, block_glyph, paint, paint_agent};
+use crate::frontend::play_level::TerminalGuard;
+
+const HELP_LINES: [&str; 3] = [
+    "arrows move  Space cycle block  1-4 air/block/wall/goal  t tags  z size  v victory",
+    "Tab next agent  a move agent here  n new agent here  x remove agent",
+    "u undo  r redo  w write  q quit",
+];
+
+enum Command {
+    Cursor(Direction),
+    Cycle,
+    Paint(PaletteBlock),
+    Tags,
+    Size,
+    Victory,
+    NextAgent,
+    PlaceAgent,
+    AddAgent,
+    RemoveAgent,
+    Undo,
+    Redo,
+    Write,
+    Quit,
+}
+
+fn key_command(key: KeyEvent) -> Option<Command> {
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Command::Quit),
+        KeyCode::Up => Some(Command::Cursor(Direction::Up)),
+        KeyCode::Down => Some(Command::Cursor(Direction::Down)),
+        KeyCode::Left => Some(Command::Cursor(Direction::Left)),
+        KeyCode::Right => Some(Command::Cursor(Direction::Right)),
+        KeyCode::Char(' ') => Some(Command::Cycle),
+        KeyCode::Char(c @ '1'..='4') => Some(Command::Paint(
+            PaletteBlock::ALL[c.to_digit(10).unwrap() as usize - 1],
+        )),
+        KeyCode::Char('t') => Some(Command::Tags),
+        KeyCode::Char('z') => Some(Command::Size),
+        KeyCode::Char('v') => Some(Command::Victory),
+        KeyCode::Tab => Some(Command::NextAgent),
+        KeyCode::Char('a') => Some(Command::PlaceAgent),
+        KeyCode::Char('n') => Some(Command::AddAgent),
+        KeyCode::Char('x') => Some(Command::RemoveAgent),
+        KeyCode::Char('u') => Some(Command::Undo),
+        KeyCode::Char('r') => Some(Command::Redo),
+        KeyCode::Char('w') => Some(Command::Write),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/// The tags as the tag prompt reads them
+fn tags_text(tags: &BlockTags) -> String {
+    let slide: String = match tags.slide {
+        None | Some(SlideSpec::Fast) => String::from("fast"),
+        Some(SlideSpec::Squares(0)) => String::from("none"),
+        Some(SlideSpec::Squares(squares)) => squares.to_string(),
+    };
+    format!(
+        "passable={} slide={} goal={}",
+        yes_no(tags.passable.unwrap_or(true)),
+        slide,
+        tags.num_goal_agents.unwrap_or(0)
+    )
+}
+
+/// Reads key=value pairs, each of them changes one tag and the tags not given keep their value
+fn parse_tags(text: &str, mut tags: BlockTags) -> Result<BlockTags, String> {
+    for pair in text.split_whitespace() {
+        let Some((key, value)) = pair.split_once('=') else {
+            return Err(format!("{} is not of the form key=value", pair));
+        };
+        match (key, value) {
+            ("passable", "yes" | "true") => tags.passable = None,
+            ("passable", "no" | "false") => tags.passable = Some(false),
+            ("slide", "fast") => tags.slide = None,
+            ("slide", "none") => tags.slide = Some(SlideSpec::Squares(0)),
+            ("slide", squares) => match squares.parse::<u8>() {
+                Ok(squares) => tags.slide = Some(SlideSpec::Squares(squares)),
+                Err(_) => return Err(String::from("slide is fast, none or a number of squares")),
+            },
+            ("goal", agents) => match agents.parse::<u8>() {
+                Ok(0) => tags.num_goal_agents = None,
+                Ok(agents) => tags.num_goal_agents = Some(agents),
+                Err(_) => return Err(String::from("goal is the number of agents the goal needs")),
+            },
+            _ => return Err(format!("Unknown tag or value {}", pair)),
+        }
+    }
+    Ok(tags)
+}
+
+/// Reads the size of the board, e.g. 7x5
+fn parse_size(text: &str) -> Result<(Index, Index), String> {
+    let sizes: Vec<Result<Index, _>> = text
+        .split(|c: char| c == 'x' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<Index>())
+        .collect();
+    match sizes[..] {
+        [Ok(x_size), Ok(y_size)] => Ok((x_size, y_size)),
+        _ => Err(format!("{} is not a size like 7x5", text.trim())),
+    }
+}
+
+fn victory_text(level: &LevelFile, goals: u8) -> String {
+    let must_finish: String = match level.num_agents_must_finish {
+        Some(agents) => agents.to_string(),
+        None => String::from("all"),
+    };
+    format!("goals={} finish={}", goals, must_finish)
+}
+
+/// Applies the victory conditions of the prompt, the ones that stay the same are not edited
+fn apply_victory(editor: &mut LevelEditor, text: &str) -> Result<(), String> {
+    for pair in text.split_whitespace() {
+        match pair.split_once('=') {
+            Some(("goals", goals)) => match goals.parse::<u8>() {
+                Ok(goals) if goals != editor.goals_to_satisfy() => {
+                    editor.set_goals_to_satisfy(goals)?
+                }
+                Ok(_) => (),
+                Err(_) => return Err(String::from("goals is a number")),
+            },
+            Some(("finish", agents)) => {
+                let agents: Option<AgentID> = match agents {
+                    "all" => None,
+                    agents => match agents.parse::<AgentID>() {
+                        Ok(agents) => Some(agents),
+                        Err(_) => return Err(String::from("finish is all or a number")),
+                    },
+                };
+                if agents != editor.level().num_agents_must_finish {
+                    editor.set_agents_must_finish(agents)?;
+                }
+            }
+            _ => return Err(format!("Unknown victory condition {}", pair)),
+        }
+    }
+    Ok(())
+}
+
+/// The squares of the board, three characters each with brackets around the cursor
+fn grid_lines(
+    editor: &LevelEditor,
+    cursor: Coordinate,
+    selected: AgentID,
+    options: RenderOptions,
+) -> Vec<String> {
+    let (x_size, y_size): (Index, Index) = editor.size();
+    let (corners, horizontal, vertical): ([char; 4], char, char) = if options.ascii {
+        (['+', '+', '+', '+'], '-', '|')
+    } else {
+        (['┌', '┐', '└', '┘'], '─', '│')
+    };
+    let border: String = String::from(horizontal).repeat(3 * x_size as usize);
+
+    let mut lines: Vec<String> = vec![format!("{}{}{}", corners[0], border, corners[1])];
+    for y in 0..y_size {
+        let mut line: String = String::from(vertical);
+        for x in 0..x_size {
+            let square: Coordinate = Coordinate { x, y };
+            let (left, right): (&str, &str) = if square == cursor {
+                ("[", "]")
+            } else {
+                (" ", " ")
+            };
+            let agents: Vec<AgentID> = editor.agents_at(square);
+            let middle: String = match agents.first() {
+                // The chosen agent is shown when it shares the square, * stands for IDs above 9
+                Some(first) => {
+                    let agent: AgentID = if agents.contains(&selected) {
+                        selected
+                    } else {
+                        *first
+                    };
+                    let label: String = match agent {
+                        0..=9 => agent.to_string(),
+                        _ => String::from("*"),
+                    };
+                    paint_agent(&label, agent, options)
+                }
+                None => match block_glyph(block_texture(editor.block(square)), options.ascii) {
+                    (' ', _) => paint(
+                        if options.ascii { "." } else { "·" },
+                        Color::BrightBlack,
+                        options,
+                    ),
+                    (glyph, color) => paint(&glyph.to_string(), color, options),
+                },
+            };
+            line.push_str(&format!("{}{}{}", left, middle, right));
+        }
+        line.push(vertical);
+        lines.push(line);
+    }
+    lines.push(format!("{}{}{}", corners[2], border, corners[3]));
+    lines
+}
+
+/// Everything on the screen but the prompt
+fn screen_lines(
+    editor: &LevelEditor,
+    path: &str,
+    cursor: Coordinate,
+    selected: AgentID,
+    warnings: &[String],
+    message: &str,
+    options: RenderOptions,
+) -> Vec<String> {
+    let level: &LevelFile = editor.level();
+    let mut lines: Vec<String> = vec![format!(
+        "{}{}  {}x{}",
+        path,
+        if editor.is_modified() { " *" } else { "" },
+        level.x_size,
+        level.y_size
+    )];
+    lines.push(String::new());
+    lines.extend(grid_lines(editor, cursor, selected, options));
+    lines.push(String::new());
+
+    let block: PaletteBlock = PaletteBlock::of(editor.block(cursor));
+    let square: String = match editor.block(cursor) {
+        Some(spec) => format!(
+            "{}: {}",
+            block.name(),
+            tags_text(&spec.tags.clone().unwrap_or_default())
+        ),
+        None => String::from(block.name()),
+    };
+    lines.push(format!("Square ({}, {}) {}", cursor.x, cursor.y, square));
+    let start: String = match editor.agent_start(selected) {
+        Some(start) => format!("({}, {})", start.x, start.y),
+        None => String::from("nowhere"),
+    };
+    lines.push(format!(
+        "Agent {} of {} starts at {}",
+        selected, level.num_agents, start
+    ));
+    lines.push(format!(
+        "Victory: {}",
+        victory_text(level, editor.goals_to_satisfy())
+    ));
+    lines.push(String::new());
+
+    if warnings.is_empty() {
+        lines.push(paint("No problems found", Color::Green, options));
+    }
+    for warning in warnings {
+        lines.push(paint(&format!("! {}", warning), Color::Yellow, options));
+    }
+    lines.push(String::from(message));
+    lines.extend(HELP_LINES.iter().map(|line| String::from(*line)));
+    lines
+}
+
+fn draw(out: &mut impl Write, lines: &[String], prompt: Option<&str>) -> io::Result<()> {
+    queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+    // Raw mode does not return the cursor to the start of the line on a line feed
+    for line in lines {
+        queue!(out, Print(line), Print("\r\n"))?;
+    }
+    if let Some(prompt) = prompt {
+        queue!(out, Print(prompt), Print("_"))?;
+    }
+    out.flush()
+}
+
+/// Reads a line below the screen starting from the given text, None if Esc cancels it
+fn prompt(
+    out: &mut impl Write,
+    lines: &[String],
+    question: &str,
+    initial: String,
+) -> io::Result<Option<String>> {
+    let mut text: String = initial;
+    loop {
+        draw(out, lines, Some(&format!("{}: {}", question, text)))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(text)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Char(c) => text.push(c),
+            _ => (),
+        }
+    }
+}
+
+/// Edits the level file at the path, or a new level saved there if it does not exist yet
+pub fn edit_level(path: &str, options: RenderOptions) -> Result<(), String> {
+    let editor: LevelEditor = if Path::new(path).exists() {
+        LevelEditor::load(path)?
+    } else {
+        LevelEditor::new()
+    };
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(String::from("The level editor needs a terminal"));
+    }
+    match run_editor(editor, path, options) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("The terminal failed: {}", e)),
+    }
+}
+
+fn run_editor(mut editor: LevelEditor, path: &str, options: RenderOptions) -> io::Result<()> {
+    let _guard: TerminalGuard = TerminalGuard::enter()?;
+    let mut out = io::stdout();
+
+    let mut cursor: Coordinate = Coordinate { x: 0, y: 0 };
+    let mut selected: AgentID = 0;
+    let mut message: String = String::new();
+    let mut quit_pending: bool = false;
+    // The warnings need a search, so they are only found again when the level changed
+    let mut checked: Option<LevelFile> = None;
+    let mut warnings: Vec<String> = vec![];
+
+    loop {
+        if checked.as_ref() != Some(editor.level()) {
+            warnings = editor.warnings();
+            checked = Some(editor.level().clone());
+        }
+        let (x_size, y_size): (Index, Index) = editor.size();
+        cursor = Coordinate {
+            x: cursor.x.min(x_size - 1),
+            y: cursor.y.min(y_size - 1),
+        };
+        selected = selected.min(editor.level().num_agents - 1);
+
+        let lines: Vec<String> = screen_lines(
+            &editor, path, cursor, selected, &warnings, &message, options,
+        );
+        draw(&mut out, &lines, None)?;
+        // Other events such as a resize only need the redraw at the start of the loop
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        message.clear();
+        let command: Option<Command> = key_command(key);
+        let quitting: bool = matches!(command, Some(Command::Quit));
+        let result: Result<(), String> = match command {
+            Some(Command::Quit) if !editor.is_modified() || quit_pending => return Ok(()),
+            Some(Command::Quit) => Err(String::from(
+                "There are unsaved edits, press q again to quit without saving",
+            )),
+            Some(Command::Cursor(direction)) => {
+                let next: Coordinate = cursor.move_direction(direction);
+                if editor.in_bounds(next) {
+                    cursor = next;
+                }
+                Ok(())
+            }
+            Some(Command::Cycle) => {
+                let block: PaletteBlock = PaletteBlock::of(editor.block(cursor));
+                let index: usize = PaletteBlock::ALL.iter().position(|b| *b == block).unwrap();
+                let next: PaletteBlock = PaletteBlock::ALL[(index + 1) % PaletteBlock::ALL.len()];
+                editor.paint(cursor, next, false).map(|_| ())
+            }
+            Some(Command::Paint(block)) => editor.paint(cursor, block, false).map(|_| ()),
+            Some(Command::Tags) => match editor.block(cursor) {
+                None => Err(String::from("Air has no tags, paint a block first")),
+                Some(spec) => {
+                    let tags: BlockTags = spec.tags.clone().unwrap_or_default();
+                    match prompt(&mut out, &lines, "Tags", tags_text(&tags))? {
+                        Some(text) => {
+                            parse_tags(&text, tags).and_then(|tags| editor.set_tags(cursor, tags))
+                        }
+                        None => Ok(()),
+                    }
+                }
+            },
+            Some(Command::Size) => {
+                let size: String = format!("{}x{}", x_size, y_size);
+                match prompt(&mut out, &lines, "Size", size)? {
+                    Some(text) => {
+                        parse_size(&text).and_then(|(x_size, y_size)| editor.resize(x_size, y_size))
+                    }
+                    None => Ok(()),
+                }
+            }
+            Some(Command::Victory) => {
+                let victory: String = victory_text(editor.level(), editor.goals_to_satisfy());
+                match prompt(&mut out, &lines, "Victory", victory)? {
+                    Some(text) => apply_victory(&mut editor, &text),
+                    None => Ok(()),
+                }
+            }
+            Some(Command::NextAgent) => {
+                selected = (selected + 1) % editor.level().num_agents;
+                Ok(())
+            }
+            Some(Command::PlaceAgent) => editor.place_agent(selected, cursor),
+            Some(Command::AddAgent) => editor.add_agent(cursor).map(|agent| selected = agent),
+            Some(Command::RemoveAgent) => editor.remove_agent(selected),
+            Some(Command::Undo) if !editor.undo() => Err(String::from("Nothing to undo")),
+            Some(Command::Redo) if !editor.redo() => Err(String::from("Nothing to redo")),
+            Some(Command::Write) => editor
+                .save(Path::new(path))
+                .map(|()| message = format!("Wrote {}", path)),
+            Some(Command::Undo | Command::Redo) | None => Ok(()),
+        };
+        if let Err(msg) = result {
+            message = msg;
+        }
+        quit_pending = quitting;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_read_tags_and_sizes() {
+        let tags: BlockTags = parse_tags("goal=2 slide=3", BlockTags::default()).unwrap();
+        assert_eq!(tags.num_goal_agents, Some(2));
+        assert_eq!(tags.slide, Some(SlideSpec::Squares(3)));
+        assert_eq!(
+            parse_tags(&tags_text(&tags), BlockTags::default()),
+            Ok(tags)
+        );
+        assert!(parse_tags("passable=maybe", BlockTags::default()).is_err());
+
+        assert_eq!(parse_size("7x5"), Ok((7, 5)));
+        assert_eq!(parse_size(" 3 4 "), Ok((3, 4)));
+        assert!(parse_size("7").is_err());
+    }
+}
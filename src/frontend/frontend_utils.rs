@@ -1,12 +1,169 @@
-use bevy::{color::Color, state::state::States};
+use std::path::PathBuf;
+use std::time::Duration;
 
-const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+use bevy::{
+    color::Color, ecs::system::Resource, input::keyboard::KeyCode, log::error, state::state::States,
+};
 
-const SCREEN_WIDTH: u32 = 1600;
+use super::config::{Config, Theme};
+use crate::backend::Board;
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash, States)]
 pub enum FrontendState {
     #[default]
     MainMenu,
     LevelSelectMenu,
+    Playing,
+    Editor,
+}
+
+/// The settings in use and the file they are saved to
+#[derive(Resource)]
+pub struct Settings {
+    pub config: Config,
+    pub path: PathBuf,
+    /// What was wrong with the file when it was loaded, shown in the settings menu
+    pub notice: Option<String>,
+    /// The file could not be loaded at all, so it is not saved over and the changes only last until the game
+    /// is closed
+    pub read_only: bool,
+}
+
+impl Settings {
+    /// Writes the settings to their file, a failure is only logged as the settings stay in use
+    pub fn save(&mut self) {
+        if self.read_only {
+            return;
+        }
+        match self.config.store(&self.path) {
+            // The values that were not valid are not in the file any more
+            Ok(()) => self.notice = None,
+            Err(msg) => error!("Could not save the settings. {}", msg),
+        }
+    }
+}
+
+/// Colours of the board and of text drawn straight on the background
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    pub background: Color,
+    pub text: Color,
+    pub dim_text: Color,
+    pub basic_block: Color,
+    pub impassable: Color,
+    pub goal: Color,
+    pub goal_reached: Color,
+    pub selection: Color,
+}
+
+impl Palette {
+    pub fn of(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Palette {
+                background: Color::srgb(0.17, 0.17, 0.18),
+                text: Color::srgb(1.0, 1.0, 1.0),
+                dim_text: Color::srgb(0.6, 0.6, 0.6),
+                basic_block: Color::srgb(0.55, 0.55, 0.6),
+                impassable: Color::srgb(0.2, 0.2, 0.22),
+                goal: Color::srgb(0.8, 0.65, 0.2),
+                goal_reached: Color::srgb(0.3, 0.75, 0.3),
+                selection: Color::srgb(1.0, 1.0, 1.0),
+            },
+            Theme::Light => Palette {
+                background: Color::srgb(0.92, 0.92, 0.9),
+                text: Color::srgb(0.1, 0.1, 0.1),
+                dim_text: Color::srgb(0.4, 0.4, 0.4),
+                basic_block: Color::srgb(0.7, 0.72, 0.78),
+                impassable: Color::srgb(0.35, 0.35, 0.38),
+                goal: Color::srgb(0.9, 0.7, 0.25),
+                goal_reached: Color::srgb(0.35, 0.7, 0.35),
+                selection: Color::srgb(0.0, 0.0, 0.0),
+            },
+            Theme::HighContrast => Palette {
+                background: Color::srgb(0.0, 0.0, 0.0),
+                text: Color::srgb(1.0, 1.0, 1.0),
+                dim_text: Color::srgb(0.85, 0.85, 0.85),
+                basic_block: Color::srgb(0.85, 0.85, 0.85),
+                impassable: Color::srgb(0.3, 0.1, 0.1),
+                goal: Color::srgb(1.0, 0.85, 0.0),
+                goal_reached: Color::srgb(0.0, 1.0, 0.3),
+                selection: Color::srgb(1.0, 0.0, 1.0),
+            },
+        }
+    }
+}
+
+/// Keys that can be bound to an action, the digits stay reserved for choosing an agent
+const BINDABLE_KEYS: [KeyCode; 44] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Escape,
+    KeyCode::Home,
+    KeyCode::End,
+];
+
+/// Name of a key in the settings file, the same as the name of its key code
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// The key with this name, if it can be bound
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|key| key_name(*key) == name)
+}
+
+/// Short name of a key for the screen, e.g. W for KeyW
+pub fn key_label(name: &str) -> &str {
+    name.strip_prefix("Key").unwrap_or(name)
+}
+
+/// The level the game scene plays next, inserted before switching to FrontendState::Playing
+#[derive(Resource)]
+pub struct LevelToPlay {
+    pub board: Board,
+    /// Pack level ID for recording progress in the profile, levels from elsewhere have none
+    pub level_id: Option<String>,
+    /// Time already played on a resumed game
+    pub played_before: Duration,
+    /// Where the game scene goes once the level is over or left
+    pub return_to: FrontendState,
 }
@@ -0,0 +1,268 @@
+/// Animations of the game scene
+///
+/// An input is shown step by step from the action log of the board. Walking agents and sliding blocks,
+/// with the agents riding on them, move one square at a time, blocks leaving the board fall and fade out,
+/// and goals completed by the input light up. Once the last step is shown the board is drawn again from
+/// its actual state.
+use std::collections::VecDeque;
+
+use bevy::math::curve::{Curve, EaseFunction, EasingCurve};
+use bevy::prelude::*;
+
+use super::{BoardChanged, CELL_SIZE, Command, Game, OnGameScene, cell_position};
+use crate::backend::utils_backend::{
+    AgentID, Coordinate, Index, OUT_OF_BOUND, SlideType, TextureType,
+};
+use crate::backend::{ActionLog, ActionStep, Board};
+use crate::frontend::frontend_utils::{Palette, Settings};
+
+/// Seconds to walk to a neighbouring square
+const WALK_TIME: f32 = 0.12;
+/// Seconds per square of a fast slide
+const FAST_SLIDE_TIME: f32 = 0.06;
+/// Seconds per square of a slow slide, multiplied by how slow it is
+const SLOW_SLIDE_TIME: f32 = 0.1;
+const FALL_TIME: f32 = 0.35;
+const GOAL_EFFECT_TIME: f32 = 0.5;
+/// Further commands given during an animation are dropped
+const MAX_QUEUED_COMMANDS: usize = 3;
+
+/// The square an entity of the board belongs to, it moves with the contents of that square
+#[derive(Component)]
+pub(super) struct CellContents(pub Coordinate);
+
+#[derive(Component)]
+pub(super) struct AgentSprite(pub AgentID);
+
+/// Moves, scales and optionally fades an entity over time
+#[derive(Component)]
+pub(super) struct Tween {
+    start: Vec3,
+    end: Vec3,
+    start_scale: f32,
+    end_scale: f32,
+    fade: bool,
+    ease: EaseFunction,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Tween {
+    fn movement(start: Vec3, end: Vec3, ease: EaseFunction, duration: f32) -> Self {
+        Tween {
+            start,
+            end,
+            start_scale: 1.0,
+            end_scale: 1.0,
+            fade: false,
+            ease,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+}
+
+/// Shown around a goal that an input completed, it does not hold up further inputs
+#[derive(Component)]
+pub(super) struct GoalEffect;
+
+/// Everything run_tweens changes about an entity
+type TweenedEntity = (
+    Entity,
+    &'static mut Tween,
+    &'static mut Transform,
+    Option<&'static mut Sprite>,
+    Option<&'static mut TextColor>,
+    Has<GoalEffect>,
+);
+
+#[derive(Resource, Default)]
+pub(super) struct Animation {
+    steps: VecDeque<ActionStep>,
+    /// Goals that were satisfied before the input, the others that are satisfied after it light up
+    goals_before: Vec<Coordinate>,
+    running: bool,
+    queued: VecDeque<Command>,
+}
+
+impl Animation {
+    pub(super) fn start(&mut self, log: ActionLog, goals_before: Vec<Coordinate>) {
+        self.steps = log.steps.into();
+        self.goals_before = goals_before;
+        self.running = true;
+    }
+
+    pub(super) fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub(super) fn queue(&mut self, command: Command) {
+        if self.queued.len() < MAX_QUEUED_COMMANDS {
+            self.queued.push_back(command);
+        }
+    }
+
+    pub(super) fn next_queued(&mut self) -> Option<Command> {
+        self.queued.pop_front()
+    }
+}
+
+/// Goals with exactly as many agents on them as they need
+pub(super) fn satisfied_goals(board: &Board) -> Vec<Coordinate> {
+    let (x_size, y_size): (Index, Index) = board.get_dimensions();
+    let mut ret: Vec<Coordinate> = vec![];
+
+    for y in 0..y_size {
+        for x in 0..x_size {
+            let coordinate: Coordinate = Coordinate { x, y };
+            let block = board.read_block(coordinate);
+            if let TextureType::Goal(num) = block.get_texture()
+                && block.get_agents().len() == num as usize
+            {
+                ret.push(coordinate);
+            }
+        }
+    }
+    ret
+}
+
+/// Duration and easing of a block moving the given number of squares
+fn slide_timing(slide: SlideType, squares: f32) -> (f32, EaseFunction) {
+    match slide {
+        SlideType::SlowSlide(slowness) => (
+            SLOW_SLIDE_TIME * squares * (slowness.max(1) as f32),
+            EaseFunction::SineInOut,
+        ),
+        SlideType::FastSlide | SlideType::NoSlide => {
+            (FAST_SLIDE_TIME * squares, EaseFunction::Linear)
+        }
+    }
+}
+
+fn offset(from: Coordinate, to: Coordinate) -> Vec3 {
+    (cell_position(to) - cell_position(from)).extend(0.0)
+}
+
+/// Starts the next step once the previous one is over and redraws the board after the last one
+pub(super) fn play_steps(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    game: Res<Game>,
+    mut animation: ResMut<Animation>,
+    tweens: Query<(), (With<Tween>, Without<GoalEffect>)>,
+    mut cells: Query<(Entity, &mut CellContents, &Transform, Option<&AgentSprite>)>,
+    mut changed: EventWriter<BoardChanged>,
+) {
+    if !animation.running || !tweens.is_empty() {
+        return;
+    }
+
+    let Some(step) = animation.steps.pop_front() else {
+        animation.running = false;
+        changed.send(BoardChanged);
+        let palette: Palette = Palette::of(settings.config.theme);
+        for goal in satisfied_goals(&game.board) {
+            if !animation.goals_before.contains(&goal) {
+                spawn_goal_effect(&mut commands, goal, palette.goal_reached);
+            }
+        }
+        return;
+    };
+
+    let speed: f32 = settings.config.animation.speed.max(0.01);
+    for (entity, mut cell, transform, agent) in &mut cells {
+        let tween: Tween = match step {
+            ActionStep::AgentMoved {
+                agent: moved,
+                from,
+                to,
+            } if cell.0 == from && agent.is_some_and(|a| a.0 == moved) => {
+                cell.0 = to;
+                Tween::movement(
+                    transform.translation,
+                    transform.translation + offset(from, to),
+                    EaseFunction::QuadraticOut,
+                    WALK_TIME / speed,
+                )
+            }
+            ActionStep::BlockMoved { from, to, slide } if cell.0 == from => {
+                let squares: f32 = ((to.x - from.x).abs() + (to.y - from.y).abs()) as f32;
+                let (duration, ease) = slide_timing(slide, squares);
+                cell.0 = to;
+                Tween::movement(
+                    transform.translation,
+                    transform.translation + offset(from, to),
+                    ease,
+                    duration / speed,
+                )
+            }
+            ActionStep::BlockFell { from, direction } if cell.0 == from => {
+                cell.0 = OUT_OF_BOUND;
+                Tween {
+                    end_scale: 0.3,
+                    fade: true,
+                    ..Tween::movement(
+                        transform.translation,
+                        transform.translation + offset(from, from.move_direction(direction)),
+                        EaseFunction::QuadraticIn,
+                        FALL_TIME / speed,
+                    )
+                }
+            }
+            _ => continue,
+        };
+        commands.entity(entity).insert(tween);
+    }
+}
+
+fn spawn_goal_effect(commands: &mut Commands, goal: Coordinate, color: Color) {
+    let position: Vec3 = cell_position(goal).extend(-1.0);
+    commands.spawn((
+        Sprite::from_color(color, Vec2::splat(CELL_SIZE)),
+        Transform::from_translation(position),
+        Tween {
+            start_scale: 1.0,
+            end_scale: 1.8,
+            fade: true,
+            ..Tween::movement(
+                position,
+                position,
+                EaseFunction::QuadraticOut,
+                GOAL_EFFECT_TIME,
+            )
+        },
+        GoalEffect,
+        OnGameScene,
+    ));
+}
+
+pub(super) fn run_tweens(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tweens: Query<TweenedEntity>,
+) {
+    for (entity, mut tween, mut transform, sprite, text_color, goal_effect) in &mut tweens {
+        tween.elapsed += time.delta_secs();
+        let progress: f32 = (tween.elapsed / tween.duration).min(1.0);
+        let eased: f32 = EasingCurve::new(0.0, 1.0, tween.ease).sample_clamped(progress);
+
+        transform.translation = tween.start.lerp(tween.end, eased);
+        transform.scale = Vec3::splat(tween.start_scale.lerp(tween.end_scale, eased));
+        if tween.fade {
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_alpha(1.0 - eased);
+            }
+            if let Some(mut text_color) = text_color {
+                text_color.0.set_alpha(1.0 - eased);
+            }
+        }
+
+        if progress >= 1.0 {
+            if goal_effect {
+                commands.entity(entity).despawn_recursive();
+            } else {
+                commands.entity(entity).remove::<Tween>();
+            }
+        }
+    }
+}
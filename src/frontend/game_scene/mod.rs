@@ -0,0 +1,680 @@
+/// The scene the levels are played in
+///
+/// Every square of the board is a sprite coloured by the texture of its block, with the agents drawn on
+/// top, and the camera is fitted to the board while the scene is shown. Inputs are animated, see the
+/// animation module. 0-9 or Tab select an agent, the arrow keys (or w/a/s/d) walk it and Shift+arrows (or
+/// W/A/S/D) slide, all keys but the digits can be changed in the settings. A left click on a square next to
+/// the selected agent walks there, a left click on an agent selects it and a right click slides towards the
+/// clicked square. The scene goes back to where the level was chosen, the level select or the editor, when
+/// the level is won or lost, or on Esc.
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam, prelude::*, render::camera::ScalingMode, window::PrimaryWindow,
+};
+
+mod animation;
+use animation::{AgentSprite, Animation, CellContents, play_steps, run_tweens, satisfied_goals};
+
+use super::config::KeyBindings;
+use super::frontend_utils::{FrontendState, LevelToPlay, Palette, Settings, key_code, key_label};
+use super::play_level::status_bar;
+use crate::backend::profile::{Profile, ProfileStore};
+use crate::backend::utils_backend::{
+    AgentID, Coordinate, Direction, Index, PlayerInput, TextureType,
+};
+use crate::backend::{ActionLog, Board, GameState};
+
+/// Side of a square in world units
+pub(super) const CELL_SIZE: f32 = 64.0;
+/// Space between neighbouring blocks
+pub(super) const BLOCK_GAP: f32 = 4.0;
+/// Room left around the board by the camera, in squares
+const BOARD_MARGIN: f32 = 1.5;
+/// Part of a square the agents on it are drawn in
+const AGENT_AREA: f32 = 0.6;
+
+/// Colour of the labels on agents and goals
+pub(super) const LABEL_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+
+const AGENT_COLORS: [Color; 6] = [
+    Color::srgb(0.85, 0.2, 0.2),
+    Color::srgb(0.2, 0.7, 0.3),
+    Color::srgb(0.25, 0.4, 0.9),
+    Color::srgb(0.9, 0.8, 0.2),
+    Color::srgb(0.75, 0.3, 0.8),
+    Color::srgb(0.2, 0.75, 0.8),
+];
+
+#[derive(Resource)]
+struct Game {
+    board: Board,
+    level_id: Option<String>,
+    return_to: FrontendState,
+    selected: AgentID,
+    /// Time played, including the time before a resumed game was saved
+    time: Duration,
+    undos: usize,
+    message: String,
+    /// Restarting waits for the restart key to be pressed a second time
+    confirm_restart: bool,
+    restart_pending: bool,
+}
+
+/// Sent whenever the board has to be drawn again
+#[derive(Event)]
+struct BoardChanged;
+
+#[derive(Component)]
+struct OnGameScene;
+
+/// Sprites and labels of the board, replaced whenever the board changes
+#[derive(Component)]
+struct BoardSprite;
+
+#[derive(Component)]
+struct StatusText;
+
+enum Command {
+    Select(AgentID),
+    NextAgent,
+    Input(PlayerInput),
+    Undo,
+    Restart,
+    ToggleAnimations,
+    Quit,
+}
+
+/// What a command changed
+enum Outcome {
+    Unchanged,
+    Redraw,
+    /// An input was made, with the goals that were satisfied before it
+    Played(ActionLog, Vec<Coordinate>),
+}
+
+/// Everything needed to carry out a command
+#[derive(SystemParam)]
+struct Play<'w> {
+    game: ResMut<'w, Game>,
+    animation: ResMut<'w, Animation>,
+    settings: ResMut<'w, Settings>,
+    next_state: ResMut<'w, NextState<FrontendState>>,
+    changed: EventWriter<'w, BoardChanged>,
+}
+
+impl Play<'_> {
+    /// Carries out the command, commands given while an input is animated wait for the animation
+    fn dispatch(&mut self, command: Command) {
+        match command {
+            Command::Quit => self.next_state.set(self.game.return_to),
+            Command::ToggleAnimations => {
+                let animation = &mut self.settings.config.animation;
+                animation.instant = !animation.instant;
+                self.game.message = String::from(if animation.instant {
+                    "Animations off"
+                } else {
+                    "Animations on"
+                });
+                self.settings.save();
+            }
+            _ if self.animation.is_running() => self.animation.queue(command),
+            _ => match apply_command(&mut self.game, command) {
+                Outcome::Unchanged => (),
+                Outcome::Played(log, goals_before) if !self.settings.config.animation.instant => {
+                    self.animation.start(log, goals_before)
+                }
+                Outcome::Redraw | Outcome::Played(..) => {
+                    self.changed.send(BoardChanged);
+                }
+            },
+        }
+    }
+}
+
+pub fn game_scene_plugin(app: &mut App) {
+    app.add_event::<BoardChanged>()
+        .add_systems(
+            OnEnter(FrontendState::Playing),
+            (game_setup, fit_camera).chain(),
+        )
+        .add_systems(
+            OnExit(FrontendState::Playing),
+            (record_progress, despawn_game, reset_camera).chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                leave_finished_game,
+                keyboard_input,
+                mouse_input,
+                run_queued_commands,
+                run_tweens,
+                play_steps,
+                tick_clock,
+                draw_board,
+                update_status,
+            )
+                .chain()
+                .run_if(in_state(FrontendState::Playing)),
+        );
+}
+
+fn game_setup(
+    mut commands: Commands,
+    level: Res<LevelToPlay>,
+    settings: Res<Settings>,
+    mut changed: EventWriter<BoardChanged>,
+) {
+    commands.insert_resource(Game {
+        board: level.board.clone(),
+        level_id: level.level_id.clone(),
+        return_to: level.return_to,
+        selected: 0,
+        time: level.played_before,
+        undos: 0,
+        message: String::new(),
+        confirm_restart: settings.config.confirm_restart,
+        restart_pending: false,
+    });
+    commands.remove_resource::<LevelToPlay>();
+    commands.insert_resource(Animation::default());
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Palette::of(settings.config.theme).text),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        StatusText,
+        OnGameScene,
+    ));
+    changed.send(BoardChanged);
+}
+
+/// World position of the centre of a square, the board grows downwards like its coordinates
+pub(super) fn cell_position(coordinate: Coordinate) -> Vec2 {
+    Vec2::new(
+        coordinate.x as f32 * CELL_SIZE,
+        -(coordinate.y as f32) * CELL_SIZE,
+    )
+}
+
+fn fit_camera(
+    game: Res<Game>,
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    let (x_size, y_size): (Index, Index) = game.board.get_dimensions();
+    let center: Vec2 = Vec2::new(
+        (x_size - 1) as f32 * CELL_SIZE / 2.0,
+        -((y_size - 1) as f32) * CELL_SIZE / 2.0,
+    );
+
+    for (mut projection, mut transform) in &mut camera {
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: (x_size as f32 + 2.0 * BOARD_MARGIN) * CELL_SIZE,
+            min_height: (y_size as f32 + 2.0 * BOARD_MARGIN) * CELL_SIZE,
+        };
+        transform.translation = center.extend(transform.translation.z);
+    }
+}
+
+pub(super) fn reset_camera(
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    for (mut projection, mut transform) in &mut camera {
+        *projection = OrthographicProjection::default_2d();
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+    }
+}
+
+/// Keeps the result in the profile chosen in the settings, levels from outside the packs are not recorded
+fn record_progress(game: Res<Game>, settings: Res<Settings>) {
+    let Some(level_id) = &game.level_id else {
+        return;
+    };
+    let store: ProfileStore = settings.config.profile_store();
+    let mut profile: Profile = match store.load(&settings.config.profile) {
+        Ok(p) => p,
+        Err(msg) => {
+            error!("Could not save your progress. {}", msg);
+            return;
+        }
+    };
+
+    match game.board.get_game_state() {
+        GameState::Won => {
+            profile.record_win(level_id, game.board.move_count(), game.time, game.undos)
+        }
+        GameState::Running if game.board.move_count() > 0 => {
+            profile.record_unfinished(level_id, &game.board, game.time, game.undos)
+        }
+        _ => return,
+    }
+    if let Err(msg) = store.store(&profile) {
+        error!("Could not save your progress. {}", msg);
+    }
+}
+
+fn despawn_game(mut commands: Commands, to_despawn: Query<Entity, With<OnGameScene>>) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Game>();
+    commands.remove_resource::<Animation>();
+}
+
+/// Once the level is won or lost any key or click leaves the scene
+fn leave_finished_game(
+    game: Res<Game>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut next_state: ResMut<NextState<FrontendState>>,
+) {
+    let pressed: bool =
+        keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some();
+    if game.board.get_game_state() != GameState::Running && pressed {
+        next_state.set(game.return_to);
+    }
+}
+
+fn digit(key: KeyCode) -> Option<AgentID> {
+    match key {
+        KeyCode::Digit0 => Some(0),
+        KeyCode::Digit1 => Some(1),
+        KeyCode::Digit2 => Some(2),
+        KeyCode::Digit3 => Some(3),
+        KeyCode::Digit4 => Some(4),
+        KeyCode::Digit5 => Some(5),
+        KeyCode::Digit6 => Some(6),
+        KeyCode::Digit7 => Some(7),
+        KeyCode::Digit8 => Some(8),
+        KeyCode::Digit9 => Some(9),
+        _ => None,
+    }
+}
+
+fn bound(names: &[String], key: KeyCode) -> bool {
+    names.iter().any(|name| key_code(name) == Some(key))
+}
+
+fn key_command(
+    key: KeyCode,
+    slide: bool,
+    selected: AgentID,
+    keys: &KeyBindings,
+) -> Option<Command> {
+    let input = |direction: Direction| {
+        if slide {
+            Some(Command::Input(PlayerInput::Slide(selected, direction)))
+        } else {
+            Some(Command::Input(PlayerInput::Move(selected, direction)))
+        }
+    };
+
+    if bound(&keys.next_agent, key) {
+        Some(Command::NextAgent)
+    } else if bound(&keys.up, key) {
+        input(Direction::Up)
+    } else if bound(&keys.down, key) {
+        input(Direction::Down)
+    } else if bound(&keys.left, key) {
+        input(Direction::Left)
+    } else if bound(&keys.right, key) {
+        input(Direction::Right)
+    } else if bound(&keys.undo, key) {
+        Some(Command::Undo)
+    } else if bound(&keys.restart, key) {
+        Some(Command::Restart)
+    } else if bound(&keys.toggle_animations, key) {
+        Some(Command::ToggleAnimations)
+    } else if bound(&keys.quit, key) {
+        Some(Command::Quit)
+    } else {
+        digit(key).map(Command::Select)
+    }
+}
+
+/// The controls as they are currently bound
+fn help_line(keys: &KeyBindings) -> String {
+    let first = |names: &[String]| -> String {
+        names
+            .first()
+            .map_or(String::from("-"), |name| String::from(key_label(name)))
+    };
+    format!(
+        "0-9/{} agent  {}/{}/{}/{} walk  {}+direction slide  click walk or select  right click slide  {} undo  {} restart  {} animations  {} back",
+        first(&keys.next_agent),
+        first(&keys.up),
+        first(&keys.left),
+        first(&keys.down),
+        first(&keys.right),
+        first(&keys.slide),
+        first(&keys.undo),
+        first(&keys.restart),
+        first(&keys.toggle_animations),
+        first(&keys.quit),
+    )
+}
+
+/// Direction from one square to another in the same row or column
+fn direction_towards(from: Coordinate, to: Coordinate) -> Option<Direction> {
+    match ((to.x - from.x).signum(), (to.y - from.y).signum()) {
+        (0, -1) => Some(Direction::Up),
+        (0, 1) => Some(Direction::Down),
+        (-1, 0) => Some(Direction::Left),
+        (1, 0) => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn click_command(game: &Game, clicked: Coordinate, right_click: bool) -> Option<Command> {
+    let (x_size, y_size): (Index, Index) = game.board.get_dimensions();
+    if clicked.x < 0 || clicked.y < 0 || clicked.x >= x_size || clicked.y >= y_size {
+        return None;
+    }
+    let from: Coordinate = game.board.get_agent_position(game.selected);
+    let direction: Option<Direction> = direction_towards(from, clicked);
+
+    if right_click {
+        return direction.map(|d| Command::Input(PlayerInput::Slide(game.selected, d)));
+    }
+    if let Some(direction) = direction
+        && (clicked.x - from.x).abs() + (clicked.y - from.y).abs() == 1
+    {
+        return Some(Command::Input(PlayerInput::Move(game.selected, direction)));
+    }
+
+    let mut agents: Vec<AgentID> = game
+        .board
+        .read_block(clicked)
+        .get_agents()
+        .into_iter()
+        .collect();
+    agents.sort();
+    // Clicking the square of the selected agent goes through the agents on it
+    let next: Option<&AgentID> = agents
+        .iter()
+        .find(|agent| clicked == from && **agent > game.selected);
+    next.or(agents.first()).map(|agent| Command::Select(*agent))
+}
+
+/// Carries out a command that changes the game
+fn apply_command(game: &mut Game, command: Command) -> Outcome {
+    game.message.clear();
+    let restart_pending: bool = std::mem::take(&mut game.restart_pending);
+
+    match command {
+        Command::Quit | Command::ToggleAnimations => Outcome::Unchanged,
+        Command::Select(agent) if agent < game.board.get_num_agents() => {
+            game.selected = agent;
+            Outcome::Redraw
+        }
+        Command::Select(agent) => {
+            game.message = format!("There is no agent {}", agent);
+            Outcome::Unchanged
+        }
+        // A level without agents has nobody to switch to
+        Command::NextAgent if game.board.get_num_agents() == 0 => Outcome::Unchanged,
+        Command::NextAgent => {
+            game.selected = (game.selected + 1) % game.board.get_num_agents();
+            Outcome::Redraw
+        }
+        Command::Undo => {
+            if game.board.move_count() > 0 {
+                game.board.undo();
+                game.undos += 1;
+            }
+            Outcome::Redraw
+        }
+        Command::Restart
+            if game.confirm_restart && !restart_pending && game.board.move_count() > 0 =>
+        {
+            game.restart_pending = true;
+            game.message = String::from("Press restart again to start the level over");
+            Outcome::Unchanged
+        }
+        Command::Restart => {
+            game.board.restart();
+            Outcome::Redraw
+        }
+        Command::Input(input) => match game.board.check_input(input) {
+            Err(reason) => {
+                game.message = reason;
+                Outcome::Unchanged
+            }
+            Ok(()) => {
+                let goals_before: Vec<Coordinate> = satisfied_goals(&game.board);
+                let log: ActionLog = game.board.apply_input(input);
+                game.message = match game.board.get_game_state() {
+                    GameState::Won => format!(
+                        "Solved in {} moves! Press any key to continue.",
+                        game.board.move_count()
+                    ),
+                    GameState::Lost => {
+                        String::from("Womp womp, that lost the level. Press any key to continue.")
+                    }
+                    GameState::Running => String::new(),
+                };
+                Outcome::Played(log, goals_before)
+            }
+        },
+    }
+}
+
+fn keyboard_input(keys: Res<ButtonInput<KeyCode>>, mut play: Play) {
+    let bindings: KeyBindings = play.settings.config.keys.clone();
+    let slide: bool = keys.any_pressed(bindings.slide.iter().filter_map(|name| key_code(name)));
+
+    for key in keys.get_just_pressed() {
+        if play.game.board.get_game_state() != GameState::Running {
+            return;
+        }
+        if let Some(command) = key_command(*key, slide, play.game.selected, &bindings) {
+            play.dispatch(command);
+        }
+    }
+}
+
+fn mouse_input(
+    buttons: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut play: Play,
+) {
+    let right_click: bool = buttons.just_pressed(MouseButton::Right);
+    if play.game.board.get_game_state() != GameState::Running
+        || !(right_click || buttons.just_pressed(MouseButton::Left))
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (window.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let Some(world) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    let clicked: Coordinate = Coordinate {
+        x: (world.x / CELL_SIZE).round() as Index,
+        y: (-world.y / CELL_SIZE).round() as Index,
+    };
+    if let Some(command) = click_command(&play.game, clicked, right_click) {
+        play.dispatch(command);
+    }
+}
+
+/// Carries out the commands given during an animation once it is over
+fn run_queued_commands(mut play: Play) {
+    if play.animation.is_running() || play.game.board.get_game_state() != GameState::Running {
+        return;
+    }
+    if let Some(command) = play.animation.next_queued() {
+        play.dispatch(command);
+    }
+}
+
+fn tick_clock(time: Res<Time>, mut game: ResMut<Game>) {
+    if game.board.get_game_state() == GameState::Running {
+        game.time += time.delta();
+    }
+}
+
+pub(super) fn block_color(
+    texture: TextureType,
+    num_agents: usize,
+    palette: &Palette,
+) -> Option<Color> {
+    match texture {
+        TextureType::None => None,
+        TextureType::BasicBlock => Some(palette.basic_block),
+        TextureType::BasicImpassable => Some(palette.impassable),
+        TextureType::Goal(num) if num as usize == num_agents => Some(palette.goal_reached),
+        TextureType::Goal(_) => Some(palette.goal),
+    }
+}
+
+pub(super) fn agent_color(agent: AgentID) -> Color {
+    AGENT_COLORS[agent as usize % AGENT_COLORS.len()]
+}
+
+/// Draws the agents of a square in a grid, so any number of them fits
+fn spawn_agents(
+    commands: &mut Commands,
+    agents: &[AgentID],
+    coordinate: Coordinate,
+    selected: AgentID,
+    palette: &Palette,
+) {
+    if agents.is_empty() {
+        return;
+    }
+    let per_line: usize = (agents.len() as f32).sqrt().ceil() as usize;
+    let lines: usize = agents.len().div_ceil(per_line);
+    let size: f32 = CELL_SIZE * AGENT_AREA / per_line as f32;
+    let center: Vec2 = cell_position(coordinate);
+
+    for (index, agent) in agents.iter().enumerate() {
+        let offset: Vec2 = Vec2::new(
+            ((index % per_line) as f32 - (per_line - 1) as f32 / 2.0) * size,
+            -((index / per_line) as f32 - (lines - 1) as f32 / 2.0) * size,
+        );
+        let position: Vec2 = center + offset;
+
+        if *agent == selected {
+            commands.spawn((
+                Sprite::from_color(palette.selection, Vec2::splat(size)),
+                Transform::from_translation(position.extend(1.0)),
+                CellContents(coordinate),
+                AgentSprite(*agent),
+                BoardSprite,
+                OnGameScene,
+            ));
+        }
+        commands.spawn((
+            Sprite::from_color(agent_color(*agent), Vec2::splat(size * 0.8)),
+            Transform::from_translation(position.extend(2.0)),
+            CellContents(coordinate),
+            AgentSprite(*agent),
+            BoardSprite,
+            OnGameScene,
+        ));
+        commands.spawn((
+            Text2d::new(agent.to_string()),
+            TextFont {
+                font_size: size * 0.5,
+                ..default()
+            },
+            TextColor(LABEL_COLOR),
+            Transform::from_translation(position.extend(3.0)),
+            CellContents(coordinate),
+            AgentSprite(*agent),
+            BoardSprite,
+            OnGameScene,
+        ));
+    }
+}
+
+fn draw_board(
+    mut commands: Commands,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut changed: EventReader<BoardChanged>,
+    sprites: Query<Entity, With<BoardSprite>>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    changed.clear();
+    for entity in &sprites {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette: Palette = Palette::of(settings.config.theme);
+    let (x_size, y_size): (Index, Index) = game.board.get_dimensions();
+    for y in 0..y_size {
+        for x in 0..x_size {
+            let coordinate: Coordinate = Coordinate { x, y };
+            let block = game.board.read_block(coordinate);
+            let center: Vec2 = cell_position(coordinate);
+            let mut agents: Vec<AgentID> = block.get_agents().into_iter().collect();
+            agents.sort();
+
+            if let Some(color) = block_color(block.get_texture(), agents.len(), &palette) {
+                commands.spawn((
+                    Sprite::from_color(color, Vec2::splat(CELL_SIZE - BLOCK_GAP)),
+                    Transform::from_translation(center.extend(0.0)),
+                    CellContents(coordinate),
+                    BoardSprite,
+                    OnGameScene,
+                ));
+            }
+            if let TextureType::Goal(num) = block.get_texture() {
+                commands.spawn((
+                    Text2d::new(format!("{}/{}", agents.len(), num)),
+                    TextFont {
+                        font_size: CELL_SIZE * 0.2,
+                        ..default()
+                    },
+                    TextColor(LABEL_COLOR),
+                    Transform::from_translation(
+                        (center - Vec2::new(0.0, CELL_SIZE * 0.38)).extend(3.0),
+                    ),
+                    CellContents(coordinate),
+                    BoardSprite,
+                    OnGameScene,
+                ));
+            }
+            spawn_agents(&mut commands, &agents, coordinate, game.selected, &palette);
+        }
+    }
+}
+
+fn update_status(
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut status: Query<&mut Text, With<StatusText>>,
+) {
+    let mut lines: Vec<String> = vec![];
+    if let Some(title) = &game.board.get_meta().title {
+        lines.push(title.clone());
+    }
+    lines.push(status_bar(&game.board, game.selected, game.time));
+    lines.push(game.message.clone());
+    lines.push(help_line(&settings.config.keys));
+
+    for mut text in &mut status {
+        text.0 = lines.join("\n");
+    }
+}
@@ -0,0 +1,1033 @@
+/// The level editor
+///
+/// The board is drawn like in the game scene, with a side panel on the right. A left click paints the square
+/// with what is chosen in the palette, dragging paints several squares at once, and a right click picks a
+/// square for the tags in the panel. The panel also sets the board size, the agents and the victory
+/// conditions, and loads, saves and playtests the level, and lists the problems that do not stop the level
+/// from being saved. Ctrl+Z undoes, Ctrl+Y redoes, Ctrl+S saves, F5
+/// playtests, 1-5 choose from the palette and Esc goes back to the main menu. The level stays in the editor
+/// while it is playtested or the editor is left.
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+    render::camera::ScalingMode,
+    window::PrimaryWindow,
+};
+
+use super::frontend_utils::{FrontendState, LevelToPlay, Palette, Settings};
+use super::game_scene::{
+    BLOCK_GAP, CELL_SIZE, LABEL_COLOR, agent_color, block_color, cell_position, reset_camera,
+};
+use crate::backend::editor::{LevelEditor, PaletteBlock, block_texture};
+use crate::backend::level_file::{BlockTags, SlideSpec};
+use crate::backend::utils_backend::{AgentID, Coordinate, Index};
+
+const PANEL_WIDTH: f32 = 360.0;
+/// Room left around the board, in squares
+const BOARD_MARGIN: f32 = 0.5;
+/// Part of a square the agents on it are drawn in
+const AGENT_AREA: f32 = 0.5;
+const DEFAULT_LEVEL_PATH: &str = "new_level.toml";
+/// The slides the slide button goes through, None is the default fast slide
+const SLIDES: [Option<SlideSpec>; 6] = [
+    None,
+    Some(SlideSpec::Squares(0)),
+    Some(SlideSpec::Squares(1)),
+    Some(SlideSpec::Squares(2)),
+    Some(SlideSpec::Squares(3)),
+    Some(SlideSpec::Squares(4)),
+];
+
+const PANEL_COLOR: Color = Color::srgb(0.1, 0.1, 0.12);
+const BUTTON_COLOR: Color = Color::srgb(0.22, 0.22, 0.25);
+const HOVERED_BUTTON_COLOR: Color = Color::srgb(0.32, 0.32, 0.36);
+const ACTIVE_BUTTON_COLOR: Color = Color::srgb(0.25, 0.5, 0.25);
+/// Squares of air are drawn faintly so that the size of the board can be seen
+const AIR_COLOR: Color = Color::srgba(0.5, 0.5, 0.5, 0.15);
+const TEXT_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const DIM_TEXT_COLOR: Color = Color::srgb(0.65, 0.65, 0.65);
+const WARNING_TEXT_COLOR: Color = Color::srgb(1.0, 0.75, 0.3);
+
+const HELP_LINE: &str = "Left click paints, right click picks a square\nCtrl+Z undo  Ctrl+Y redo  Ctrl+S save\nF5 playtest  1-5 palette  Esc back";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Paint(PaletteBlock),
+    /// Moves the start of the chosen agent
+    Agent,
+}
+
+const TOOLS: [Tool; 5] = [
+    Tool::Paint(PaletteBlock::Air),
+    Tool::Paint(PaletteBlock::Block),
+    Tool::Paint(PaletteBlock::Wall),
+    Tool::Paint(PaletteBlock::Goal),
+    Tool::Agent,
+];
+
+#[derive(Resource)]
+struct EditorSession {
+    editor: LevelEditor,
+    path: String,
+    tool: Tool,
+    /// The square the tags in the panel belong to
+    square: Option<Coordinate>,
+    agent: AgentID,
+    /// Last square painted while the left button is held, the squares of one stroke are undone together
+    stroke: Option<Coordinate>,
+    stroke_edited: bool,
+    editing_path: bool,
+    /// An action that throws away unsaved edits waits for a second click
+    unconfirmed: Option<EditorAction>,
+    message: String,
+}
+
+impl EditorSession {
+    fn new() -> Self {
+        EditorSession {
+            editor: LevelEditor::new(),
+            path: String::from(DEFAULT_LEVEL_PATH),
+            tool: Tool::Paint(PaletteBlock::Block),
+            square: None,
+            agent: 0,
+            stroke: None,
+            stroke_edited: false,
+            editing_path: false,
+            unconfirmed: None,
+            message: String::new(),
+        }
+    }
+
+    /// The chosen square and the tags of its block
+    fn selected_block(&self) -> Result<(Coordinate, BlockTags), String> {
+        let Some(square) = self.square else {
+            return Err(String::from("Right click a square first"));
+        };
+        match self.editor.block(square) {
+            Some(spec) => Ok((square, spec.tags.clone().unwrap_or_default())),
+            None => Err(String::from("Air has no tags, paint a block first")),
+        }
+    }
+
+    /// Keeps the chosen square and agent valid after the level changed
+    fn clamp_selection(&mut self) {
+        if self
+            .square
+            .is_some_and(|square| !self.editor.in_bounds(square))
+        {
+            self.square = None;
+        }
+        self.agent = self
+            .agent
+            .min(self.editor.level().num_agents.saturating_sub(1));
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum EditorAction {
+    Resize(Index, Index),
+    Tool(Tool),
+    TogglePassable,
+    NextSlide,
+    GoalAgents(i16),
+    SelectAgent(i16),
+    AddAgent,
+    RemoveAgent,
+    Goals(i16),
+    MustFinish(i16),
+    EditPath,
+    New,
+    Load,
+    Save,
+    Undo,
+    Redo,
+    Playtest,
+    Back,
+}
+
+/// Text in the panel that shows part of the level
+#[derive(Component, Clone, Copy)]
+enum PanelField {
+    Width,
+    Height,
+    Square,
+    Passable,
+    Slide,
+    GoalAgents,
+    Agent,
+    Goals,
+    MustFinish,
+    Path,
+    Message,
+    /// Problems of the level that do not stop it from being saved
+    Warnings,
+}
+
+#[derive(Component)]
+struct OnEditor;
+
+/// Sprites and labels of the board, replaced whenever the level changes
+#[derive(Component)]
+struct BoardSprite;
+
+pub fn level_editor_plugin(app: &mut App) {
+    app.add_systems(OnEnter(FrontendState::Editor), editor_setup)
+        .add_systems(
+            OnExit(FrontendState::Editor),
+            (despawn_editor, reset_camera).chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                keyboard_shortcuts,
+                path_input,
+                button_actions,
+                mouse_input,
+                button_colors,
+                fit_camera,
+                draw_board,
+                update_panel,
+            )
+                .chain()
+                .run_if(in_state(FrontendState::Editor)),
+        );
+}
+
+/// Everything needed to carry out an action
+#[derive(SystemParam)]
+struct Actions<'w, 's> {
+    commands: Commands<'w, 's>,
+    session: ResMut<'w, EditorSession>,
+    next_state: ResMut<'w, NextState<FrontendState>>,
+}
+
+impl Actions<'_, '_> {
+    fn dispatch(&mut self, action: EditorAction) {
+        let session: &mut EditorSession = &mut self.session;
+        let discards: bool = matches!(action, EditorAction::New | EditorAction::Load);
+        if discards && session.editor.is_modified() && session.unconfirmed != Some(action) {
+            session.unconfirmed = Some(action);
+            session.message =
+                String::from("There are unsaved edits, click again to throw them away");
+            return;
+        }
+        session.unconfirmed = None;
+
+        let result: Result<String, String> = match action {
+            EditorAction::Playtest => match session.editor.to_board() {
+                Ok(board) => {
+                    self.commands.insert_resource(LevelToPlay {
+                        board,
+                        level_id: None,
+                        played_before: Duration::ZERO,
+                        return_to: FrontendState::Editor,
+                    });
+                    self.next_state.set(FrontendState::Playing);
+                    Ok(String::new())
+                }
+                Err(msg) => Err(format!("The level cannot be played. {}", msg)),
+            },
+            EditorAction::Back => {
+                self.next_state.set(FrontendState::MainMenu);
+                Ok(String::new())
+            }
+            _ => run_action(session, action),
+        };
+        session.message = match result {
+            Ok(msg) | Err(msg) => msg,
+        };
+        session.clamp_selection();
+    }
+}
+
+/// Carries out an action that stays in the editor, returns the message to show
+fn run_action(session: &mut EditorSession, action: EditorAction) -> Result<String, String> {
+    let editor: &mut LevelEditor = &mut session.editor;
+    match action {
+        EditorAction::Resize(x_change, y_change) => {
+            let (x_size, y_size): (Index, Index) = editor.size();
+            editor.resize(x_size + x_change, y_size + y_change)?;
+            Ok(String::new())
+        }
+        EditorAction::Tool(tool) => {
+            session.tool = tool;
+            Ok(String::new())
+        }
+        EditorAction::TogglePassable => {
+            let (square, mut tags) = session.selected_block()?;
+            tags.passable = match tags.passable {
+                Some(false) => None,
+                _ => Some(false),
+            };
+            session.editor.set_tags(square, tags)?;
+            Ok(String::new())
+        }
+        EditorAction::NextSlide => {
+            let (square, mut tags) = session.selected_block()?;
+            tags.slide = match SLIDES.iter().position(|slide| *slide == tags.slide) {
+                Some(position) => SLIDES[(position + 1) % SLIDES.len()],
+                None => SLIDES[1],
+            };
+            session.editor.set_tags(square, tags)?;
+            Ok(String::new())
+        }
+        EditorAction::GoalAgents(change) => {
+            let (square, mut tags) = session.selected_block()?;
+            let agents: i16 = tags.num_goal_agents.unwrap_or(0) as i16 + change;
+            tags.num_goal_agents = match agents.clamp(0, u8::MAX as i16) {
+                0 => None,
+                agents => Some(agents as u8),
+            };
+            session.editor.set_tags(square, tags)?;
+            Ok(String::new())
+        }
+        EditorAction::SelectAgent(change) => {
+            let agents: i16 = editor.level().num_agents as i16;
+            session.agent = (session.agent as i16 + change).rem_euclid(agents) as AgentID;
+            Ok(String::new())
+        }
+        EditorAction::AddAgent => {
+            let Some(square) = session.square else {
+                return Err(String::from(
+                    "Right click the square the agent starts on first",
+                ));
+            };
+            session.agent = editor.add_agent(square)?;
+            Ok(format!("Agent {} added", session.agent))
+        }
+        EditorAction::RemoveAgent => {
+            editor.remove_agent(session.agent)?;
+            Ok(format!("Agent {} removed", session.agent))
+        }
+        EditorAction::Goals(change) => {
+            let goals: i16 = editor.goals_to_satisfy() as i16 + change;
+            editor.set_goals_to_satisfy(goals.clamp(0, u8::MAX as i16) as u8)?;
+            Ok(String::new())
+        }
+        EditorAction::MustFinish(change) => {
+            let agents: AgentID = editor.level().num_agents;
+            let must_finish: i16 =
+                editor.level().num_agents_must_finish.unwrap_or(agents) as i16 + change;
+            let must_finish: AgentID = must_finish.clamp(0, agents as i16) as AgentID;
+            editor.set_agents_must_finish((must_finish < agents).then_some(must_finish))?;
+            Ok(String::new())
+        }
+        EditorAction::EditPath => {
+            session.editing_path = !session.editing_path;
+            Ok(String::from(if session.editing_path {
+                "Type the path of the level file, Enter ends"
+            } else {
+                ""
+            }))
+        }
+        EditorAction::New => {
+            session.editor = LevelEditor::new();
+            session.square = None;
+            Ok(String::from("New level"))
+        }
+        EditorAction::Load => {
+            session.editor = LevelEditor::load(&session.path)?;
+            session.square = None;
+            Ok(format!("Loaded {}", session.path))
+        }
+        EditorAction::Save => {
+            editor.save(Path::new(&session.path))?;
+            Ok(format!("Saved to {}", session.path))
+        }
+        EditorAction::Undo if editor.undo() => Ok(String::new()),
+        EditorAction::Undo => Err(String::from("Nothing to undo")),
+        EditorAction::Redo if editor.redo() => Ok(String::new()),
+        EditorAction::Redo => Err(String::from("Nothing to redo")),
+        EditorAction::Playtest | EditorAction::Back => Ok(String::new()),
+    }
+}
+
+fn on_off(on: bool) -> String {
+    String::from(if on { "on" } else { "off" })
+}
+
+fn field_text(field: PanelField, session: &EditorSession) -> String {
+    let editor: &LevelEditor = &session.editor;
+    let tags: Option<BlockTags> = session.selected_block().ok().map(|(_, tags)| tags);
+    match field {
+        PanelField::Width => editor.size().0.to_string(),
+        PanelField::Height => editor.size().1.to_string(),
+        PanelField::Square => match session.square {
+            Some(square) => format!(
+                "({}, {}) {}",
+                square.x,
+                square.y,
+                PaletteBlock::of(editor.block(square)).name()
+            ),
+            None => String::from("none"),
+        },
+        PanelField::Passable => tags.map_or(String::from("-"), |tags| {
+            on_off(tags.passable.unwrap_or(true))
+        }),
+        PanelField::Slide => match tags.map(|tags| tags.slide) {
+            None => String::from("-"),
+            Some(None | Some(SlideSpec::Fast)) => String::from("fast"),
+            Some(Some(SlideSpec::Squares(0))) => String::from("none"),
+            Some(Some(SlideSpec::Squares(squares))) => format!("slow {}", squares),
+        },
+        PanelField::GoalAgents => tags.map_or(String::from("-"), |tags| {
+            tags.num_goal_agents.unwrap_or(0).to_string()
+        }),
+        PanelField::Agent => {
+            let start: String = match editor.agent_start(session.agent) {
+                Some(start) => format!("({}, {})", start.x, start.y),
+                None => String::from("nowhere"),
+            };
+            format!(
+                "{} of {} at {}",
+                session.agent,
+                editor.level().num_agents,
+                start
+            )
+        }
+        PanelField::Goals => editor.goals_to_satisfy().to_string(),
+        PanelField::MustFinish => match editor.level().num_agents_must_finish {
+            Some(agents) => agents.to_string(),
+            None => String::from("all"),
+        },
+        PanelField::Path => format!(
+            "{}{}{}",
+            session.path,
+            if session.editing_path { "_" } else { "" },
+            if editor.is_modified() { " *" } else { "" }
+        ),
+        PanelField::Message => session.message.clone(),
+        PanelField::Warnings => editor.warnings().join("\n"),
+    }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, action: EditorAction) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(32.0),
+                height: Val::Px(28.0),
+                padding: UiRect::horizontal(Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+        });
+}
+
+fn spawn_heading(parent: &mut ChildBuilder, text: &str) {
+    parent.spawn((
+        Text::new(text),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(DIM_TEXT_COLOR),
+        Node {
+            margin: UiRect::top(Val::Px(8.0)),
+            ..default()
+        },
+    ));
+}
+
+/// A row with a label, optionally a value and buttons
+fn spawn_row(
+    parent: &mut ChildBuilder,
+    label: &str,
+    field: Option<PanelField>,
+    buttons: &[(&str, EditorAction)],
+) {
+    parent
+        .spawn(Node {
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(6.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            if !label.is_empty() {
+                parent.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        width: Val::Px(110.0),
+                        ..default()
+                    },
+                ));
+            }
+            if let Some(field) = field {
+                parent.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        flex_grow: 1.0,
+                        ..default()
+                    },
+                    field,
+                ));
+            }
+            for (label, action) in buttons {
+                spawn_button(parent, label, *action);
+            }
+        });
+}
+
+fn editor_setup(mut commands: Commands, session: Option<ResMut<EditorSession>>) {
+    // The panel and the board are filled in from the session once it counts as changed
+    match session {
+        Some(mut session) => session.set_changed(),
+        None => commands.insert_resource(EditorSession::new()),
+    }
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.0),
+                width: Val::Px(PANEL_WIDTH),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(12.0)),
+                row_gap: Val::Px(6.0),
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BackgroundColor(PANEL_COLOR),
+            OnEditor,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Level editor"),
+                TextFont {
+                    font_size: 28.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+
+            spawn_heading(parent, "Board");
+            spawn_row(
+                parent,
+                "Width",
+                Some(PanelField::Width),
+                &[
+                    ("-", EditorAction::Resize(-1, 0)),
+                    ("+", EditorAction::Resize(1, 0)),
+                ],
+            );
+            spawn_row(
+                parent,
+                "Height",
+                Some(PanelField::Height),
+                &[
+                    ("-", EditorAction::Resize(0, -1)),
+                    ("+", EditorAction::Resize(0, 1)),
+                ],
+            );
+
+            spawn_heading(parent, "Palette");
+            let tools: Vec<(&str, EditorAction)> = TOOLS
+                .iter()
+                .map(|tool| {
+                    let name: &str = match tool {
+                        Tool::Paint(block) => block.name(),
+                        Tool::Agent => "Agent",
+                    };
+                    (name, EditorAction::Tool(*tool))
+                })
+                .collect();
+            spawn_row(parent, "", None, &tools);
+
+            spawn_heading(parent, "Square");
+            spawn_row(parent, "Square", Some(PanelField::Square), &[]);
+            spawn_row(
+                parent,
+                "Passable",
+                Some(PanelField::Passable),
+                &[("Toggle", EditorAction::TogglePassable)],
+            );
+            spawn_row(
+                parent,
+                "Slide",
+                Some(PanelField::Slide),
+                &[("Change", EditorAction::NextSlide)],
+            );
+            spawn_row(
+                parent,
+                "Goal agents",
+                Some(PanelField::GoalAgents),
+                &[
+                    ("-", EditorAction::GoalAgents(-1)),
+                    ("+", EditorAction::GoalAgents(1)),
+                ],
+            );
+
+            spawn_heading(parent, "Agents");
+            spawn_row(
+                parent,
+                "Agent",
+                Some(PanelField::Agent),
+                &[
+                    ("<", EditorAction::SelectAgent(-1)),
+                    (">", EditorAction::SelectAgent(1)),
+                ],
+            );
+            spawn_row(
+                parent,
+                "",
+                None,
+                &[
+                    ("Add on square", EditorAction::AddAgent),
+                    ("Remove", EditorAction::RemoveAgent),
+                ],
+            );
+
+            spawn_heading(parent, "Victory");
+            spawn_row(
+                parent,
+                "Goals",
+                Some(PanelField::Goals),
+                &[
+                    ("-", EditorAction::Goals(-1)),
+                    ("+", EditorAction::Goals(1)),
+                ],
+            );
+            spawn_row(
+                parent,
+                "Must finish",
+                Some(PanelField::MustFinish),
+                &[
+                    ("-", EditorAction::MustFinish(-1)),
+                    ("+", EditorAction::MustFinish(1)),
+                ],
+            );
+
+            spawn_heading(parent, "File");
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Percent(100.0),
+                        min_height: Val::Px(28.0),
+                        padding: UiRect::horizontal(Val::Px(8.0)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(BUTTON_COLOR),
+                    EditorAction::EditPath,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        PanelField::Path,
+                    ));
+                });
+            spawn_row(
+                parent,
+                "",
+                None,
+                &[
+                    ("New", EditorAction::New),
+                    ("Load", EditorAction::Load),
+                    ("Save", EditorAction::Save),
+                    ("Undo", EditorAction::Undo),
+                    ("Redo", EditorAction::Redo),
+                ],
+            );
+            spawn_row(
+                parent,
+                "",
+                None,
+                &[
+                    ("Playtest", EditorAction::Playtest),
+                    ("Main menu", EditorAction::Back),
+                ],
+            );
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::top(Val::Px(8.0)),
+                    ..default()
+                },
+                PanelField::Message,
+            ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(WARNING_TEXT_COLOR),
+                PanelField::Warnings,
+            ));
+            parent.spawn((
+                Text::new(HELP_LINE),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(DIM_TEXT_COLOR),
+            ));
+        });
+}
+
+fn despawn_editor(
+    mut commands: Commands,
+    mut session: ResMut<EditorSession>,
+    to_despawn: Query<Entity, With<OnEditor>>,
+) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+    session.editing_path = false;
+    session.stroke = None;
+}
+
+fn keyboard_shortcuts(keys: Res<ButtonInput<KeyCode>>, mut actions: Actions) {
+    if actions.session.editing_path {
+        return;
+    }
+    let control: bool = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift: bool = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let tool_keys: [KeyCode; 5] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+    ];
+
+    for key in keys.get_just_pressed() {
+        let action: Option<EditorAction> = match key {
+            KeyCode::KeyZ if control && shift => Some(EditorAction::Redo),
+            KeyCode::KeyZ if control => Some(EditorAction::Undo),
+            KeyCode::KeyY if control => Some(EditorAction::Redo),
+            KeyCode::KeyS if control => Some(EditorAction::Save),
+            KeyCode::F5 => Some(EditorAction::Playtest),
+            KeyCode::Escape => Some(EditorAction::Back),
+            _ => tool_keys
+                .iter()
+                .position(|tool_key| tool_key == key)
+                .map(|index| EditorAction::Tool(TOOLS[index])),
+        };
+        if let Some(action) = action {
+            actions.dispatch(action);
+        }
+    }
+}
+
+/// Typing into the path of the level file
+fn path_input(mut events: EventReader<KeyboardInput>, mut session: ResMut<EditorSession>) {
+    if !session.editing_path {
+        events.clear();
+        return;
+    }
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => session.path.push_str(text),
+            Key::Space => session.path.push(' '),
+            Key::Backspace => {
+                session.path.pop();
+            }
+            Key::Enter | Key::Escape => {
+                session.editing_path = false;
+                session.message.clear();
+            }
+            _ => (),
+        }
+    }
+}
+
+fn button_actions(
+    buttons: Query<(&Interaction, &EditorAction), Changed<Interaction>>,
+    mut actions: Actions,
+) {
+    for (interaction, action) in &buttons {
+        if *interaction == Interaction::Pressed {
+            actions.dispatch(*action);
+        }
+    }
+}
+
+/// The square under the cursor, if it is on the board and not behind the panel
+fn hovered_square(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    editor: &LevelEditor,
+) -> Option<Coordinate> {
+    let cursor: Vec2 = window.cursor_position()?;
+    if cursor.x > window.width() - PANEL_WIDTH {
+        return None;
+    }
+    let world: Vec2 = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+    let square: Coordinate = Coordinate {
+        x: (world.x / CELL_SIZE).round() as Index,
+        y: (-world.y / CELL_SIZE).round() as Index,
+    };
+    editor.in_bounds(square).then_some(square)
+}
+
+fn mouse_input(
+    buttons: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut session: ResMut<EditorSession>,
+) {
+    if !buttons.pressed(MouseButton::Left) && session.stroke.is_some() {
+        session.stroke = None;
+        session.stroke_edited = false;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (window.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let Some(square) = hovered_square(window, camera, camera_transform, &session.editor) else {
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Right) {
+        session.square = Some(square);
+        if let Some(agent) = session.editor.agents_at(square).first() {
+            session.agent = *agent;
+        }
+        return;
+    }
+    // A stroke starts with a click on the board and paints every square the cursor passes once
+    let starts: bool = buttons.just_pressed(MouseButton::Left);
+    if !buttons.pressed(MouseButton::Left)
+        || session.stroke == Some(square)
+        || (session.stroke.is_none() && !starts)
+    {
+        return;
+    }
+    session.stroke = Some(square);
+    session.square = Some(square);
+
+    let result: Result<(), String> = match session.tool {
+        Tool::Paint(block) => {
+            let merge: bool = session.stroke_edited;
+            session
+                .editor
+                .paint(square, block, merge)
+                .map(|changed| session.stroke_edited |= changed)
+        }
+        Tool::Agent if starts => {
+            let agent: AgentID = session.agent;
+            session.editor.place_agent(agent, square)
+        }
+        Tool::Agent => Ok(()),
+    };
+    session.message = result.err().unwrap_or_default();
+}
+
+fn button_colors(
+    session: Res<EditorSession>,
+    mut buttons: Query<(&Interaction, &EditorAction, &mut BackgroundColor)>,
+) {
+    for (interaction, action, mut background) in &mut buttons {
+        let active: bool = match action {
+            EditorAction::Tool(tool) => session.tool == *tool,
+            EditorAction::EditPath => session.editing_path,
+            _ => false,
+        };
+        let color: Color = match (interaction, active) {
+            (Interaction::Pressed, _) | (_, true) => ACTIVE_BUTTON_COLOR,
+            (Interaction::Hovered, false) => HOVERED_BUTTON_COLOR,
+            (Interaction::None, false) => BUTTON_COLOR,
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
+
+/// Fits the board into the part of the window left of the panel
+fn fit_camera(
+    session: Res<EditorSession>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let (x_size, y_size): (Index, Index) = session.editor.size();
+    let board_width: f32 = (x_size as f32 + 2.0 * BOARD_MARGIN) * CELL_SIZE;
+    let board_height: f32 = (y_size as f32 + 2.0 * BOARD_MARGIN) * CELL_SIZE;
+    // World units per logical pixel
+    let scale: f32 = (board_width / (window.width() - PANEL_WIDTH).max(1.0))
+        .max(board_height / window.height().max(1.0));
+    let center: Vec2 = Vec2::new(
+        (x_size - 1) as f32 * CELL_SIZE / 2.0 + PANEL_WIDTH / 2.0 * scale,
+        -((y_size - 1) as f32) * CELL_SIZE / 2.0,
+    );
+
+    for (mut projection, mut transform) in &mut camera {
+        if projection.scale != scale || !matches!(projection.scaling_mode, ScalingMode::WindowSize)
+        {
+            projection.scaling_mode = ScalingMode::WindowSize;
+            projection.scale = scale;
+        }
+        if transform.translation.truncate() != center {
+            transform.translation = center.extend(transform.translation.z);
+        }
+    }
+}
+
+fn spawn_label(commands: &mut Commands, text: String, position: Vec2, size: f32) {
+    commands.spawn((
+        Text2d::new(text),
+        TextFont {
+            font_size: size,
+            ..default()
+        },
+        TextColor(LABEL_COLOR),
+        Transform::from_translation(position.extend(3.0)),
+        BoardSprite,
+        OnEditor,
+    ));
+}
+
+fn draw_board(
+    mut commands: Commands,
+    session: Res<EditorSession>,
+    settings: Res<Settings>,
+    sprites: Query<Entity, With<BoardSprite>>,
+) {
+    if !session.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for entity in &sprites {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette: Palette = Palette::of(settings.config.theme);
+    let (x_size, y_size): (Index, Index) = session.editor.size();
+    for y in 0..y_size {
+        for x in 0..x_size {
+            let square: Coordinate = Coordinate { x, y };
+            let spec = session.editor.block(square);
+            let center: Vec2 = cell_position(square);
+
+            if session.square == Some(square) {
+                commands.spawn((
+                    Sprite::from_color(palette.selection, Vec2::splat(CELL_SIZE)),
+                    Transform::from_translation(center.extend(-1.0)),
+                    BoardSprite,
+                    OnEditor,
+                ));
+            }
+            let color: Color = block_color(block_texture(spec), 0, &palette).unwrap_or(AIR_COLOR);
+            commands.spawn((
+                Sprite::from_color(color, Vec2::splat(CELL_SIZE - BLOCK_GAP)),
+                Transform::from_translation(center.extend(0.0)),
+                BoardSprite,
+                OnEditor,
+            ));
+
+            if let Some(tags) = spec.and_then(|spec| spec.tags.as_ref()) {
+                if let Some(agents) = tags.num_goal_agents.filter(|agents| *agents > 0) {
+                    spawn_label(
+                        &mut commands,
+                        format!("goal {}", agents),
+                        center - Vec2::new(0.0, CELL_SIZE * 0.38),
+                        CELL_SIZE * 0.2,
+                    );
+                }
+                let slide: Option<String> = match tags.slide {
+                    None | Some(SlideSpec::Fast) => None,
+                    Some(SlideSpec::Squares(0)) => Some(String::from("no slide")),
+                    Some(SlideSpec::Squares(squares)) => Some(format!("slow {}", squares)),
+                };
+                if let Some(slide) = slide {
+                    spawn_label(
+                        &mut commands,
+                        slide,
+                        center + Vec2::new(0.0, CELL_SIZE * 0.36),
+                        CELL_SIZE * 0.2,
+                    );
+                }
+            }
+
+            let agents: Vec<AgentID> = session.editor.agents_at(square);
+            if agents.is_empty() {
+                continue;
+            }
+            let per_line: usize = (agents.len() as f32).sqrt().ceil() as usize;
+            let lines: usize = agents.len().div_ceil(per_line);
+            let size: f32 = CELL_SIZE * AGENT_AREA / per_line as f32;
+            for (index, agent) in agents.iter().enumerate() {
+                let position: Vec2 = center
+                    + Vec2::new(
+                        ((index % per_line) as f32 - (per_line - 1) as f32 / 2.0) * size,
+                        -((index / per_line) as f32 - (lines - 1) as f32 / 2.0) * size,
+                    );
+                if *agent == session.agent {
+                    commands.spawn((
+                        Sprite::from_color(palette.selection, Vec2::splat(size)),
+                        Transform::from_translation(position.extend(1.0)),
+                        BoardSprite,
+                        OnEditor,
+                    ));
+                }
+                commands.spawn((
+                    Sprite::from_color(agent_color(*agent), Vec2::splat(size * 0.8)),
+                    Transform::from_translation(position.extend(2.0)),
+                    BoardSprite,
+                    OnEditor,
+                ));
+                spawn_label(&mut commands, agent.to_string(), position, size * 0.5);
+            }
+        }
+    }
+}
+
+fn update_panel(session: Res<EditorSession>, mut fields: Query<(&PanelField, &mut Text)>) {
+    if !session.is_changed() {
+        return;
+    }
+    for (field, mut text) in &mut fields {
+        text.0 = field_text(*field, &session);
+    }
+}
@@ -0,0 +1,584 @@
+/// The level select screen
+///
+/// Lists the levels of every pack in the levels directory, grouped by pack, with a thumbnail of the board,
+/// the title and the progress of the profile chosen in the settings. Empty packs say so, and packs that could
+/// not be loaded get a row with the reason at the end of the list. Up/Down (or w/s), Page Up/Down, Home and
+/// End choose a level, Enter or a click plays it, the mouse wheel scrolls and Esc goes back to the main menu.
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use super::frontend_utils::{FrontendState, LevelToPlay, Palette, Settings};
+use super::game_scene::{agent_color, block_color};
+use super::play_level::{level_label, level_status};
+use crate::backend::Board;
+use crate::backend::packs::{DEFAULT_PACKS_DIR, LevelPack, PackError, PackListing, list_packs};
+use crate::backend::profile::{Profile, ProfileStore};
+use crate::backend::utils_backend::{AgentID, Coordinate, Index};
+
+const HEADER_HEIGHT: f32 = 60.0;
+const ROW_HEIGHT: f32 = 84.0;
+const LIST_WIDTH: f32 = 760.0;
+/// Largest side of a thumbnail in pixels on the screen
+const THUMBNAIL_SIZE: f32 = 72.0;
+/// Pixels per square in the thumbnail image
+const THUMBNAIL_CELL_PIXELS: u32 = 8;
+/// Levels skipped by Page Up and Page Down
+const PAGE_LEVELS: usize = 5;
+/// Logical pixels scrolled per line of the mouse wheel
+const SCROLL_LINE_HEIGHT: f32 = 40.0;
+
+const ROW_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const SELECTED_ROW_COLOR: Color = Color::srgb(0.25, 0.45, 0.25);
+const LOCKED_ROW_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+const SELECTED_LOCKED_ROW_COLOR: Color = Color::srgb(0.3, 0.2, 0.2);
+const THUMBNAIL_BACKGROUND: Color = Color::srgb(0.08, 0.08, 0.1);
+const TEXT_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const DIM_TEXT_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+const ERROR_TEXT_COLOR: Color = Color::srgb(0.9, 0.5, 0.5);
+
+const HELP_LINE: &str = "Up/Down choose  Enter or click play  wheel scroll  Esc back";
+
+struct LevelEntry {
+    /// Index into LevelList::packs
+    pack: usize,
+    index: usize,
+    unlocked: bool,
+    /// Distance of the row from the top of the list, for keeping the selection in view
+    top: f32,
+}
+
+#[derive(Resource)]
+struct LevelList {
+    packs: Vec<LevelPack>,
+    entries: Vec<LevelEntry>,
+    /// Packs that could not be loaded, shown after the others
+    errors: Vec<PackError>,
+    selected: usize,
+    profile: Profile,
+}
+
+#[derive(Component)]
+struct OnLevelSelect;
+
+/// The scrolling part of the screen
+#[derive(Component)]
+struct LevelListView;
+
+/// Row of the level with this index into LevelList::entries
+#[derive(Component)]
+struct LevelRow(usize);
+
+#[derive(Component)]
+struct MessageText;
+
+pub fn level_select_plugin(app: &mut App) {
+    app.add_systems(OnEnter(FrontendState::LevelSelectMenu), level_select_setup)
+        .add_systems(OnExit(FrontendState::LevelSelectMenu), despawn_level_select)
+        .add_systems(
+            Update,
+            (
+                keyboard_navigation,
+                mouse_navigation,
+                scroll_list,
+                highlight_selected,
+            )
+                .chain()
+                .run_if(in_state(FrontendState::LevelSelectMenu)),
+        );
+}
+
+/// A picture of the board with a pixel block per square and the agents as dots in the middle
+fn board_thumbnail(board: &Board, palette: &Palette) -> Image {
+    let (x_size, y_size): (Index, Index) = board.get_dimensions();
+    let width: u32 = x_size as u32 * THUMBNAIL_CELL_PIXELS;
+    let height: u32 = y_size as u32 * THUMBNAIL_CELL_PIXELS;
+    let mut data: Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+
+    for pixel_y in 0..height {
+        for pixel_x in 0..width {
+            let coordinate: Coordinate = Coordinate {
+                x: (pixel_x / THUMBNAIL_CELL_PIXELS) as Index,
+                y: (pixel_y / THUMBNAIL_CELL_PIXELS) as Index,
+            };
+            let (inner_x, inner_y) = (
+                pixel_x % THUMBNAIL_CELL_PIXELS,
+                pixel_y % THUMBNAIL_CELL_PIXELS,
+            );
+            let block = board.read_block(coordinate);
+            let agents: Option<AgentID> = block.get_agents().into_iter().min();
+            let middle = 2..THUMBNAIL_CELL_PIXELS - 2;
+            let edge: bool = inner_x == 0 || inner_y == 0;
+
+            let color: Color = match agents {
+                Some(agent) if middle.contains(&inner_x) && middle.contains(&inner_y) => {
+                    agent_color(agent)
+                }
+                _ if edge => THUMBNAIL_BACKGROUND,
+                _ => block_color(block.get_texture(), block.get_agents().len(), palette)
+                    .unwrap_or(THUMBNAIL_BACKGROUND),
+            };
+            data.extend_from_slice(&color.to_srgba().to_u8_array());
+        }
+    }
+
+    let mut image: Image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+/// The level as the game scene takes it, continuing an unfinished game of it
+fn level_to_play(pack: &LevelPack, index: usize, profile: &Profile) -> Result<LevelToPlay, String> {
+    let level_id: String = pack.level_id(index);
+    let (board, played_before) = match profile.resume(&level_id) {
+        Some(Ok(game)) => game,
+        _ => (
+            pack.load_level(index).map_err(|e| e.to_string())?,
+            Duration::ZERO,
+        ),
+    };
+    Ok(LevelToPlay {
+        board,
+        level_id: Some(level_id),
+        played_before,
+        return_to: FrontendState::LevelSelectMenu,
+    })
+}
+
+fn load_level_list(store: &ProfileStore, profile: &str) -> Result<LevelList, String> {
+    let profile: Profile = store.load(profile)?;
+    let listing: PackListing = list_packs(Path::new(DEFAULT_PACKS_DIR))?;
+    let packs: Vec<LevelPack> = listing.packs;
+    let solved = profile.solved_levels();
+
+    let mut entries: Vec<LevelEntry> = vec![];
+    let mut top: f32 = 0.0;
+    // The first level that is not solved yet is selected at the start
+    let mut selected: Option<usize> = None;
+    for (pack_number, pack) in packs.iter().enumerate() {
+        top += HEADER_HEIGHT;
+        let next: Option<usize> = pack.next_level(None, &solved);
+        for index in 0..pack.len() {
+            if selected.is_none() && next == Some(index) {
+                selected = Some(entries.len());
+            }
+            entries.push(LevelEntry {
+                pack: pack_number,
+                index,
+                unlocked: pack.is_unlocked(index, &solved),
+                top,
+            });
+            top += ROW_HEIGHT;
+        }
+        if pack.is_empty() {
+            // The row saying that the pack has no levels
+            top += ROW_HEIGHT;
+        }
+    }
+
+    Ok(LevelList {
+        packs,
+        entries,
+        errors: listing.errors,
+        selected: selected.unwrap_or(0),
+        profile,
+    })
+}
+
+fn spawn_pack_header(parent: &mut ChildBuilder, header: String, palette: &Palette) {
+    parent.spawn((
+        Text::new(header),
+        TextFont {
+            font_size: 30.0,
+            ..default()
+        },
+        TextColor(palette.text),
+        Node {
+            height: Val::Px(HEADER_HEIGHT),
+            flex_shrink: 0.0,
+            align_items: AlignItems::End,
+            ..default()
+        },
+    ));
+}
+
+/// Row that only explains something about a pack, e.g. why it could not be loaded, it cannot be selected
+fn spawn_note_row(parent: &mut ChildBuilder, note: String, color: Color) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                min_height: Val::Px(ROW_HEIGHT),
+                flex_shrink: 0.0,
+                padding: UiRect::horizontal(Val::Px(12.0)),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(LOCKED_ROW_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(note),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(color),
+            ));
+        });
+}
+
+fn spawn_level_row(
+    parent: &mut ChildBuilder,
+    list: &LevelList,
+    entry_number: usize,
+    images: &mut Assets<Image>,
+    palette: &Palette,
+) {
+    let entry: &LevelEntry = &list.entries[entry_number];
+    let pack: &LevelPack = &list.packs[entry.pack];
+    let text_color: Color = if entry.unlocked {
+        TEXT_COLOR
+    } else {
+        DIM_TEXT_COLOR
+    };
+
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Px(ROW_HEIGHT),
+                flex_shrink: 0.0,
+                padding: UiRect::horizontal(Val::Px(12.0)),
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(ROW_COLOR),
+            LevelRow(entry_number),
+        ))
+        .with_children(|parent| {
+            let mut thumbnail = Node {
+                width: Val::Px(THUMBNAIL_SIZE),
+                height: Val::Px(THUMBNAIL_SIZE),
+                flex_shrink: 0.0,
+                ..default()
+            };
+            match pack.load_level(entry.index) {
+                Ok(board) => {
+                    let (x_size, y_size): (Index, Index) = board.get_dimensions();
+                    let scale: f32 = THUMBNAIL_SIZE / x_size.max(y_size) as f32;
+                    thumbnail.width = Val::Px(x_size as f32 * scale);
+                    thumbnail.height = Val::Px(y_size as f32 * scale);
+                    // Keeps the labels lined up whatever the shape of the board
+                    thumbnail.margin =
+                        UiRect::right(Val::Px(THUMBNAIL_SIZE - x_size as f32 * scale));
+                    parent.spawn((
+                        ImageNode::new(images.add(board_thumbnail(&board, palette))),
+                        thumbnail,
+                    ));
+                }
+                Err(_) => {
+                    parent.spawn((thumbnail, BackgroundColor(THUMBNAIL_BACKGROUND)));
+                }
+            }
+            parent.spawn((
+                Text::new(level_label(pack, entry.index)),
+                TextFont {
+                    font_size: 26.0,
+                    ..default()
+                },
+                TextColor(text_color),
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                Text::new(level_status(pack, entry.index, &list.profile)),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(DIM_TEXT_COLOR),
+            ));
+        });
+}
+
+fn level_select_setup(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // Rows have a background of their own, the theme colours the rest of the screen
+    let palette: Palette = Palette::of(settings.config.theme);
+    let (list, message): (Option<LevelList>, String) =
+        match load_level_list(&settings.config.profile_store(), &settings.config.profile) {
+            Ok(list) if list.packs.is_empty() && list.errors.is_empty() => (
+                None,
+                format!("There are no level packs in {}", DEFAULT_PACKS_DIR),
+            ),
+            Ok(list) => (Some(list), String::from(HELP_LINE)),
+            Err(msg) => (None, msg),
+        };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::vertical(Val::Px(20.0)),
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            OnLevelSelect,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Choose a level"),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+                TextColor(palette.text),
+            ));
+
+            if let Some(list) = &list {
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Px(LIST_WIDTH),
+                            flex_grow: 1.0,
+                            flex_direction: FlexDirection::Column,
+                            overflow: Overflow::scroll_y(),
+                            ..default()
+                        },
+                        ScrollPosition::default(),
+                        LevelListView,
+                    ))
+                    .with_children(|parent| {
+                        for (pack_number, pack) in list.packs.iter().enumerate() {
+                            let header: String = match &pack.manifest.description {
+                                Some(description) => {
+                                    format!("{}  ({})", pack.manifest.name, description)
+                                }
+                                None => pack.manifest.name.clone(),
+                            };
+                            spawn_pack_header(parent, header, &palette);
+                            if pack.is_empty() {
+                                spawn_note_row(
+                                    parent,
+                                    String::from("This pack has no levels"),
+                                    DIM_TEXT_COLOR,
+                                );
+                            }
+                            for (entry_number, _) in list
+                                .entries
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, entry)| entry.pack == pack_number)
+                            {
+                                spawn_level_row(parent, list, entry_number, &mut images, &palette);
+                            }
+                        }
+                        for error in &list.errors {
+                            spawn_pack_header(parent, error.id.clone(), &palette);
+                            spawn_note_row(
+                                parent,
+                                format!("Could not load this pack. {}", error.message),
+                                ERROR_TEXT_COLOR,
+                            );
+                        }
+                    });
+            }
+
+            parent.spawn((
+                Text::new(message),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(palette.dim_text),
+                MessageText,
+            ));
+        });
+
+    if let Some(list) = list {
+        commands.insert_resource(list);
+    }
+}
+
+fn despawn_level_select(mut commands: Commands, to_despawn: Query<Entity, With<OnLevelSelect>>) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<LevelList>();
+}
+
+/// Starts the selected level, locked levels only explain how to unlock them
+fn launch_selected(
+    list: &LevelList,
+    commands: &mut Commands,
+    next_state: &mut NextState<FrontendState>,
+    message: &mut Text,
+) {
+    let Some(entry) = list.entries.get(list.selected) else {
+        return;
+    };
+    if !entry.unlocked {
+        message.0 = String::from("This level is locked, solve the levels before it first");
+        return;
+    }
+    match level_to_play(&list.packs[entry.pack], entry.index, &list.profile) {
+        Ok(level) => {
+            commands.insert_resource(level);
+            next_state.set(FrontendState::Playing);
+        }
+        Err(msg) => message.0 = format!("Could not load the level. {}", msg),
+    }
+}
+
+/// Scrolls the list so that the selected level is in view
+fn scroll_to_selected(
+    list: &LevelList,
+    view: &mut Query<(&mut ScrollPosition, &ComputedNode), With<LevelListView>>,
+) {
+    let Some(entry) = list.entries.get(list.selected) else {
+        return;
+    };
+    for (mut scroll, node) in view {
+        let height: f32 = node.size().y * node.inverse_scale_factor();
+        // The pack header stays in view together with the first level of the pack
+        let top: f32 = if entry.index == 0 {
+            entry.top - HEADER_HEIGHT
+        } else {
+            entry.top
+        };
+        if top < scroll.offset_y {
+            scroll.offset_y = top;
+        } else if entry.top + ROW_HEIGHT > scroll.offset_y + height {
+            scroll.offset_y = entry.top + ROW_HEIGHT - height;
+        }
+    }
+}
+
+fn keyboard_navigation(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    list: Option<ResMut<LevelList>>,
+    mut view: Query<(&mut ScrollPosition, &ComputedNode), With<LevelListView>>,
+    mut message: Query<&mut Text, With<MessageText>>,
+    mut next_state: ResMut<NextState<FrontendState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(FrontendState::MainMenu);
+        return;
+    }
+    let Some(mut list) = list else {
+        return;
+    };
+    let last: usize = list.entries.len().saturating_sub(1);
+
+    let selected: usize = list.selected;
+    for key in keys.get_just_pressed() {
+        list.selected = match key {
+            KeyCode::ArrowUp | KeyCode::KeyW => list.selected.saturating_sub(1),
+            KeyCode::ArrowDown | KeyCode::KeyS => (list.selected + 1).min(last),
+            KeyCode::PageUp => list.selected.saturating_sub(PAGE_LEVELS),
+            KeyCode::PageDown => (list.selected + PAGE_LEVELS).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Enter | KeyCode::Space => {
+                if let Ok(mut message) = message.get_single_mut() {
+                    launch_selected(&list, &mut commands, &mut next_state, &mut message);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+    }
+    if list.selected != selected {
+        scroll_to_selected(&list, &mut view);
+    }
+}
+
+fn mouse_navigation(
+    mut commands: Commands,
+    rows: Query<(&Interaction, &LevelRow), Changed<Interaction>>,
+    list: Option<ResMut<LevelList>>,
+    mut message: Query<&mut Text, With<MessageText>>,
+    mut next_state: ResMut<NextState<FrontendState>>,
+) {
+    let Some(mut list) = list else {
+        return;
+    };
+    for (interaction, row) in &rows {
+        match interaction {
+            Interaction::Hovered => list.selected = row.0,
+            Interaction::Pressed => {
+                list.selected = row.0;
+                if let Ok(mut message) = message.get_single_mut() {
+                    launch_selected(&list, &mut commands, &mut next_state, &mut message);
+                }
+            }
+            Interaction::None => (),
+        }
+    }
+}
+
+fn scroll_list(
+    mut wheel: EventReader<MouseWheel>,
+    mut view: Query<&mut ScrollPosition, With<LevelListView>>,
+) {
+    for event in wheel.read() {
+        let distance: f32 = match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        // The UI layout keeps the position within the list
+        for mut scroll in &mut view {
+            scroll.offset_y = (scroll.offset_y - distance).max(0.0);
+        }
+    }
+}
+
+fn highlight_selected(
+    list: Option<Res<LevelList>>,
+    mut rows: Query<(&LevelRow, &mut BackgroundColor)>,
+) {
+    let Some(list) = list else {
+        return;
+    };
+    if !list.is_changed() {
+        return;
+    }
+    for (row, mut background) in &mut rows {
+        let unlocked: bool = list.entries[row.0].unlocked;
+        background.0 = match (row.0 == list.selected, unlocked) {
+            (true, true) => SELECTED_ROW_COLOR,
+            (true, false) => SELECTED_LOCKED_ROW_COLOR,
+            (false, true) => ROW_COLOR,
+            (false, false) => LOCKED_ROW_COLOR,
+        };
+    }
+}
@@ -1,6 +1,9 @@
 use bevy::{color::palettes::css::CRIMSON, prelude::*};
 
-use super::{frontend_utils::FrontendState, play_level::play_level};
+use super::frontend_utils::FrontendState;
+
+mod settings;
+use settings::settings_menu_plugin;
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash, States)]
 enum MenuState {
@@ -13,12 +16,10 @@ enum MenuState {
 #[derive(Component)]
 struct OnMainMenu;
 
-#[derive(Component)]
-struct OnStatusMenu;
-
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    Editor,
     Exit,
     Settings,
     ToMainMenu,
@@ -27,6 +28,13 @@ enum MenuButtonAction {
 #[derive(Component)]
 struct SelectedOption;
 
+type ColouredButton = (
+    &'static Interaction,
+    &'static mut BackgroundColor,
+    Option<&'static SelectedOption>,
+);
+type ClickedButton = (&'static Interaction, &'static MenuButtonAction);
+
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
 const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
@@ -34,15 +42,13 @@ const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
 
 const TEXT_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
 
-pub fn main_menu_plugin(app: &mut App) -> () {
+pub fn main_menu_plugin(app: &mut App) {
     app.init_state::<MenuState>()
         .add_systems(OnEnter(FrontendState::MainMenu), menu_setup)
         .add_systems(OnEnter(FrontendState::MainMenu), menu_setup)
         .add_systems(OnEnter(MenuState::Main), main_menu_setup)
         .add_systems(OnExit(MenuState::Main), despawn_menu::<OnMainMenu>)
-        .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
-        .add_systems(OnExit(MenuState::Settings), despawn_menu::<OnStatusMenu>)
-        .add_systems(OnEnter(FrontendState::LevelSelectMenu), setup_game)
+        .add_plugins(settings_menu_plugin)
         .add_systems(
             Update,
             (menu_action, button_colour_update).run_if(in_state(FrontendState::MainMenu)),
@@ -50,11 +56,8 @@ pub fn main_menu_plugin(app: &mut App) -> () {
 }
 
 fn button_colour_update(
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-        (Changed<Interaction>, With<Button>),
-    >,
-) -> () {
+    mut interaction_query: Query<ColouredButton, (Changed<Interaction>, With<Button>)>,
+) {
     for (interaction, mut background_colour, selected) in &mut interaction_query {
         match (*interaction, selected) {
             (Interaction::Pressed, _) | (Interaction::None, Some(_)) => {
@@ -73,76 +76,11 @@ fn button_colour_update(
     }
 }
 
-fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) -> () {
+fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
     menu_state.set(MenuState::Main);
 }
 
-fn settings_menu_setup(mut commands: Commands) -> () {
-    let button_node = Node {
-        width: Val::Px(200.0),
-        height: Val::Px(65.0),
-        margin: UiRect::all(Val::Px(20.0)),
-        justify_content: JustifyContent::Center,
-        align_items: AlignItems::Center,
-        ..default()
-    };
-
-    let button_text_style = (
-        TextFont {
-            font_size: 33.0,
-            ..default()
-        },
-        TextColor(TEXT_COLOR),
-    );
-
-    commands
-        .spawn((
-            Node {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                ..default()
-            },
-            OnStatusMenu,
-        ))
-        .with_children(|parent| {
-            parent
-                .spawn((
-                    Node {
-                        flex_direction: FlexDirection::Column,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    BackgroundColor(CRIMSON.into()),
-                ))
-                .with_children(|parent| {
-                    parent.spawn((
-                        Text::new("Settings (Under Construction)"),
-                        TextFont {
-                            font_size: 67.0,
-                            ..default()
-                        },
-                        TextColor(TEXT_COLOR),
-                        Node {
-                            margin: UiRect::all(Val::Px(50.0)),
-                            ..default()
-                        },
-                    ));
-                    parent
-                        .spawn((
-                            Button,
-                            button_node.clone(),
-                            BackgroundColor(NORMAL_BUTTON),
-                            MenuButtonAction::ToMainMenu,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn((Text::new("Main Menu"), button_text_style.clone()));
-                        });
-                });
-        });
-}
-fn main_menu_setup(mut commands: Commands) -> () {
+fn main_menu_setup(mut commands: Commands) {
     // Common style for all buttons on the screen
     let button_node = Node {
         width: Val::Px(300.0),
@@ -202,8 +140,9 @@ fn main_menu_setup(mut commands: Commands) -> () {
                         },
                     ));
 
-                    // Display three buttons for each action available from the main menu:
+                    // Display a button for each action available from the main menu:
                     // - new game
+                    // - level editor
                     // - settings
                     // - quit
                     parent
@@ -220,6 +159,20 @@ fn main_menu_setup(mut commands: Commands) -> () {
                                 TextColor(TEXT_COLOR),
                             ));
                         });
+                    parent
+                        .spawn((
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::Editor,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Text::new("Level Editor"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ));
+                        });
                     parent
                         .spawn((
                             Button,
@@ -252,24 +205,12 @@ fn main_menu_setup(mut commands: Commands) -> () {
         });
 }
 
-fn setup_game(
-    mut menu_state: ResMut<NextState<MenuState>>,
-    mut game_state: ResMut<NextState<FrontendState>>,
-) -> () {
-    play_level();
-    game_state.set(FrontendState::MainMenu);
-    menu_state.set(MenuState::Main);
-}
-
 fn menu_action(
-    interaction_query: Query<
-        (&Interaction, &MenuButtonAction),
-        (Changed<Interaction>, With<Button>),
-    >,
+    interaction_query: Query<ClickedButton, (Changed<Interaction>, With<Button>)>,
     mut app_exit_events: EventWriter<AppExit>,
     mut menu_state: ResMut<NextState<MenuState>>,
     mut game_state: ResMut<NextState<FrontendState>>,
-) -> () {
+) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match menu_button_action {
@@ -277,6 +218,10 @@ fn menu_action(
                     game_state.set(FrontendState::LevelSelectMenu);
                     menu_state.set(MenuState::Disabled);
                 }
+                MenuButtonAction::Editor => {
+                    game_state.set(FrontendState::Editor);
+                    menu_state.set(MenuState::Disabled);
+                }
                 MenuButtonAction::Exit => {
                     app_exit_events.send(AppExit::Success);
                 }
@@ -291,7 +236,7 @@ fn menu_action(
     }
 }
 
-fn despawn_menu<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) -> () {
+fn despawn_menu<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
         commands.entity(entity).despawn_recursive();
     }
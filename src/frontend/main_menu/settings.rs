@@ -0,0 +1,438 @@
+/// The settings screen of the main menu
+///
+/// Every setting is a button showing its value and a click on it goes to the next value, the profile goes
+/// through the stored profiles. A click on the keys of an action waits for a key, which then becomes the
+/// first key of the action, Esc cancels. Every change is saved at once and Esc goes back to the main menu.
+/// Problems with the settings file are shown instead of the help line until they are saved over.
+use bevy::{color::palettes::css::CRIMSON, prelude::*};
+
+use super::{MenuButtonAction, MenuState, NORMAL_BUTTON, TEXT_COLOR, despawn_menu};
+use crate::backend::profile::DEFAULT_PROFILE;
+use crate::frontend::config::{Config, Theme, WindowMode};
+use crate::frontend::frontend_utils::{Settings, key_code, key_label, key_name};
+
+const WINDOW_SIZES: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+const ANIMATION_SPEEDS: [f32; 5] = [0.5, 1.0, 1.5, 2.0, 3.0];
+const ROW_HEIGHT: f32 = 38.0;
+const HELP_LINE: &str = "Click a setting to change it, changes are saved at once";
+
+#[derive(Component)]
+struct OnSettingsMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SettingButton {
+    WindowMode,
+    WindowSize,
+    Animations,
+    AnimationSpeed,
+    Theme,
+    ConfirmRestart,
+    /// The stored profiles and the default one in turn
+    Profile,
+    /// Index into KeyBindings::actions
+    Key(usize),
+    Reset,
+}
+
+/// The text showing the value of a setting
+#[derive(Component)]
+struct SettingValue(SettingButton);
+
+#[derive(Component)]
+struct SettingsMessage;
+
+type ClickedSetting = (&'static Interaction, &'static SettingButton);
+
+/// The action waiting for its new key
+#[derive(Resource, Default)]
+struct Rebinding(Option<usize>);
+
+pub(super) fn settings_menu_plugin(app: &mut App) {
+    app.init_resource::<Rebinding>()
+        .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
+        .add_systems(
+            OnExit(MenuState::Settings),
+            (despawn_menu::<OnSettingsMenu>, stop_rebinding),
+        )
+        .add_systems(
+            Update,
+            (setting_action, rebind_key, update_values)
+                .chain()
+                .run_if(in_state(MenuState::Settings)),
+        );
+}
+
+fn on_off(on: bool) -> String {
+    String::from(if on { "On" } else { "Off" })
+}
+
+fn value_label(setting: SettingButton, config: &Config, rebinding: Option<usize>) -> String {
+    match setting {
+        SettingButton::WindowMode => String::from(match config.window.mode {
+            WindowMode::Windowed => "Windowed",
+            WindowMode::Borderless => "Borderless",
+            WindowMode::Fullscreen => "Fullscreen",
+        }),
+        SettingButton::WindowSize => format!("{}x{}", config.window.width, config.window.height),
+        SettingButton::Animations => on_off(!config.animation.instant),
+        SettingButton::AnimationSpeed => format!("{}x", config.animation.speed),
+        SettingButton::Theme => String::from(match config.theme {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::HighContrast => "High contrast",
+        }),
+        SettingButton::ConfirmRestart => on_off(config.confirm_restart),
+        SettingButton::Profile => config.profile.clone(),
+        SettingButton::Key(action) if rebinding == Some(action) => String::from("Press a key"),
+        SettingButton::Key(action) => {
+            let names: &[String] = config.keys.actions()[action].1;
+            if names.is_empty() {
+                String::from("None")
+            } else {
+                let labels: Vec<&str> = names.iter().map(|name| key_label(name)).collect();
+                labels.join(", ")
+            }
+        }
+        SettingButton::Reset => String::from("Reset to defaults"),
+    }
+}
+
+/// The option after the current one, or the first option when the current one is not in the list
+fn next_option<T: Clone + PartialEq>(options: &[T], current: T) -> T {
+    match options.iter().position(|option| *option == current) {
+        Some(position) => options[(position + 1) % options.len()].clone(),
+        None => options[0].clone(),
+    }
+}
+
+fn change_setting(config: &mut Config, setting: SettingButton) {
+    match setting {
+        SettingButton::WindowMode => {
+            config.window.mode = next_option(
+                &[
+                    WindowMode::Windowed,
+                    WindowMode::Borderless,
+                    WindowMode::Fullscreen,
+                ],
+                config.window.mode,
+            )
+        }
+        SettingButton::WindowSize => {
+            let (width, height) =
+                next_option(&WINDOW_SIZES, (config.window.width, config.window.height));
+            config.window.width = width;
+            config.window.height = height;
+        }
+        SettingButton::Animations => config.animation.instant = !config.animation.instant,
+        SettingButton::AnimationSpeed => {
+            config.animation.speed = next_option(&ANIMATION_SPEEDS, config.animation.speed)
+        }
+        SettingButton::Theme => {
+            config.theme = next_option(
+                &[Theme::Dark, Theme::Light, Theme::HighContrast],
+                config.theme,
+            )
+        }
+        SettingButton::ConfirmRestart => config.confirm_restart = !config.confirm_restart,
+        SettingButton::Profile => {
+            // New profiles are made by entering their name in the console
+            let mut names: Vec<String> = config.profile_store().list().unwrap_or_default();
+            if !names.iter().any(|name| name == DEFAULT_PROFILE) {
+                names.insert(0, String::from(DEFAULT_PROFILE));
+            }
+            config.profile = next_option(&names, config.profile.clone());
+        }
+        SettingButton::Reset => *config = Config::default(),
+        SettingButton::Key(_) => (),
+    }
+}
+
+fn spawn_setting_row(
+    parent: &mut ChildBuilder,
+    label: &str,
+    setting: SettingButton,
+    config: &Config,
+) {
+    parent
+        .spawn(Node {
+            width: Val::Px(440.0),
+            height: Val::Px(ROW_HEIGHT),
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(220.0),
+                        height: Val::Px(ROW_HEIGHT - 6.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(NORMAL_BUTTON),
+                    setting,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(value_label(setting, config, None)),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        SettingValue(setting),
+                    ));
+                });
+        });
+}
+
+/// The problems of the settings file if there are any, the help line otherwise
+fn help_text(settings: &Settings) -> String {
+    match &settings.notice {
+        Some(notice) => notice.clone(),
+        None => String::from(HELP_LINE),
+    }
+}
+
+fn settings_menu_setup(mut commands: Commands, settings: Res<Settings>) {
+    let config: &Config = &settings.config;
+    let column_node = Node {
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(4.0),
+        ..default()
+    };
+    let button_node = Node {
+        width: Val::Px(260.0),
+        height: Val::Px(50.0),
+        margin: UiRect::horizontal(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 26.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            OnSettingsMenu,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(24.0)),
+                        row_gap: Val::Px(16.0),
+                        ..default()
+                    },
+                    BackgroundColor(CRIMSON.into()),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Settings"),
+                        TextFont {
+                            font_size: 50.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                    ));
+
+                    parent
+                        .spawn(Node {
+                            column_gap: Val::Px(40.0),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(column_node.clone()).with_children(|parent| {
+                                let general = [
+                                    ("Window", SettingButton::WindowMode),
+                                    ("Window size", SettingButton::WindowSize),
+                                    ("Animations", SettingButton::Animations),
+                                    ("Animation speed", SettingButton::AnimationSpeed),
+                                    ("Theme", SettingButton::Theme),
+                                    ("Confirm restart", SettingButton::ConfirmRestart),
+                                    ("Profile", SettingButton::Profile),
+                                ];
+                                for (label, setting) in general {
+                                    spawn_setting_row(parent, label, setting, config);
+                                }
+                            });
+                            parent.spawn(column_node).with_children(|parent| {
+                                for (action, (label, _)) in
+                                    config.keys.actions().into_iter().enumerate()
+                                {
+                                    spawn_setting_row(
+                                        parent,
+                                        label,
+                                        SettingButton::Key(action),
+                                        config,
+                                    );
+                                }
+                            });
+                        });
+
+                    parent.spawn(Node::default()).with_children(|parent| {
+                        parent
+                            .spawn((
+                                Button,
+                                button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                SettingButton::Reset,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    Text::new(value_label(SettingButton::Reset, config, None)),
+                                    button_text_style.clone(),
+                                ));
+                            });
+                        parent
+                            .spawn((
+                                Button,
+                                button_node,
+                                BackgroundColor(NORMAL_BUTTON),
+                                MenuButtonAction::ToMainMenu,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((Text::new("Main Menu"), button_text_style));
+                            });
+                    });
+
+                    parent.spawn((
+                        Text::new(help_text(&settings)),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        SettingsMessage,
+                    ));
+                });
+        });
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
+fn setting_action(
+    buttons: Query<ClickedSetting, (Changed<Interaction>, With<Button>)>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut message: Query<&mut Text, With<SettingsMessage>>,
+) {
+    for (interaction, setting) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let text: String = match setting {
+            SettingButton::Key(action) => {
+                rebinding.0 = Some(*action);
+                let label: &str = settings.config.keys.actions()[*action].0;
+                format!("Press the new key for {}, Esc cancels", label)
+            }
+            _ => {
+                rebinding.0 = None;
+                change_setting(&mut settings.config, *setting);
+                settings.save();
+                help_text(&settings)
+            }
+        };
+        for mut message in &mut message {
+            message.0 = text.clone();
+        }
+    }
+}
+
+/// Binds the key pressed after clicking the keys of an action, taking it away from the other actions
+fn rebind_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut message: Query<&mut Text, With<SettingsMessage>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    let Some(key) = keys.get_just_pressed().next().copied() else {
+        return;
+    };
+    let Some(action) = rebinding.0 else {
+        if key == KeyCode::Escape {
+            menu_state.set(MenuState::Main);
+        }
+        return;
+    };
+    rebinding.0 = None;
+
+    let name: String = key_name(key);
+    let text: String = if key == KeyCode::Escape {
+        help_text(&settings)
+    } else if key_code(&name).is_none() {
+        format!("{} cannot be bound", name)
+    } else {
+        let mut action_label: &str = "";
+        let mut taken_from: Vec<&str> = vec![];
+        for (index, (label, names)) in settings.config.keys.actions_mut().into_iter().enumerate() {
+            if index == action {
+                action_label = label;
+                match names.iter().position(|bound| *bound == name) {
+                    Some(position) => names.swap(0, position),
+                    None if names.is_empty() => names.push(name.clone()),
+                    None => names[0] = name.clone(),
+                }
+            } else if names.contains(&name) {
+                names.retain(|bound| *bound != name);
+                taken_from.push(label);
+            }
+        }
+        settings.save();
+        if taken_from.is_empty() {
+            format!("{} is now {}", action_label, key_label(&name))
+        } else {
+            format!(
+                "{} is now {}, it was taken from {}",
+                action_label,
+                key_label(&name),
+                taken_from.join(", ")
+            )
+        }
+    };
+    for mut message in &mut message {
+        message.0 = text.clone();
+    }
+}
+
+fn update_values(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut values: Query<(&SettingValue, &mut Text)>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (value, mut text) in &mut values {
+        text.0 = value_label(value.0, &settings.config, rebinding.0);
+    }
+}
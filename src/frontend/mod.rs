@@ -1,22 +1,154 @@
-use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use std::path::{Path, PathBuf};
 
+#[cfg(feature = "gui")]
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow},
+};
+
+#[cfg(feature = "gui")]
+use config::{Config, DEFAULT_CONFIG_FALLBACK, WindowMode, WindowSettings};
+
+#[cfg(feature = "gui")]
 mod frontend_utils;
-use frontend_utils::FrontendState;
+#[cfg(feature = "gui")]
+use frontend_utils::{FrontendState, Palette, Settings, key_code};
+
+#[cfg(feature = "gui")]
+mod game_scene;
+#[cfg(feature = "gui")]
+use game_scene::game_scene_plugin;
 
+#[cfg(feature = "gui")]
+mod level_editor;
+#[cfg(feature = "gui")]
+use level_editor::level_editor_plugin;
+
+#[cfg(feature = "gui")]
+mod level_select;
+#[cfg(feature = "gui")]
+use level_select::level_select_plugin;
+
+#[cfg(feature = "gui")]
 mod main_menu;
+#[cfg(feature = "gui")]
 use main_menu::main_menu_plugin;
 
-mod play_level;
+/// The settings are kept apart from Bevy, so that they can be checked without it
+pub mod config;
+/// The console frontend does not need Bevy
+pub mod console_render;
+pub mod edit_level;
+pub mod play_level;
 
+#[cfg(feature = "gui")]
 pub fn start_game_frontend() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let path: PathBuf = Config::locate(Path::new(DEFAULT_CONFIG_FALLBACK));
+    let (config, notice, read_only): (Config, Option<String>, bool) = match Config::load(&path) {
+        Ok((config, problems)) if problems.is_empty() => (config, None, false),
+        Ok((config, problems)) => (
+            config,
+            Some(format!(
+                "Invalid settings {}, their defaults are used: {}",
+                path.display(),
+                problems.join(", ")
+            )),
+            false,
+        ),
+        Err(msg) => (
+            Config::default(),
+            Some(format!(
+                "{} The defaults are used and changes are not saved until the file is fixed.",
+                msg
+            )),
+            true,
+        ),
+    };
+
+    let mut app: App = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: String::from("Sliding Game"),
+            ..configured_window(&config.window)
+        }),
+        ..default()
+    }));
+    // Logging only works once the default plugins are added
+    if let Some(msg) = &notice {
+        warn!("{}", msg);
+    }
+    for (action, names) in config.keys.actions() {
+        for name in names.iter().filter(|name| key_code(name).is_none()) {
+            warn!("The key {} of {} is not known and is ignored", name, action);
+        }
+    }
+
+    app.insert_resource(ClearColor(Palette::of(config.theme).background))
+        .insert_resource(Settings {
+            config,
+            path,
+            notice,
+            read_only,
+        })
         .init_state::<FrontendState>()
         .add_systems(Startup, init_camera)
+        .add_systems(Update, apply_settings)
         .add_plugins(main_menu_plugin)
+        .add_plugins(level_select_plugin)
+        .add_plugins(game_scene_plugin)
+        .add_plugins(level_editor_plugin)
         .run();
 }
 
+#[cfg(feature = "gui")]
 fn init_camera(mut commands: Commands) {
     commands.spawn(Camera2d);
 }
+
+/// A window with the mode and size of the settings
+#[cfg(feature = "gui")]
+fn configured_window(settings: &WindowSettings) -> Window {
+    let mut window: Window = Window {
+        mode: match settings.mode {
+            WindowMode::Windowed => bevy::window::WindowMode::Windowed,
+            WindowMode::Borderless => {
+                bevy::window::WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+            }
+            WindowMode::Fullscreen => {
+                bevy::window::WindowMode::Fullscreen(MonitorSelection::Current)
+            }
+        },
+        ..default()
+    };
+    window
+        .resolution
+        .set(settings.width as f32, settings.height as f32);
+    window
+}
+
+/// Brings the window and the background in line with settings changed in the settings menu
+#[cfg(feature = "gui")]
+fn apply_settings(
+    settings: Res<Settings>,
+    mut applied: Local<Option<WindowSettings>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    clear_color.0 = Palette::of(settings.config.theme).background;
+
+    // The window is left alone when only other settings changed, so that it keeps a size set by hand
+    let window_settings: WindowSettings = settings.config.window;
+    if applied.replace(window_settings) == Some(window_settings) || settings.is_added() {
+        return;
+    }
+    for mut window in &mut windows {
+        window.mode = configured_window(&window_settings).mode;
+        window
+            .resolution
+            .set(window_settings.width as f32, window_settings.height as f32);
+    }
+}
@@ -1,244 +1,496 @@
-use std::collections::HashSet;
-use std::io::{self};
+use std::io::{self, BufRead, IsTerminal};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+mod tui;
+pub(crate) use tui::TerminalGuard;
 
 use crate::backend::{
     Board, GameState,
     io::BoardLoadingError,
-    utils_backend::{AgentID, Coordinate, Direction, Index, TextureType},
+    level_file::LevelMeta,
+    notation::{format_move, parse_moves},
+    packs::{DEFAULT_PACKS_DIR, LevelPack, PackListing, list_packs},
+    profile::{LevelProgress, Profile, ProfileStore},
+    utils_backend::{AgentID, GoalType},
 };
+use crate::frontend::config::{Config, DEFAULT_CONFIG_FALLBACK};
+use crate::frontend::console_render::{RenderOptions, render_board};
 
-pub fn play_level() -> () {
-    let stdin = io::stdin();
+pub fn play_level(options: RenderOptions) {
     let input = &mut String::new();
 
-    print!("Please enter which testing level you want to play:\n");
+    // The console shares the profiles, and where they are kept, with the graphical frontend
+    let config: Config = match Config::load(&Config::locate(Path::new(DEFAULT_CONFIG_FALLBACK))) {
+        Ok((c, problems)) => {
+            for problem in problems {
+                println!("Invalid setting {}, its default is used instead", problem);
+            }
+            c
+        }
+        Err(msg) => {
+            println!("{} The default settings are used instead.", msg);
+            Config::default()
+        }
+    };
+    let store: ProfileStore = config.profile_store();
+    let Some(mut profile) = choose_profile(&store, &config.profile) else {
+        return;
+    };
+
+    let listing: PackListing = match list_packs(Path::new(DEFAULT_PACKS_DIR)) {
+        Ok(l) => l,
+        Err(msg) => {
+            println!("{}", msg);
+            return;
+        }
+    };
+    for error in &listing.errors {
+        println!(
+            "Could not load the level pack {}: {}",
+            error.id, error.message
+        );
+    }
+    let packs: Vec<LevelPack> = listing.packs;
+    if packs.is_empty() {
+        println!("There are no level packs in {}", DEFAULT_PACKS_DIR);
+        return;
+    }
 
+    println!("Level packs:");
+    for (number, pack) in packs.iter().enumerate() {
+        println!(
+            "{:>3}. {} ({} levels)",
+            number + 1,
+            pack.manifest.name,
+            pack.len()
+        );
+        if let Some(description) = &pack.manifest.description {
+            println!("     {}", description);
+        }
+    }
+    println!("Please choose a pack by its number (or load <file> to resume a saved game):");
+
+    if !read_input(input) {
+        return;
+    }
+
+    if let Some(save) = input.trim().strip_prefix("load ") {
+        match Board::load_game(save.trim()) {
+            Err(msg) => print_loading_error(msg),
+            Ok(board) => {
+                run_board_console(board, Duration::ZERO, options);
+            }
+        }
+        return;
+    }
+
+    match input.trim().parse::<usize>() {
+        Ok(number) if number >= 1 && number <= packs.len() => {
+            play_pack(&packs[number - 1], &store, &mut profile, options);
+        }
+        _ => println!("There is no pack {}", input.trim()),
+    }
+}
+
+/// Plays a single board in the terminal and returns whether the player won
+pub fn play_board(board: Board, options: RenderOptions) -> bool {
+    run_board_console(board, Duration::ZERO, options).won
+}
+
+/// Reads the next line of stdin into input, false at the end of the input or when it can not be read
+fn read_input(input: &mut String) -> bool {
+    read_line_from(&mut io::stdin().lock(), input)
+}
+
+fn read_line_from(reader: &mut impl BufRead, input: &mut String) -> bool {
     input.clear();
-    stdin.read_line(input).unwrap();
+    matches!(reader.read_line(input), Ok(read) if read > 0)
+}
 
-    let mut path = String::from("levels/testing_levels/");
-    path.push_str(input.trim());
-    path.push_str(".toml");
+/// The profile the player names, None if the input ends first. An empty name is the profile the graphical
+/// frontend plays with.
+fn choose_profile(store: &ProfileStore, offered: &str) -> Option<Profile> {
+    let input = &mut String::new();
 
-    print!("{}", path.as_str());
+    if let Ok(names) = store.list()
+        && !names.is_empty()
+    {
+        println!("Profiles: {}", names.join(", "));
+    }
+    loop {
+        println!(
+            "Please enter your profile name (press enter for {}):",
+            offered
+        );
+        if !read_input(input) {
+            return None;
+        }
+        let name: &str = match input.trim() {
+            "" => offered,
+            name => name,
+        };
+        match store.load(name) {
+            Ok(profile) => return Some(profile),
+            Err(msg) => println!("{}", msg),
+        }
+    }
+}
 
-    run_game_console(path);
+fn format_time(time: Duration) -> String {
+    let seconds: u64 = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
 }
 
-fn print_board(board: &Board) {
-    let dimensions: (Index, Index) = board.get_dimensions();
+/// Selected agent, moves, progress towards the victory conditions and time played on one line
+pub(crate) fn status_bar(board: &Board, selected: AgentID, time: Duration) -> String {
+    let mut parts: Vec<String> = vec![format!("Agent {}", selected)];
 
-    for y in 0..dimensions.1 {
-        for x in 0..dimensions.0 {
-            match board.read_block(Coordinate { x, y }).get_texture() {
-                TextureType::BasicImpassable => {
-                    print!("█████");
-                }
-                TextureType::BasicBlock => {
-                    print!("▒▒▒▒▒");
-                }
-                TextureType::Goal(_) => {
-                    print!("▚▚▚▚▚");
-                }
-                TextureType::None => {
-                    print!("     ");
-                }
-            }
+    match board.get_meta().par {
+        Some(par) => parts.push(format!("Moves {} (par {})", board.move_count(), par)),
+        None => parts.push(format!("Moves {}", board.move_count())),
+    }
+    for (name, current, goal) in board.get_goal_progress() {
+        let goal: String = match goal {
+            GoalType::Exactly(num) => num.to_string(),
+            GoalType::AtLeast(num) => format!(">={}", num),
+            GoalType::AtMost(num) => format!("<={}", num),
+        };
+        parts.push(format!("{} {}/{}", name, current, goal));
+    }
+    parts.push(format_time(time));
+
+    parts.join(" | ")
+}
+
+pub(crate) fn level_status(pack: &LevelPack, index: usize, profile: &Profile) -> String {
+    match profile.progress(&pack.level_id(index)) {
+        Some(LevelProgress {
+            solved: true,
+            best_moves: Some(moves),
+            best_time_ms: Some(time_ms),
+            ..
+        }) => format!(
+            "solved, best {} moves and {}",
+            moves,
+            format_time(Duration::from_millis(*time_ms))
+        ),
+        Some(LevelProgress { save: Some(_), .. }) => String::from("unfinished"),
+        _ if pack.is_unlocked(index, &profile.solved_levels()) => String::new(),
+        _ => String::from("locked"),
+    }
+}
+
+/// Title of the level, or its ID when it has none
+pub(crate) fn level_label(pack: &LevelPack, index: usize) -> String {
+    match pack.level_meta(index) {
+        Ok(LevelMeta {
+            title: Some(title), ..
+        }) => title,
+        _ => pack.level_id(index),
+    }
+}
+
+fn play_pack(
+    pack: &LevelPack,
+    store: &ProfileStore,
+    profile: &mut Profile,
+    options: RenderOptions,
+) {
+    let input = &mut String::new();
+
+    if pack.is_empty() {
+        println!("The pack {} has no levels", pack.manifest.name);
+        return;
+    }
+
+    loop {
+        print!("\n{}\n", pack.manifest.name);
+        for index in 0..pack.len() {
+            println!(
+                "{:>3}. {:<40} {}",
+                index + 1,
+                level_label(pack, index),
+                level_status(pack, index, profile)
+            );
         }
-        print!("\n");
-        for x in 0..dimensions.0 {
-            match board.read_block(Coordinate { x, y }).get_texture() {
-                TextureType::BasicImpassable => {
-                    print!("█   █");
-                }
-                TextureType::BasicBlock => {
-                    let agents: HashSet<AgentID> =
-                        board.read_block(Coordinate { x, y }).get_agents();
-                    print!(
-                        "▒{} {}▒",
-                        if agents.contains(&0) { "0" } else { " " },
-                        if agents.contains(&1) { "1" } else { " " }
-                    );
-                }
-                TextureType::Goal(_) => {
-                    let agents: HashSet<AgentID> =
-                        board.read_block(Coordinate { x, y }).get_agents();
-                    print!(
-                        "▚{} {}▚",
-                        if agents.contains(&0) { "0" } else { " " },
-                        if agents.contains(&1) { "1" } else { " " }
-                    );
-                }
-                TextureType::None => {
-                    print!("     ");
-                }
-            }
+        println!("Please choose a level by its number (or type quit):");
+
+        if !read_input(input) || input.trim() == "quit" {
+            return;
         }
-        print!("\n");
-        for x in 0..dimensions.0 {
-            match board.read_block(Coordinate { x, y }).get_texture() {
-                TextureType::BasicImpassable => {
-                    print!("█   █");
-                }
-                TextureType::BasicBlock => {
-                    print!("▒   ▒");
-                }
-                TextureType::Goal(num) => {
-                    print!(
-                        "▚{}/{}▚",
-                        num,
-                        board.read_block(Coordinate { x, y }).get_agents().len()
-                    );
-                }
-                TextureType::None => {
-                    print!("     ");
-                }
+        let mut index: usize = match input.trim().parse::<usize>() {
+            Ok(number) if number >= 1 && number <= pack.len() => number - 1,
+            _ => {
+                println!("There is no level {}", input.trim());
+                continue;
             }
+        };
+        if !pack.is_unlocked(index, &profile.solved_levels()) {
+            println!("That level is still locked");
+            continue;
         }
-        print!("\n");
-        for x in 0..dimensions.0 {
-            match board.read_block(Coordinate { x, y }).get_texture() {
-                TextureType::BasicImpassable => {
-                    print!("█   █");
-                }
-                TextureType::BasicBlock => {
-                    let agents: HashSet<AgentID> =
-                        board.read_block(Coordinate { x, y }).get_agents();
-                    print!(
-                        "▒{} {}▒",
-                        if agents.contains(&2) { "2" } else { " " },
-                        if agents.contains(&3) { "3" } else { " " }
-                    );
+
+        // Keep playing the following levels as long as the player wins and wants to go on
+        loop {
+            let level_id: String = pack.level_id(index);
+            let Some((board, played_before)) = start_level(pack, index, profile) else {
+                break;
+            };
+            let attempt: Attempt = run_board_console(board, played_before, options);
+
+            if attempt.won {
+                profile.record_win(
+                    &level_id,
+                    attempt.board.move_count(),
+                    attempt.time,
+                    attempt.undos,
+                );
+            } else if attempt.board.move_count() > 0 {
+                profile.record_unfinished(&level_id, &attempt.board, attempt.time, attempt.undos);
+            }
+            if let Err(msg) = store.store(profile) {
+                println!("Could not save your progress. {}", msg);
+            }
+            if !attempt.won {
+                break;
+            }
+
+            match pack.next_level(Some(index), &profile.solved_levels()) {
+                None => {
+                    println!("There are no more levels to play in this pack right now.");
+                    break;
                 }
-                TextureType::Goal(_) => {
-                    let agents: HashSet<AgentID> =
-                        board.read_block(Coordinate { x, y }).get_agents();
-                    print!(
-                        "▚{} {}▚",
-                        if agents.contains(&2) { "2" } else { " " },
-                        if agents.contains(&3) { "3" } else { " " }
+                Some(next) => {
+                    println!(
+                        "Next level: {}. Press enter to play it or type quit to choose another level.",
+                        level_label(pack, next)
                     );
-                }
-                TextureType::None => {
-                    print!("     ");
+                    if !read_input(input) || input.trim() == "quit" {
+                        break;
+                    }
+                    index = next;
                 }
             }
         }
-        print!("\n");
-        for x in 0..dimensions.0 {
-            match board.read_block(Coordinate { x, y }).get_texture() {
-                TextureType::BasicImpassable => {
-                    print!("█████");
-                }
-                TextureType::BasicBlock => {
-                    print!("▒▒▒▒▒");
-                }
-                TextureType::Goal(_) => {
-                    print!("▚▚▚▚▚");
-                }
-                TextureType::None => {
-                    print!("     ");
-                }
+    }
+}
+
+/// Loads the level, or the game the player left unfinished if they want to continue it
+fn start_level(pack: &LevelPack, index: usize, profile: &Profile) -> Option<(Board, Duration)> {
+    let input = &mut String::new();
+
+    match profile.resume(&pack.level_id(index)) {
+        Some(Ok((board, played_before))) => {
+            println!(
+                "You left this level unfinished. Press enter to continue that game or type new to start over."
+            );
+            if !read_input(input) {
+                return None;
+            }
+            if input.trim() != "new" {
+                return Some((board, played_before));
             }
         }
-        print!("\n");
+        Some(Err(e)) => println!("Could not resume your unfinished game. {}", e),
+        None => (),
+    }
+
+    match pack.load_level(index) {
+        Ok(board) => Some((board, Duration::ZERO)),
+        Err(e) => {
+            print_loading_error(e);
+            None
+        }
     }
 }
 
-fn char_to_direction(input: &String) -> Result<Direction, String> {
-    if input.trim().eq(&String::from("u")) {
-        return Ok(Direction::Up);
+fn print_board(board: &Board, options: RenderOptions) {
+    print!("{}", render_board(board, options));
+}
+
+fn print_loading_error(error: BoardLoadingError) {
+    println!("Could not load the level. {}", error);
+}
+
+fn print_level_info(board: &Board) {
+    let meta: &LevelMeta = board.get_meta();
+
+    if let Some(title) = &meta.title {
+        print!("\n{}\n", title);
     }
-    if input.trim().eq(&String::from("d")) {
-        return Ok(Direction::Down);
+    if let Some(author) = &meta.author {
+        println!("by {}", author);
     }
-    if input.trim().eq(&String::from("l")) {
-        return Ok(Direction::Left);
+    if let Some(description) = &meta.description {
+        println!("{}", description);
     }
-    if input.trim().eq(&String::from("r")) {
-        return Ok(Direction::Right);
+    if let Some(par) = meta.par {
+        println!("Par: {} moves", par);
     }
+    println!();
+}
 
-    Err(String::from("Not a direction"))
+fn print_move_count(board: &Board) {
+    match board.get_meta().par {
+        Some(par) => println!("Moves: {} (par {})", board.move_count(), par),
+        None => println!("Moves: {}", board.move_count()),
+    }
 }
 
-fn run_game_console(file: String) -> () {
-    let mut board: Board;
-    match Board::from_file(file.as_str()) {
-        Err(msg) => {
-            match msg {
-                BoardLoadingError::FileNotFound => print!("File not found"),
-                BoardLoadingError::FileReadingError => print!("File could not be read"),
-                BoardLoadingError::TOMLParsingError => print!("TOML parsing failed"),
-                BoardLoadingError::BoardDescriptionError(text) => print!("{}", text),
+/// How a game of run_board_console ended
+struct Attempt {
+    board: Board,
+    won: bool,
+    /// Includes the time played before the game was resumed
+    time: Duration,
+    undos: usize,
+}
+
+fn print_win(board: &Board, time: Duration) {
+    print!("\n\nCONGRATULATIONS! YOU ARE A WINNER!\n");
+    if let Some(par) = board.get_meta().par {
+        if board.move_count() <= par {
+            println!(
+                "You solved it in {} moves, par is {}.",
+                board.move_count(),
+                par
+            );
+        } else {
+            println!(
+                "You solved it in {} moves, {} more than par.",
+                board.move_count(),
+                board.move_count() - par
+            );
+        }
+    }
+    println!("Time: {}", format_time(time));
+}
+
+/// Plays the board until the level is won or the player quits, full-screen when running in a terminal
+fn run_board_console(board: Board, played_before: Duration, options: RenderOptions) -> Attempt {
+    if io::stdin().is_terminal() && io::stdout().is_terminal() {
+        match tui::run_board_tui(board.clone(), played_before, options) {
+            Ok(attempt) => {
+                if attempt.won {
+                    print_win(&attempt.board, attempt.time);
+                }
+                return attempt;
             }
-            return;
+            Err(e) => println!(
+                "Could not start the full-screen game, using line input instead. {}",
+                e
+            ),
         }
-        Ok(b) => board = b,
-    };
-    print_board(&board);
+    }
+    run_board_lines(board, played_before, options, &mut io::stdin().lock())
+}
+
+/// Plays the board with one line of input at a time, for pipes and terminals without raw mode. The end of the
+/// input gives up like quit does
+fn run_board_lines(
+    mut board: Board,
+    played_before: Duration,
+    options: RenderOptions,
+    reader: &mut impl BufRead,
+) -> Attempt {
+    print_level_info(&board);
+    print_board(&board, options);
+    print_move_count(&board);
 
-    let stdin = io::stdin();
     let input = &mut String::new();
+    let start: Instant = Instant::now();
+    let mut undos: usize = 0;
 
     loop {
-        input.clear();
-        print!(
-            "Do you want to enter an agent (please type the ID) or undo the last action (type undo)?\n"
+        println!(
+            "Enter one or more moves (e.g. \"0r 1R\": agent ID, then u/d/l/r to walk or U/D/L/R to slide), undo the last action (type undo), save the game (type save <file>) or give up (type quit)"
         );
-        match stdin.read_line(input) {
-            Err(_) => {
-                print!("Somehow that input failed, please try again.\n");
-                continue;
+        if !read_line_from(reader, input) || input.trim() == "quit" {
+            return Attempt {
+                board,
+                won: false,
+                time: played_before + start.elapsed(),
+                undos,
+            };
+        }
+        if input.trim() == "undo" {
+            board.undo();
+            undos += 1;
+        } else if let Some(save) = input.trim().strip_prefix("save ") {
+            match board.save_game(save.trim()) {
+                Ok(()) => println!("Saved the game to {}", save.trim()),
+                Err(msg) => println!("{}", msg),
             }
-            Ok(_) => match input.trim().parse::<AgentID>() {
-                Ok(agent) => {
-                    input.clear();
-                    print!("Which direction do you want to move in (u, d, l, r)?\n");
-
-                    match stdin.read_line(input) {
-                        Err(_) => {
-                            print!("Reading the direction somehow failed\n");
-                        }
-                        Ok(_) => (),
-                    }
-
-                    match char_to_direction(&input) {
-                        Err(_) => {
-                            print!(
-                                "That was not a direction, please use a direction(u, d, l, r)\n"
-                            );
-                            continue;
+            continue;
+        } else {
+            match parse_moves(input) {
+                Err(e) => {
+                    println!("Could not read the moves, {}", e);
+                    continue;
+                }
+                Ok(moves) => {
+                    for player_input in moves {
+                        if let Err(reason) = board.check_input(player_input) {
+                            println!("Stopped before {}: {}", format_move(player_input), reason);
+                            break;
                         }
-                        Ok(direction) => {
-                            if board.can_move_agent(agent, direction) {
-                                board.move_agent(agent, direction);
-                            } else {
-                                print!("Sliding agent {} in direction {}", agent, input);
-                                board.slide_agent(agent, direction);
-                            }
+                        board.apply_input(player_input);
+                        if board.get_game_state() != GameState::Running {
+                            break;
                         }
                     }
                 }
-                Err(_) => {
-                    if input.trim() == String::from("undo") {
-                        board.undo();
-                    }
-                }
-            },
+            }
         }
-        print_board(&board);
+        print_board(&board, options);
+        print_move_count(&board);
         match board.get_game_state() {
             GameState::Won => {
-                print!("\n\nCONGRATULATIONS! YOU ARE A WINNER!\n");
-                return;
+                let time: Duration = played_before + start.elapsed();
+                print_win(&board, time);
+                return Attempt {
+                    board,
+                    won: true,
+                    time,
+                    undos,
+                };
             }
             GameState::Lost => {
                 print!("\n\nWomp womp, you lost.\n");
                 board.undo();
-                print_board(&board);
+                print_board(&board, options);
             }
             GameState::Running => (),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_board() -> Board {
+        Board::from_file("levels/testing_levels/example0.toml").unwrap()
+    }
+
+    #[test]
+    fn line_input_gives_up_at_the_end_of_the_input() {
+        let options: RenderOptions = RenderOptions::default();
+        let attempt: Attempt =
+            run_board_lines(example_board(), Duration::ZERO, options, &mut "".as_bytes());
+        assert!(!attempt.won);
+        assert_eq!(attempt.board.move_count(), 0);
+
+        // Moves before the end are still played, a last line without a newline included
+        let attempt: Attempt = run_board_lines(
+            example_board(),
+            Duration::ZERO,
+            options,
+            &mut "1U\nundo\n1U".as_bytes(),
+        );
+        assert!(!attempt.won);
+        assert_eq!(attempt.board.move_count(), 1);
+        assert_eq!(attempt.undos, 1);
+    }
+}
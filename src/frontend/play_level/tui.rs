@@ -0,0 +1,250 @@
+/// Full-screen terminal play with keyboard input in raw mode
+///
+/// 0-9 or Tab select an agent, the arrow keys (or w/a/s/d) walk it and Shift+arrows (or W/A/S/D) slide the
+/// block it stands on. u or Backspace undoes the last action, r restarts the level and q or Esc gives up.
+/// The terminal is put back the way it was when play ends, also when the game panics.
+use std::io::{self, Write};
+use std::panic;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue, style::Print};
+
+use super::{Attempt, status_bar};
+use crate::backend::utils_backend::{AgentID, Direction, PlayerInput};
+use crate::backend::{Board, GameState};
+use crate::frontend::console_render::{RenderOptions, RenderStyle, render_board};
+
+/// How often the clock in the status bar is redrawn while no key is pressed
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+const HELP_LINE: &str = "0-9/Tab agent  arrows walk  Shift+arrows slide  u undo  r restart  q quit";
+
+static RAW_MODE: AtomicBool = AtomicBool::new(false);
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Switches the terminal to the full-screen raw mode and back when dropped
+pub(crate) struct TerminalGuard;
+
+impl TerminalGuard {
+    pub(crate) fn enter() -> io::Result<Self> {
+        // A panic message printed on the alternate screen in raw mode would be unreadable or lost
+        INSTALL_PANIC_HOOK.call_once(|| {
+            let default_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore_terminal();
+                default_hook(info);
+            }));
+        });
+
+        terminal::enable_raw_mode()?;
+        RAW_MODE.store(true, Ordering::SeqCst);
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            restore_terminal();
+            return Err(e);
+        }
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+fn restore_terminal() {
+    if RAW_MODE.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+enum Command {
+    Select(AgentID),
+    NextAgent,
+    Input(PlayerInput),
+    Undo,
+    Restart,
+    Quit,
+}
+
+fn key_command(key: KeyEvent, selected: AgentID) -> Option<Command> {
+    let input = |direction: Direction, slide: bool| {
+        if slide {
+            Some(Command::Input(PlayerInput::Slide(selected, direction)))
+        } else {
+            Some(Command::Input(PlayerInput::Move(selected, direction)))
+        }
+    };
+    let shift: bool = key.modifiers.contains(KeyModifiers::SHIFT);
+
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Command::Quit),
+        KeyCode::Char(c) if c.is_ascii_digit() => {
+            Some(Command::Select(c.to_digit(10).unwrap() as AgentID))
+        }
+        KeyCode::Tab => Some(Command::NextAgent),
+        KeyCode::Up => input(Direction::Up, shift),
+        KeyCode::Down => input(Direction::Down, shift),
+        KeyCode::Left => input(Direction::Left, shift),
+        KeyCode::Right => input(Direction::Right, shift),
+        // For terminals that do not report Shift with the arrow keys
+        KeyCode::Char(c @ ('w' | 'W')) => input(Direction::Up, c.is_ascii_uppercase()),
+        KeyCode::Char(c @ ('s' | 'S')) => input(Direction::Down, c.is_ascii_uppercase()),
+        KeyCode::Char(c @ ('a' | 'A')) => input(Direction::Left, c.is_ascii_uppercase()),
+        KeyCode::Char(c @ ('d' | 'D')) => input(Direction::Right, c.is_ascii_uppercase()),
+        KeyCode::Char('u') | KeyCode::Backspace => Some(Command::Undo),
+        KeyCode::Char('r') => Some(Command::Restart),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+/// Number of characters on the screen, leaving out the colour escape sequences
+fn console_width(line: &str) -> usize {
+    let mut width: usize = 0;
+    let mut in_escape: bool = false;
+    for c in line.chars() {
+        match (in_escape, c) {
+            (false, '\x1b') => in_escape = true,
+            (false, _) => width += 1,
+            (true, 'm') => in_escape = false,
+            (true, _) => (),
+        }
+    }
+    width
+}
+
+fn draw(
+    out: &mut impl Write,
+    board: &Board,
+    selected: AgentID,
+    time: Duration,
+    message: &str,
+    options: RenderOptions,
+) -> io::Result<()> {
+    queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+
+    let mut board_text: String = render_board(board, options);
+    // Lines for the title, the status bar, the message and the help
+    let (columns, rows): (u16, u16) = terminal::size()?;
+    let fits: bool = board_text.lines().count() + 6 <= rows as usize
+        && board_text
+            .lines()
+            .all(|line| console_width(line) <= columns as usize);
+    if !fits {
+        board_text = render_board(
+            board,
+            RenderOptions {
+                style: RenderStyle::Compact,
+                ..options
+            },
+        );
+    }
+
+    let mut lines: Vec<String> = vec![];
+    if let Some(title) = &board.get_meta().title {
+        lines.push(title.clone());
+        lines.push(String::new());
+    }
+    lines.extend(board_text.lines().map(String::from));
+    lines.push(String::new());
+    lines.push(status_bar(board, selected, time));
+    lines.push(String::from(message));
+    lines.push(String::from(HELP_LINE));
+
+    // Raw mode does not return the cursor to the start of the line on a line feed
+    for line in lines {
+        queue!(out, Print(line), Print("\r\n"))?;
+    }
+    out.flush()
+}
+
+/// Plays the board full-screen until the level is won or the player gives up
+pub(super) fn run_board_tui(
+    mut board: Board,
+    played_before: Duration,
+    options: RenderOptions,
+) -> io::Result<Attempt> {
+    let _guard: TerminalGuard = TerminalGuard::enter()?;
+    let mut out = io::stdout();
+
+    let start: Instant = Instant::now();
+    let mut undos: usize = 0;
+    let mut selected: AgentID = 0;
+    let mut message: String = String::new();
+
+    loop {
+        let time: Duration = played_before + start.elapsed();
+
+        if board.get_game_state() == GameState::Won {
+            message = String::from("Solved! Press any key to continue.");
+            draw(&mut out, &board, selected, time, &message, options)?;
+            loop {
+                if let Event::Key(key) = event::read()?
+                    && key.kind == KeyEventKind::Press
+                {
+                    break;
+                }
+            }
+            return Ok(Attempt {
+                board,
+                won: true,
+                time,
+                undos,
+            });
+        }
+
+        draw(&mut out, &board, selected, time, &message, options)?;
+        if !event::poll(REDRAW_INTERVAL)? {
+            continue;
+        }
+        // Other events such as a resize only need the redraw at the start of the loop
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        message.clear();
+        match key_command(key, selected) {
+            Some(Command::Quit) => {
+                return Ok(Attempt {
+                    board,
+                    won: false,
+                    time,
+                    undos,
+                });
+            }
+            Some(Command::Select(agent)) if agent < board.get_num_agents() => selected = agent,
+            Some(Command::Select(agent)) => message = format!("There is no agent {}", agent),
+            // A level without agents has nobody to switch to
+            Some(Command::NextAgent) if board.get_num_agents() > 0 => {
+                selected = (selected + 1) % board.get_num_agents()
+            }
+            Some(Command::Undo) if board.move_count() > 0 => {
+                board.undo();
+                undos += 1;
+            }
+            Some(Command::Restart) => board.restart(),
+            Some(Command::Input(input)) => match board.check_input(input) {
+                Err(reason) => message = reason,
+                Ok(()) => {
+                    board.apply_input(input);
+                    if board.get_game_state() == GameState::Lost {
+                        board.undo();
+                        message =
+                            String::from("Womp womp, that lost the level. The move was undone.");
+                    }
+                }
+            },
+            None | Some(Command::Undo) | Some(Command::NextAgent) => (),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+/// The sliding game: the headless backend, the level tools and the frontends
+///
+/// Everything but the Bevy frontend builds without a game engine. The Bevy frontend is behind the `gui`
+/// feature, which is on by default, so solvers and tests can depend on the crate with
+/// `default-features = false`.
+pub mod backend;
+pub mod frontend;
+pub mod tools;
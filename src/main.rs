@@ -1,8 +1,120 @@
-mod backend;
+use sliding_game_2::backend::Board;
+use sliding_game_2::backend::packs::{DEFAULT_PACKS_DIR, find_level};
+use sliding_game_2::frontend::console_render::RenderOptions;
+use sliding_game_2::frontend::edit_level::edit_level;
+use sliding_game_2::frontend::play_level::{play_board, play_level};
+use sliding_game_2::tools::{USAGE, default_render_options, render_flag, run_tool, usage};
 
-mod frontend;
-use frontend::start_game_frontend;
+use std::path::Path;
+use std::process::ExitCode;
 
-fn main() {
-    start_game_frontend();
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|a| a.as_str()) {
+        None => usage(USAGE),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Some("play-gui") => play_gui(&args[1..]),
+        Some("play-console") => play_console(&args[1..]),
+        Some("edit") => edit(&args[1..]),
+        Some(_) => run_tool(&args),
+    }
+}
+
+/// play-gui
+fn play_gui(args: &[String]) -> ExitCode {
+    if !args.is_empty() {
+        return usage("Usage: play-gui");
+    }
+
+    #[cfg(feature = "gui")]
+    {
+        sliding_game_2::frontend::start_game_frontend();
+        ExitCode::SUCCESS
+    }
+    #[cfg(not(feature = "gui"))]
+    {
+        eprintln!("This build has no GUI, build it with the gui feature to use play-gui");
+        ExitCode::FAILURE
+    }
+}
+
+/// play-console [--compact] [--ascii] [--no-color] [--load <save>] [<level>]
+///
+/// Without a level or save the player chooses from the level packs. Fails if the player gives up the level.
+fn play_console(args: &[String]) -> ExitCode {
+    let mut options: RenderOptions = default_render_options();
+    let mut save: Option<&String> = None;
+    let mut levels: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            flag if render_flag(flag, &mut options) => (),
+            "--load" => match iter.next() {
+                Some(path) => save = Some(path),
+                None => return usage("--load needs a save file"),
+            },
+            _ => levels.push(arg),
+        }
+    }
+
+    let board: Result<Board, String> = match (save, &levels[..]) {
+        (None, []) => {
+            play_level(options);
+            return ExitCode::SUCCESS;
+        }
+        (Some(save), []) => Board::load_game(save).map_err(|e| e.to_string()),
+        (None, [level]) if Path::new(level.as_str()).is_file() => {
+            Board::from_file(level).map_err(|e| e.to_string())
+        }
+        (None, [level]) => find_level(Path::new(DEFAULT_PACKS_DIR), level)
+            .and_then(|(pack, index)| pack.load_level(index).map_err(|e| e.to_string())),
+        _ => {
+            return usage(
+                "Usage: play-console [--compact] [--ascii] [--no-color] [--load <save>] [<level>]",
+            );
+        }
+    };
+
+    match board {
+        Ok(board) => {
+            if play_board(board, options) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(msg) => {
+            eprintln!("Could not load the level. {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// edit [--ascii] [--no-color] <level>
+///
+/// Opens the level in the terminal editor, a level that does not exist yet is created when it is written.
+fn edit(args: &[String]) -> ExitCode {
+    let mut options: RenderOptions = default_render_options();
+    let mut levels: Vec<&String> = vec![];
+    for arg in args {
+        if !render_flag(arg, &mut options) {
+            levels.push(arg);
+        }
+    }
+    let [level] = levels[..] else {
+        return usage("Usage: edit [--ascii] [--no-color] <level>");
+    };
+
+    match edit_level(level, options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("Could not edit the level. {}", msg);
+            ExitCode::FAILURE
+        }
+    }
 }
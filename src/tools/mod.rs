@@ -0,0 +1,1015 @@
+/// Command line tools that work on level files without starting the game
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+mod script;
+use script::play_script;
+
+use crate::backend::analysis::{LevelAnalysis, analyze_level};
+use crate::backend::generator::{GeneratedLevel, GeneratorConfig, generate_level};
+use crate::backend::grid::{
+    GRID_TEXT_EXTENSION, grid_level_to_string, grid_text, is_grid_level, level_to_grid,
+};
+use crate::backend::io::{level_to_string, read_level, read_level_file, read_level_source};
+use crate::backend::level_file::LevelMeta;
+use crate::backend::migration::{CURRENT_FORMAT_VERSION, migrate_level, needs_migration};
+use crate::backend::minimizer::{MinimizedLevel, minimize_level};
+use crate::backend::notation::{format_move, format_moves, parse_moves};
+use crate::backend::solution::{SOLUTION_EXTENSION, Solution, verify_solution};
+use crate::backend::solver::{SearchLimits, SearchResult, solve};
+use crate::backend::utils_backend::PlayerInput;
+use crate::backend::{Board, GameState};
+use crate::frontend::console_render::{RenderOptions, RenderStyle, render_board};
+
+const DEFAULT_ANALYSIS_MAX_STATES: usize = 500_000;
+const DEFAULT_MINIMIZER_LIMITS: SearchLimits = SearchLimits {
+    max_depth: 50,
+    max_states: 500_000,
+};
+
+const DEFAULT_SOLVER_LIMITS: SearchLimits = SearchLimits {
+    max_depth: 50,
+    max_states: 1_000_000,
+};
+
+/// Exit code for arguments that could not be understood, failures of the command itself use ExitCode::FAILURE
+pub const EXIT_USAGE: u8 = 2;
+/// Exit code for searches that hit their limits before they could decide the level
+pub const EXIT_UNDECIDED: u8 = 3;
+
+pub const USAGE: &str = "Usage: sliding_game_2 <command> [<args>]
+
+Playing:
+  play-gui
+  play-console [--compact] [--ascii] [--no-color] [--load <save>] [<level>]
+      A level is a file or a pack level ID like testing_levels/example0, without one the packs are listed
+  play-script [--script <file>] [--expect won|lost|running] [--json] <level>
+      Plays the moves of the script (or stdin) and reports every step
+
+Editing:
+  edit [--ascii] [--no-color] <level>
+      Edits the level file in the terminal, a file that does not exist yet is created when it is written
+
+Levels:
+  validate [--solvable] [--max-depth <n>] [--max-states <n>] <level>...
+  solve [--max-depth <n>] [--max-states <n>] [--output <solution.toml>] <level>
+  convert [--grid | --blocks] <level> <output>
+  render [--compact] [--ascii] [--no-color] [--moves <moves>] [--output <file>] <level>
+  replay [--compact] [--ascii] [--no-color] [--final] (--moves <moves> | --solution <solution.toml>) <level>
+  verify [<level>] <solution.toml>
+  migrate <level>...
+  list [--tag <tag>] [--sort title|author|par|difficulty] <level>...
+  analyze [--max-states <n>] <level>...
+  minimize <level> <output>
+  generate <config.toml> <output> [<seed>]
+
+Boards are drawn in colour when written to a terminal. --compact draws one character per square,
+--ascii leaves out the box-drawing characters.
+
+Exit codes: 0 success, 1 failure (e.g. an invalid level or a wrong solution), 2 bad arguments,
+3 the solver hit its limits";
+
+pub fn run_tool(args: &[String]) -> ExitCode {
+    match args[0].as_str() {
+        "generate" => generate(&args[1..]),
+        "analyze" => analyze(&args[1..]),
+        "minimize" => minimize(&args[1..]),
+        "verify" => verify(&args[1..]),
+        "convert" => convert(&args[1..]),
+        "migrate" => migrate(&args[1..]),
+        "list" => list(&args[1..]),
+        "validate" => validate(&args[1..]),
+        "solve" => solve_level(&args[1..]),
+        "render" => render(&args[1..]),
+        "replay" => replay(&args[1..]),
+        "play-script" => play_script(&args[1..]),
+        other => {
+            eprintln!("Unknown command: {}\n", other);
+            usage(USAGE)
+        }
+    }
+}
+
+/// Prints the usage of a command and returns the exit code for bad arguments
+pub fn usage(text: &str) -> ExitCode {
+    eprintln!("{}", text);
+    ExitCode::from(EXIT_USAGE)
+}
+
+/// Arguments starting with -- that a command does not know are not taken for level paths
+fn unknown_flag(flag: &str) -> ExitCode {
+    usage(&format!("Unknown flag {}", flag))
+}
+
+/// Reads the value following a flag that takes a number
+fn number_flag(flag: &str, value: Option<&String>) -> Result<usize, ExitCode> {
+    match value.map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) => Ok(n),
+        _ => {
+            eprintln!("{} needs a number", flag);
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+/// Reads the value following a flag that takes text
+fn value_flag<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a String, ExitCode> {
+    match value {
+        Some(value) => Ok(value),
+        None => {
+            eprintln!("{} needs a value", flag);
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+fn load_board(path: &str) -> Result<Board, ExitCode> {
+    match Board::from_file(path) {
+        Ok(b) => Ok(b),
+        Err(e) => {
+            eprintln!("Could not load level {}: {}", path, e);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// generate <config.toml> <output.toml> [seed]
+fn generate(args: &[String]) -> ExitCode {
+    if args.len() < 2 || args.len() > 3 {
+        return usage("Usage: generate <config.toml> <output.toml> [seed]");
+    }
+
+    let config_text: String = match fs::read_to_string(&args[0]) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Could not read generator config {}: {}", args[0], e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut config: GeneratorConfig = match toml::from_str(&config_text) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Invalid generator config {}: {}", args[0], e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(seed) = args.get(2) {
+        match seed.parse::<u64>() {
+            Ok(s) => config.seed = s,
+            Err(_) => {
+                eprintln!("Seed must be a non-negative integer");
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let generated: GeneratedLevel = match generate_level(&config) {
+        Ok(g) => g,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = fs::write(&args[1], level_to_string(&generated.level)) {
+        eprintln!("Could not write {}: {}", args[1], e);
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "Wrote {} after {} attempts, optimal solution has {} moves: {}",
+        args[1],
+        generated.attempts,
+        generated.solution.len(),
+        format_moves(&generated.solution)
+    );
+    ExitCode::SUCCESS
+}
+
+#[derive(Serialize)]
+struct LevelReport<'a> {
+    level: &'a str,
+    #[serde(flatten)]
+    analysis: LevelAnalysis,
+}
+
+/// analyze [--max-states <n>] <level.toml>...
+fn analyze(args: &[String]) -> ExitCode {
+    let mut max_states: usize = DEFAULT_ANALYSIS_MAX_STATES;
+    let mut levels: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--max-states" {
+            match number_flag(arg, iter.next()) {
+                Ok(n) => max_states = n,
+                Err(code) => return code,
+            }
+        } else if arg.starts_with("--") {
+            return unknown_flag(arg);
+        } else {
+            levels.push(arg);
+        }
+    }
+
+    if levels.is_empty() {
+        return usage("Usage: analyze [--max-states <n>] <level.toml>...");
+    }
+
+    let mut reports: Vec<LevelReport> = vec![];
+    for level in levels {
+        match Board::from_file(level) {
+            Ok(board) => reports.push(LevelReport {
+                level,
+                analysis: analyze_level(&board, max_states),
+            }),
+            Err(e) => {
+                eprintln!("Could not load level {}: {}", level, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    match serde_json::to_string_pretty(&reports) {
+        Ok(json) => {
+            println!("{}", json);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Could not serialize analysis: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// minimize <level.toml> <output.toml>
+fn minimize(args: &[String]) -> ExitCode {
+    if args.len() != 2 {
+        return usage("Usage: minimize <level.toml> <output.toml>");
+    }
+
+    let level: toml::Table = match read_level(&args[0]) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Could not load level {}: {}", args[0], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let minimized: MinimizedLevel = match minimize_level(&level, DEFAULT_MINIMIZER_LIMITS) {
+        Ok(m) => m,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = fs::write(&args[1], level_to_string(&minimized.level)) {
+        eprintln!("Could not write {}: {}", args[1], e);
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "Wrote {}: removed {} blocks and {} goals, optimal solution still has {} moves",
+        args[1], minimized.blocks_removed, minimized.goals_removed, minimized.optimal_length
+    );
+    ExitCode::SUCCESS
+}
+
+/// verify [<level.toml>] <solution.toml>
+///
+/// Without a level the one named in the solution file, or else the level next to it, is used
+fn verify(args: &[String]) -> ExitCode {
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--")) {
+        return unknown_flag(flag);
+    }
+    let (level_arg, solution_path): (Option<&String>, &String) = match args {
+        [solution] => (None, solution),
+        [level, solution] => (Some(level), solution),
+        _ => {
+            return usage("Usage: verify [<level.toml>] <solution.toml>");
+        }
+    };
+
+    let solution: Solution = match Solution::from_file(solution_path) {
+        Ok(s) => s,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let level_path: String = match (level_arg, &solution.level) {
+        (Some(level), _) => level.clone(),
+        (None, Some(level)) => Path::new(solution_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(level)
+            .to_string_lossy()
+            .into_owned(),
+        (None, None) => match solution_path.strip_suffix(SOLUTION_EXTENSION) {
+            Some(level) => format!("{}.toml", level),
+            None => {
+                eprintln!(
+                    "{} does not name its level, please pass the level file",
+                    solution_path
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let board: Board = match Board::from_file(&level_path) {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Could not load level {}: {}", level_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match verify_solution(&board, &solution.moves) {
+        Ok(()) => {
+            println!(
+                "{} solves {} in {} moves",
+                solution_path,
+                level_path,
+                solution.moves.len()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{} does not solve {}: {}", solution_path, level_path, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// convert [--grid | --blocks] <level> <output>
+///
+/// Writes the level in the grid layout or with one table per block. Without a flag `.txt` outputs become grid
+/// text files and everything else uses block tables.
+fn convert(args: &[String]) -> ExitCode {
+    let (grid, paths): (bool, &[String]) = match args.first().map(|a| a.as_str()) {
+        Some("--grid") => (true, &args[1..]),
+        Some("--blocks") => (false, &args[1..]),
+        _ => (
+            args.get(1)
+                .is_some_and(|out| out.ends_with(GRID_TEXT_EXTENSION)),
+            args,
+        ),
+    };
+    if let Some(flag) = paths.iter().find(|arg| arg.starts_with("--")) {
+        return unknown_flag(flag);
+    }
+    if paths.len() != 2 {
+        return usage("Usage: convert [--grid | --blocks] <level> <output>");
+    }
+
+    let level: toml::Table = match read_level(&paths[0]) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Could not load level {}: {}", paths[0], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let text: String = if grid {
+        let grid_level: toml::Table = match level_to_grid(&level) {
+            Ok(t) => t,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return ExitCode::FAILURE;
+            }
+        };
+        if paths[1].ends_with(GRID_TEXT_EXTENSION) {
+            grid_text(&grid_level)
+        } else {
+            grid_level_to_string(&grid_level)
+        }
+    } else {
+        level_to_string(&level)
+    };
+
+    if let Err(e) = fs::write(&paths[1], text) {
+        eprintln!("Could not write {}: {}", paths[1], e);
+        return ExitCode::FAILURE;
+    }
+    println!("Wrote {}", paths[1]);
+    ExitCode::SUCCESS
+}
+
+/// migrate <level>...
+///
+/// Upgrades level files written for older format versions in place, keeping their layout.
+/// Comments in the files are not kept.
+fn migrate(args: &[String]) -> ExitCode {
+    if args.is_empty() {
+        return usage("Usage: migrate <level>...");
+    }
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with("--")) {
+        return unknown_flag(flag);
+    }
+
+    let mut failed: bool = false;
+    for path in args {
+        if let Err(msg) = migrate_file(path) {
+            eprintln!("{}: {}", path, msg);
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn migrate_file(path: &str) -> Result<(), String> {
+    let level: toml::Table = match read_level_source(path) {
+        Ok(t) => t,
+        Err(e) => return Err(e.to_string()),
+    };
+    if !needs_migration(&level)? {
+        println!("{} is up to date", path);
+        return Ok(());
+    }
+
+    let migrated: toml::Table = migrate_level(&level)?;
+    let text: String = if path.ends_with(GRID_TEXT_EXTENSION) {
+        grid_text(&migrated)
+    } else if is_grid_level(&migrated) {
+        grid_level_to_string(&migrated)
+    } else {
+        level_to_string(&migrated)
+    };
+
+    if let Err(e) = fs::write(path, text) {
+        return Err(format!("Could not write the file: {}", e));
+    }
+    println!(
+        "Upgraded {} to format version {}",
+        path, CURRENT_FORMAT_VERSION
+    );
+    Ok(())
+}
+
+const LIST_SORT_KEYS: [&str; 4] = ["title", "author", "par", "difficulty"];
+
+/// list [--tag <tag>] [--sort title|author|par|difficulty] <level>...
+///
+/// Prints the metadata of the levels, one per line.
+fn list(args: &[String]) -> ExitCode {
+    let mut tag: Option<&String> = None;
+    let mut sort: Option<&String> = None;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--tag" => match value_flag(arg, iter.next()) {
+                Ok(t) => tag = Some(t),
+                Err(code) => return code,
+            },
+            "--sort" => match value_flag(arg, iter.next()) {
+                Ok(s) if LIST_SORT_KEYS.contains(&s.as_str()) => sort = Some(s),
+                Ok(s) => {
+                    eprintln!("Can not sort by {}", s);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => return unknown_flag(flag),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return usage("Usage: list [--tag <tag>] [--sort title|author|par|difficulty] <level>...");
+    }
+
+    let mut levels: Vec<(&String, LevelMeta)> = vec![];
+    for path in paths {
+        match read_level_file(path) {
+            Ok(level) => levels.push((path, level.meta)),
+            Err(e) => {
+                eprintln!("Could not load level {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    select_levels(
+        &mut levels,
+        tag.map(|t| t.as_str()),
+        sort.map(|s| s.as_str()),
+    );
+
+    for (path, meta) in levels {
+        let mut line: String = String::from(path.as_str());
+        if let Some(title) = &meta.title {
+            line.push_str(&format!("  \"{}\"", title));
+        }
+        if let Some(author) = &meta.author {
+            line.push_str(&format!("  by {}", author));
+        }
+        if let Some(par) = meta.par {
+            line.push_str(&format!("  par {}", par));
+        }
+        if let Some(difficulty) = meta.difficulty {
+            line.push_str(&format!("  difficulty {}", difficulty));
+        }
+        if !meta.tags.is_empty() {
+            line.push_str(&format!("  [{}]", meta.tags.join(", ")));
+        }
+        println!("{}", line);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Keeps the levels with the tag and sorts them by one of LIST_SORT_KEYS, levels without the field go last
+fn select_levels<T>(levels: &mut Vec<(T, LevelMeta)>, tag: Option<&str>, sort: Option<&str>) {
+    if let Some(tag) = tag {
+        levels.retain(|(_, meta)| meta.has_tag(tag));
+    }
+    match sort {
+        Some("title") => levels.sort_by_key(|(_, meta)| (meta.title.is_none(), meta.title.clone())),
+        Some("author") => {
+            levels.sort_by_key(|(_, meta)| (meta.author.is_none(), meta.author.clone()))
+        }
+        Some("par") => levels.sort_by_key(|(_, meta)| (meta.par.is_none(), meta.par)),
+        Some("difficulty") => levels.sort_by(|(_, a), (_, b)| {
+            let key =
+                |meta: &LevelMeta| (meta.difficulty.is_none(), meta.difficulty.unwrap_or(0.0));
+            key(a).0.cmp(&key(b).0).then(key(a).1.total_cmp(&key(b).1))
+        }),
+        _ => (),
+    }
+}
+
+/// validate [--solvable] [--max-depth <n>] [--max-states <n>] <level>...
+///
+/// Reports every problem of every level. With --solvable the levels must also have a solution.
+fn validate(args: &[String]) -> ExitCode {
+    let mut solvable: bool = false;
+    let mut limits: SearchLimits = DEFAULT_SOLVER_LIMITS;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--solvable" => solvable = true,
+            "--max-depth" => match number_flag(arg, iter.next()) {
+                Ok(n) => limits.max_depth = n,
+                Err(code) => return code,
+            },
+            "--max-states" => match number_flag(arg, iter.next()) {
+                Ok(n) => limits.max_states = n,
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => return unknown_flag(flag),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return usage(
+            "Usage: validate [--solvable] [--max-depth <n>] [--max-states <n>] <level>...",
+        );
+    }
+
+    let mut failed: bool = false;
+    let mut undecided: bool = false;
+    for path in paths {
+        let board: Board = match Board::from_file(path) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("{}: {}", path, e.to_string().trim_end());
+                failed = true;
+                continue;
+            }
+        };
+        if !solvable {
+            println!("{}: ok", path);
+            continue;
+        }
+        match solve(&board, limits) {
+            SearchResult::Solved(moves) => {
+                println!("{}: ok, solvable in {} moves", path, moves.len())
+            }
+            SearchResult::Unsolvable => {
+                println!("{}: can not be solved", path);
+                failed = true;
+            }
+            SearchResult::LimitReached => {
+                println!("{}: valid, but the solver hit its limits", path);
+                undecided = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else if undecided {
+        ExitCode::from(EXIT_UNDECIDED)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// solve [--max-depth <n>] [--max-states <n>] [--output <solution.toml>] <level>
+///
+/// Prints a shortest solution and optionally writes it to a solution file.
+fn solve_level(args: &[String]) -> ExitCode {
+    let mut limits: SearchLimits = DEFAULT_SOLVER_LIMITS;
+    let mut output: Option<&String> = None;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--max-depth" => match number_flag(arg, iter.next()) {
+                Ok(n) => limits.max_depth = n,
+                Err(code) => return code,
+            },
+            "--max-states" => match number_flag(arg, iter.next()) {
+                Ok(n) => limits.max_states = n,
+                Err(code) => return code,
+            },
+            "--output" => match value_flag(arg, iter.next()) {
+                Ok(o) => output = Some(o),
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => return unknown_flag(flag),
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return usage(
+            "Usage: solve [--max-depth <n>] [--max-states <n>] [--output <solution.toml>] <level>",
+        );
+    };
+
+    let board: Board = match load_board(path) {
+        Ok(b) => b,
+        Err(code) => return code,
+    };
+    let moves: Vec<PlayerInput> = match solve(&board, limits) {
+        SearchResult::Solved(moves) => moves,
+        SearchResult::Unsolvable => {
+            eprintln!("{} can not be solved", path);
+            return ExitCode::FAILURE;
+        }
+        SearchResult::LimitReached => {
+            eprintln!(
+                "No solution found within {} moves and {} positions",
+                limits.max_depth, limits.max_states
+            );
+            return ExitCode::from(EXIT_UNDECIDED);
+        }
+    };
+    println!("{}", format_moves(&moves));
+
+    if let Some(output) = output {
+        // The level is named relative to the solution file, which is only simple when they share a directory
+        let level: Option<String> = if Path::new(path).parent() == Path::new(output).parent() {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+        let solution: Solution = Solution {
+            level,
+            optimal: Some(true),
+            moves,
+            ..Solution::default()
+        };
+        if let Err(msg) = solution.write_file(output) {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Reads the moves following --moves, there has to be at least one
+fn parse_move_arg(flag: &str, moves: Option<&String>) -> Result<Vec<PlayerInput>, ExitCode> {
+    match parse_moves(value_flag(flag, moves)?) {
+        Ok(m) if m.is_empty() => {
+            eprintln!("{} needs at least one move", flag);
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+        Ok(m) => Ok(m),
+        Err(e) => {
+            eprintln!("Could not read the moves, {}", e);
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+/// Options for drawing boards to stdout, colour is left out when stdout is not a terminal
+pub fn default_render_options() -> RenderOptions {
+    RenderOptions {
+        color: io::stdout().is_terminal(),
+        ..RenderOptions::default()
+    }
+}
+
+/// Changes the options for --compact, --ascii and --no-color, returns false for other arguments
+pub fn render_flag(arg: &str, options: &mut RenderOptions) -> bool {
+    match arg {
+        "--compact" => options.style = RenderStyle::Compact,
+        "--ascii" => options.ascii = true,
+        "--no-color" => options.color = false,
+        _ => return false,
+    }
+    true
+}
+
+/// Applies the inputs one after another, stopping at the first one that can not be made. after_step is
+/// called with the number of the step and the board after it
+fn apply_moves(
+    board: &mut Board,
+    moves: &[PlayerInput],
+    mut after_step: impl FnMut(usize, PlayerInput, &Board),
+) -> Result<(), String> {
+    for (index, input) in moves.iter().enumerate() {
+        if let Err(reason) = board.check_input(*input) {
+            return Err(format!(
+                "Step {} ({}) is illegal: {}",
+                index + 1,
+                format_move(*input),
+                reason
+            ));
+        }
+        board.apply_input(*input);
+        after_step(index + 1, *input, board);
+    }
+    Ok(())
+}
+
+/// render [--compact] [--ascii] [--no-color] [--moves <moves>] [--output <file>] <level>
+///
+/// Draws the level as text, after the moves if given. Files are written without colour.
+fn render(args: &[String]) -> ExitCode {
+    let mut options: RenderOptions = default_render_options();
+    let mut moves: Vec<PlayerInput> = vec![];
+    let mut output: Option<&String> = None;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            flag if render_flag(flag, &mut options) => (),
+            "--moves" => match parse_move_arg(arg, iter.next()) {
+                Ok(m) => moves = m,
+                Err(code) => return code,
+            },
+            "--output" => match value_flag(arg, iter.next()) {
+                Ok(o) => output = Some(o),
+                Err(code) => return code,
+            },
+            flag if flag.starts_with("--") => return unknown_flag(flag),
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return usage(
+            "Usage: render [--compact] [--ascii] [--no-color] [--moves <moves>] [--output <file>] <level>",
+        );
+    };
+
+    let mut board: Board = match load_board(path) {
+        Ok(b) => b,
+        Err(code) => return code,
+    };
+    if let Err(msg) = apply_moves(&mut board, &moves, |_, _, _| ()) {
+        eprintln!("{}", msg);
+        return ExitCode::FAILURE;
+    }
+
+    if output.is_some() {
+        options.color = false;
+    }
+    let text: String = render_board(&board, options);
+    match output {
+        None => print!("{}", text),
+        Some(output) => {
+            if let Err(e) = fs::write(output, text) {
+                eprintln!("Could not write {}: {}", output, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// replay [--compact] [--ascii] [--no-color] [--final] (--moves <moves> | --solution <solution.toml>) <level>
+///
+/// Plays the moves and draws the board after every step, or only at the end with --final. Succeeds if
+/// the moves win the level.
+fn replay(args: &[String]) -> ExitCode {
+    let mut options: RenderOptions = default_render_options();
+    let mut only_final: bool = false;
+    let mut moves: Option<Vec<PlayerInput>> = None;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            flag if render_flag(flag, &mut options) => (),
+            "--final" => only_final = true,
+            "--moves" => match parse_move_arg(arg, iter.next()) {
+                Ok(m) => moves = Some(m),
+                Err(code) => return code,
+            },
+            "--solution" => match iter.next().map(|path| Solution::from_file(path)) {
+                Some(Ok(solution)) => moves = Some(solution.moves),
+                Some(Err(msg)) => {
+                    eprintln!("{}", msg);
+                    return ExitCode::FAILURE;
+                }
+                None => return usage("--solution needs a file"),
+            },
+            flag if flag.starts_with("--") => return unknown_flag(flag),
+            _ => paths.push(arg),
+        }
+    }
+    let ([path], Some(moves)) = (&paths[..], moves) else {
+        return usage(
+            "Usage: replay [--compact] [--ascii] [--no-color] [--final] (--moves <moves> | --solution <solution.toml>) <level>",
+        );
+    };
+
+    let mut board: Board = match load_board(path) {
+        Ok(b) => b,
+        Err(code) => return code,
+    };
+    if !only_final {
+        print!("Start\n{}", render_board(&board, options));
+    }
+    let played = apply_moves(&mut board, &moves, |step, input, board| {
+        if !only_final {
+            print!(
+                "\nStep {}: {}\n{}",
+                step,
+                format_move(input),
+                render_board(board, options)
+            );
+        }
+    });
+    if let Err(msg) = played {
+        eprintln!("{}", msg);
+        return ExitCode::FAILURE;
+    }
+    if only_final {
+        print!("{}", render_board(&board, options));
+    }
+
+    match board.get_game_state() {
+        GameState::Won => {
+            println!("Won after {} moves", moves.len());
+            ExitCode::SUCCESS
+        }
+        GameState::Lost => {
+            println!("Lost after {} moves", moves.len());
+            ExitCode::FAILURE
+        }
+        GameState::Running => {
+            println!("Not won after {} moves", moves.len());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn meta(title: Option<&str>, par: Option<usize>, tags: &[&str]) -> LevelMeta {
+        LevelMeta {
+            title: title.map(String::from),
+            par,
+            tags: tags.iter().map(|t| String::from(*t)).collect(),
+            ..LevelMeta::default()
+        }
+    }
+
+    #[test]
+    fn list_filters_by_tag_and_sorts_missing_fields_last() {
+        let levels: Vec<(&str, LevelMeta)> = vec![
+            ("a", meta(Some("Zigzag"), None, &["easy"])),
+            ("b", meta(None, Some(3), &["easy", "short"])),
+            ("c", meta(Some("Arch"), Some(12), &[])),
+            ("d", meta(Some("Bridge"), Some(5), &["short"])),
+        ];
+        let order = |tag: Option<&str>, sort: Option<&str>| -> Vec<&str> {
+            let mut selected: Vec<(&str, LevelMeta)> = levels.clone();
+            select_levels(&mut selected, tag, sort);
+            selected.into_iter().map(|(name, _)| name).collect()
+        };
+
+        assert_eq!(order(None, None), vec!["a", "b", "c", "d"]);
+        assert_eq!(order(Some("short"), None), vec!["b", "d"]);
+        assert_eq!(order(None, Some("title")), vec!["c", "d", "a", "b"]);
+        assert_eq!(order(None, Some("par")), vec!["b", "d", "c", "a"]);
+        assert_eq!(order(Some("easy"), Some("par")), vec!["b", "a"]);
+        assert_eq!(order(Some("hard"), Some("title")), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn flags_without_values_are_usage_errors() {
+        let level: &str = "levels/testing_levels/example0.toml";
+        let usage_error: ExitCode = ExitCode::from(EXIT_USAGE);
+        assert_eq!(
+            solve_level(&args(&format!("{} --output", level))),
+            usage_error
+        );
+        assert_eq!(
+            solve_level(&args(&format!("--max-depth x {}", level))),
+            usage_error
+        );
+        assert_eq!(render(&args(&format!("{} --moves", level))), usage_error);
+        assert_eq!(render(&args(&format!("{} --output", level))), usage_error);
+        assert_eq!(replay(&args(&format!("{} --moves", level))), usage_error);
+        assert_eq!(replay(&args(level)), usage_error);
+        assert_eq!(
+            validate(&args(&format!("--solveable {}", level))),
+            usage_error
+        );
+        assert_eq!(analyze(&args(&format!("{} --max", level))), usage_error);
+        assert_eq!(list(&args(&format!("--tags easy {}", level))), usage_error);
+        assert_eq!(render(&args(&format!("--colour {}", level))), usage_error);
+        assert_eq!(migrate(&args(&format!("{} --dry-run", level))), usage_error);
+
+        // An empty or unreadable list of moves is not a replay of nothing
+        let with_moves = |command: fn(&[String]) -> ExitCode, moves: &str| {
+            command(&[
+                String::from("--moves"),
+                String::from(moves),
+                String::from(level),
+            ])
+        };
+        assert_eq!(with_moves(render, ""), usage_error);
+        assert_eq!(with_moves(replay, " "), usage_error);
+        assert_eq!(with_moves(render, "0X"), usage_error);
+    }
+
+    #[test]
+    fn replays_succeed_only_when_the_moves_win() {
+        let level: &str = "levels/testing_levels/example1.toml";
+        let solution: &str = "levels/testing_levels/example1.solution.toml";
+        let run = |command: fn(&[String]) -> ExitCode, extra: &[&str]| {
+            let mut arguments: Vec<String> =
+                vec![String::from("--no-color"), String::from("--final")];
+            arguments.extend(extra.iter().map(|a| String::from(*a)));
+            arguments.push(String::from(level));
+            command(&arguments)
+        };
+
+        assert_eq!(run(replay, &["--solution", solution]), ExitCode::SUCCESS);
+        assert_eq!(run(replay, &["--moves", "0R"]), ExitCode::FAILURE);
+        assert_eq!(
+            run(replay, &["--solution", "missing.solution.toml"]),
+            ExitCode::FAILURE
+        );
+
+        let mut board: Board = Board::from_file(level).unwrap();
+        let mut steps: Vec<usize> = vec![];
+        let moves: Vec<PlayerInput> = parse_moves("0R 0r 5R").unwrap();
+        let played = apply_moves(&mut board, &moves, |step, _, _| steps.push(step));
+        assert!(played.unwrap_err().starts_with("Step 3 (5R) is illegal"));
+        assert_eq!(steps, vec![1, 2]);
+        assert_eq!(board.move_count(), 2);
+    }
+
+    #[test]
+    fn list_flags_need_values() {
+        let usage_error: ExitCode = ExitCode::from(EXIT_USAGE);
+        assert_eq!(
+            list(&args("levels/testing_levels/example0.toml --tag")),
+            usage_error
+        );
+        assert_eq!(
+            list(&args("levels/testing_levels/example0.toml --sort")),
+            usage_error
+        );
+        assert_eq!(
+            list(&args("--sort moves levels/testing_levels/example0.toml")),
+            usage_error
+        );
+        assert_eq!(list(&args("--tag easy")), usage_error);
+        assert_eq!(
+            list(&args(
+                "--tag easy --sort par levels/testing_levels/example0.toml"
+            )),
+            ExitCode::SUCCESS
+        );
+    }
+}
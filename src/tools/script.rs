@@ -0,0 +1,204 @@
+/// Playing a level from a move script without a human at the keyboard
+///
+/// A script holds moves in the compact notation, any number per line. Everything after a `#` on a line is
+/// a comment. The moves are played in order until one of them is illegal, then the final position is
+/// compared with the expected game state.
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use super::{EXIT_USAGE, load_board, usage};
+use crate::backend::notation::{format_move, parse_moves};
+use crate::backend::savegame::{game_state_from_name, game_state_name};
+use crate::backend::utils_backend::PlayerInput;
+use crate::backend::{Board, GameState};
+use crate::frontend::console_render::{RenderOptions, render_board};
+
+#[derive(Serialize)]
+struct StepReport {
+    /// Counted from 1
+    step: usize,
+    #[serde(rename = "move")]
+    input: String,
+    /// State after the move, or before it if the move was illegal
+    game_state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ScriptReport<'a> {
+    level: &'a str,
+    steps: Vec<StepReport>,
+    game_state: &'static str,
+    expected_state: &'static str,
+    success: bool,
+    board: String,
+}
+
+/// Reads the moves of a script, errors name the line and column of the bad move
+fn parse_script(text: &str) -> Result<Vec<PlayerInput>, String> {
+    let mut ret: Vec<PlayerInput> = vec![];
+
+    for (number, line) in text.lines().enumerate() {
+        let code: &str = line.split('#').next().unwrap_or("");
+        match parse_moves(code) {
+            Ok(moves) => ret.extend(moves),
+            Err(e) => return Err(format!("line {}, {}", number + 1, e)),
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Plays the moves until one is illegal, returning the outcome of every move that was tried
+fn run_script(board: &mut Board, moves: &[PlayerInput]) -> Vec<StepReport> {
+    let mut ret: Vec<StepReport> = vec![];
+
+    for (index, input) in moves.iter().enumerate() {
+        let error: Option<String> = board.check_input(*input).err();
+        if error.is_none() {
+            board.apply_input(*input);
+        }
+        let illegal: bool = error.is_some();
+
+        ret.push(StepReport {
+            step: index + 1,
+            input: format_move(*input),
+            game_state: game_state_name(board.get_game_state()),
+            error,
+        });
+        if illegal {
+            break;
+        }
+    }
+
+    ret
+}
+
+/// play-script [--script <file>] [--expect won|lost|running] [--json] <level>
+///
+/// Reads the script from stdin when no file (or -) is given. Fails if a move is illegal or the game does not
+/// end in the expected state, which is won by default.
+pub(super) fn play_script(args: &[String]) -> ExitCode {
+    let mut script_path: Option<&String> = None;
+    let mut expected: GameState = GameState::Won;
+    let mut json: bool = false;
+    let mut paths: Vec<&String> = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--script" => match iter.next() {
+                Some(path) => script_path = Some(path),
+                None => return usage("--script needs a file"),
+            },
+            "--expect" => match iter.next().map(|name| game_state_from_name(name)) {
+                Some(Ok(state)) => expected = state,
+                _ => return usage("--expect needs one of won, lost or running"),
+            },
+            "--json" => json = true,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return usage(
+            "Usage: play-script [--script <file>] [--expect won|lost|running] [--json] <level>",
+        );
+    };
+
+    let text: String = match script_path.map(|p| p.as_str()) {
+        None | Some("-") => {
+            let mut text: String = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut text) {
+                eprintln!("Could not read the script from stdin: {}", e);
+                return ExitCode::FAILURE;
+            }
+            text
+        }
+        Some(script_path) => match fs::read_to_string(script_path) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", script_path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let moves: Vec<PlayerInput> = match parse_script(&text) {
+        Ok(m) => m,
+        Err(msg) => {
+            eprintln!("Could not read the script, {}", msg);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let mut board: Board = match load_board(path) {
+        Ok(b) => b,
+        Err(code) => return code,
+    };
+    let steps: Vec<StepReport> = run_script(&mut board, &moves);
+
+    let all_legal: bool = steps.iter().all(|step| step.error.is_none());
+    let report: ScriptReport = ScriptReport {
+        level: path,
+        game_state: game_state_name(board.get_game_state()),
+        expected_state: game_state_name(expected),
+        success: all_legal && board.get_game_state() == expected,
+        board: render_board(&board, RenderOptions::plain()),
+        steps,
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("Could not serialize the report: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        for step in &report.steps {
+            match &step.error {
+                None => println!("{:>4}. {:<4} {}", step.step, step.input, step.game_state),
+                Some(error) => println!("{:>4}. {:<4} illegal: {}", step.step, step.input, error),
+            }
+        }
+        print!("{}", report.board);
+        println!(
+            "Game state: {} (expected {})",
+            report.game_state, report.expected_state
+        );
+    }
+
+    if report.success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn scripts_stop_at_the_first_illegal_move() {
+        assert!(parse_script("0r 1R # walk, then slide\n\n1d").is_ok_and(|moves| moves.len() == 3));
+        assert_eq!(
+            parse_script("0r\n0x").unwrap_err(),
+            "line 2, '0x' at column 1: unknown direction 'x', use u, d, l, r to walk, U, D, L, R to slide or n, N to stay"
+        );
+
+        let level =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("levels/testing_levels/example0.toml");
+        let mut board: Board = Board::from_file(level.to_str().unwrap()).unwrap();
+        let steps: Vec<StepReport> = run_script(&mut board, &parse_script("1U 0r 0d").unwrap());
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].game_state, "running");
+        assert!(steps[1].error.is_some());
+    }
+}